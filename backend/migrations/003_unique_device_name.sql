@@ -0,0 +1,14 @@
+-- 设备名称唯一（控制台为单租户，名称在全部设备范围内唯一）
+
+-- 已有同名设备时保留最早创建的一台，其余改名为 "名称 (device_id)"，否则无法建立唯一索引
+UPDATE devices d
+SET name = LEFT(d.name, 188) || ' (' || d.device_id || ')'
+FROM (
+    SELECT device_id, ROW_NUMBER() OVER (PARTITION BY name ORDER BY created_at, device_id) AS rn
+    FROM devices
+) ranked
+WHERE d.device_id = ranked.device_id AND ranked.rn > 1;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_devices_name_unique ON devices(name);
+
+COMMENT ON INDEX idx_devices_name_unique IS '设备名称唯一约束，避免界面上出现同名设备';
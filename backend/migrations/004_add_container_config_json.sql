@@ -0,0 +1,4 @@
+-- 保存部署时的结构化配置，用于在 config.toml 丢失或损坏时重新生成
+ALTER TABLE containers ADD COLUMN IF NOT EXISTS config_json TEXT;
+
+COMMENT ON COLUMN containers.config_json IS '部署时的 EchoKit 结构化配置（JSON），外部服务器为 NULL';
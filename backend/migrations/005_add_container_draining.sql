@@ -0,0 +1,4 @@
+-- 容器排空标记：升级前停止接受新的设备连接，已有连接继续转发
+ALTER TABLE containers ADD COLUMN IF NOT EXISTS draining BOOLEAN NOT NULL DEFAULT false;
+
+COMMENT ON COLUMN containers.draining IS '是否处于排空模式（Proxy 拒绝新的设备连接）';
@@ -0,0 +1,5 @@
+-- 容器运行状态，由 Backend 在部署/启动/停止时维护，Proxy 转发前检查
+-- 外部服务器默认视为 running
+ALTER TABLE containers ADD COLUMN IF NOT EXISTS status VARCHAR(20) NOT NULL DEFAULT 'running';
+
+COMMENT ON COLUMN containers.status IS '容器状态：running, stopped, error, creating, starting';
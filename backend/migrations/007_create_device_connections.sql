@@ -0,0 +1,26 @@
+-- 设备连接日志（由 Proxy 写入，每次 WebSocket 连接一条记录）
+CREATE TABLE IF NOT EXISTS device_connections (
+    id BIGSERIAL PRIMARY KEY,
+
+    -- 设备与目标服务器
+    device_id VARCHAR(64) NOT NULL,
+    container_id VARCHAR(64),
+
+    -- 来源地址（按可信代理层数解析 X-Forwarded-For 后的客户端 IP）
+    client_ip VARCHAR(64),
+
+    -- 时间戳（Unix 秒级时间戳）
+    connected_at BIGINT NOT NULL,
+    disconnected_at BIGINT
+);
+
+-- 索引
+CREATE INDEX IF NOT EXISTS idx_device_connections_device ON device_connections(device_id, connected_at DESC);
+
+-- 注释
+COMMENT ON TABLE device_connections IS '设备连接日志';
+COMMENT ON COLUMN device_connections.device_id IS '设备唯一标识符（MAC 地址）';
+COMMENT ON COLUMN device_connections.container_id IS '转发的目标容器 ID';
+COMMENT ON COLUMN device_connections.client_ip IS '设备来源 IP';
+COMMENT ON COLUMN device_connections.connected_at IS '连接时间（Unix 时间戳）';
+COMMENT ON COLUMN device_connections.disconnected_at IS '断开时间（Unix 时间戳）';
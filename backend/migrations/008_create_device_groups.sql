@@ -0,0 +1,25 @@
+-- 设备分组：一组设备可以作为整体绑定到同一个服务器
+CREATE TABLE IF NOT EXISTS device_groups (
+    id BIGSERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL UNIQUE,
+
+    -- 时间戳（Unix 秒级时间戳）
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT
+);
+
+CREATE TABLE IF NOT EXISTS device_group_members (
+    group_id BIGINT NOT NULL REFERENCES device_groups(id) ON DELETE CASCADE,
+    device_id VARCHAR(64) NOT NULL REFERENCES devices(device_id) ON DELETE CASCADE,
+    PRIMARY KEY (group_id, device_id)
+);
+
+-- 索引
+CREATE INDEX IF NOT EXISTS idx_device_group_members_device ON device_group_members(device_id);
+
+-- 注释
+COMMENT ON TABLE device_groups IS '设备分组';
+COMMENT ON COLUMN device_groups.name IS '分组名称';
+COMMENT ON COLUMN device_groups.created_at IS '创建时间（Unix 时间戳）';
+COMMENT ON COLUMN device_groups.updated_at IS '最后更新时间（Unix 时间戳）';
+COMMENT ON TABLE device_group_members IS '设备分组成员';
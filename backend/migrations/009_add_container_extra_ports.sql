@@ -0,0 +1,4 @@
+-- 容器额外暴露的端口映射（JSON 数组：[{"containerPort": 9090, "hostPort": 8081}]）
+ALTER TABLE containers ADD COLUMN IF NOT EXISTS extra_ports_json TEXT;
+
+COMMENT ON COLUMN containers.extra_ports_json IS '额外端口映射（主端口 8080 之外，JSON 格式）';
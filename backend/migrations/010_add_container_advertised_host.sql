@@ -0,0 +1,4 @@
+-- 容器对外展示的主机名（租户子域名），仅用于生成展示给用户的 WebSocket 地址
+ALTER TABLE containers ADD COLUMN IF NOT EXISTS advertised_host VARCHAR(255);
+
+COMMENT ON COLUMN containers.advertised_host IS '对外展示的主机名（可选，覆盖 EXTERNAL_HOST）';
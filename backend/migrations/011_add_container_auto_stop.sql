@@ -0,0 +1,8 @@
+-- 容器空闲自动停止：无设备连接超过阈值后由后端停止，设备再次连接时由 Proxy 请求唤醒
+ALTER TABLE containers ADD COLUMN IF NOT EXISTS auto_stop_enabled BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE containers ADD COLUMN IF NOT EXISTS last_active_at BIGINT;
+ALTER TABLE containers ADD COLUMN IF NOT EXISTS wake_requested_at BIGINT;
+
+COMMENT ON COLUMN containers.auto_stop_enabled IS '是否在空闲后自动停止';
+COMMENT ON COLUMN containers.last_active_at IS '最近一次观察到设备连接或启动的时间（Unix 时间戳）';
+COMMENT ON COLUMN containers.wake_requested_at IS 'Proxy 请求唤醒已停止容器的时间（Unix 时间戳，处理后清空）';
@@ -0,0 +1,10 @@
+-- 连接日志中的转发统计（由 Proxy 在连接结束时写入，便于排查音频损坏等问题）
+ALTER TABLE device_connections ADD COLUMN IF NOT EXISTS frames_to_server BIGINT;
+ALTER TABLE device_connections ADD COLUMN IF NOT EXISTS frames_to_device BIGINT;
+ALTER TABLE device_connections ADD COLUMN IF NOT EXISTS forward_errors BIGINT;
+ALTER TABLE device_connections ADD COLUMN IF NOT EXISTS close_reason VARCHAR(16);
+
+COMMENT ON COLUMN device_connections.frames_to_server IS '设备->服务器转发的数据帧数（文本+二进制）';
+COMMENT ON COLUMN device_connections.frames_to_device IS '服务器->设备转发的数据帧数（文本+二进制）';
+COMMENT ON COLUMN device_connections.forward_errors IS '转发过程中的读写错误数';
+COMMENT ON COLUMN device_connections.close_reason IS '结束方式：device / server（正常关闭）或 error（异常中断）';
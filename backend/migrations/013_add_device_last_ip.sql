@@ -0,0 +1,6 @@
+-- 设备最近一次连接的网络信息（由 Proxy 在设备连接时写入，便于排查问题）
+ALTER TABLE devices ADD COLUMN IF NOT EXISTS last_ip VARCHAR(64);
+ALTER TABLE devices ADD COLUMN IF NOT EXISTS last_user_agent VARCHAR(256);
+
+COMMENT ON COLUMN devices.last_ip IS '最近一次连接的来源 IP（按可信代理层数解析 X-Forwarded-For）';
+COMMENT ON COLUMN devices.last_user_agent IS '最近一次连接上报的 User-Agent 或固件版本标识';
@@ -0,0 +1,4 @@
+-- 容器描述（用途、租户等自由文本，部署时可指定，之后可编辑）
+ALTER TABLE containers ADD COLUMN IF NOT EXISTS description TEXT;
+
+COMMENT ON COLUMN containers.description IS '容器描述（用途、租户等），为空表示未填写';
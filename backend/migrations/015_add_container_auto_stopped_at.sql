@@ -0,0 +1,4 @@
+-- 记录容器是否由空闲自动停止而停止：只有这类容器会在设备连接时被唤醒，手动停止的容器保持停止
+ALTER TABLE containers ADD COLUMN IF NOT EXISTS auto_stopped_at BIGINT;
+
+COMMENT ON COLUMN containers.auto_stopped_at IS '空闲自动停止的时间（Unix 时间戳），手动停止或启动时清空';
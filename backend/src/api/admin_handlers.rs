@@ -0,0 +1,130 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use std::collections::HashMap;
+use tracing::error;
+
+use super::router::AppState;
+use crate::models::{AdminOverview, ApiError, ContainerInfo};
+
+/// 按状态统计容器数量
+fn count_by_status(containers: &[ContainerInfo]) -> HashMap<String, usize> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for container in containers {
+        *counts
+            .entry(container.status.as_str().to_string())
+            .or_default() += 1;
+    }
+    counts
+}
+
+/// 管理概览：容器/设备状态分布、Proxy 活跃连接数与端口使用情况
+pub async fn admin_overview(State(state): State<AppState>) -> impl IntoResponse {
+    let containers = match state.docker_manager.list_containers().await {
+        Ok(containers) => containers,
+        Err(e) => {
+            let error_chain = format!("{:#}", e);
+            error!("Failed to list containers for overview: {}", error_chain);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    serde_json::to_value(ApiError {
+                        error: "overview_failed".to_string(),
+                        message: error_chain,
+                    })
+                    .unwrap(),
+                ),
+            );
+        }
+    };
+
+    let device_counts = match state.device_store.count_by_status().await {
+        Ok(counts) => counts,
+        Err(e) => {
+            let error_chain = format!("{:#}", e);
+            error!("Failed to count devices for overview: {}", error_chain);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    serde_json::to_value(ApiError {
+                        error: "overview_failed".to_string(),
+                        message: error_chain,
+                    })
+                    .unwrap(),
+                ),
+            );
+        }
+    };
+
+    let total_devices = device_counts.iter().map(|(_, count)| count).sum();
+    let devices_by_status: HashMap<String, i64> = device_counts.into_iter().collect();
+
+    let overview = AdminOverview {
+        total_containers: containers.len(),
+        containers_by_status: count_by_status(&containers),
+        total_devices,
+        devices_by_status,
+        active_proxy_connections: state.docker_manager.fetch_proxy_active_connections().await,
+        port_utilization: state.docker_manager.port_utilization(&containers).await,
+    };
+
+    (
+        StatusCode::OK,
+        Json(serde_json::to_value(overview).unwrap()),
+    )
+}
+
+/// 端口范围使用详情与下一个将被分配的端口（只读，不预留端口）
+pub async fn admin_ports(State(state): State<AppState>) -> impl IntoResponse {
+    match state.docker_manager.ports_report().await {
+        Ok(report) => (StatusCode::OK, Json(serde_json::to_value(report).unwrap())),
+        Err(e) => {
+            let error_chain = format!("{:#}", e);
+            error!("Failed to build port report: {}", error_chain);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    serde_json::to_value(ApiError {
+                        error: "ports_failed".to_string(),
+                        message: error_chain,
+                    })
+                    .unwrap(),
+                ),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ContainerStatus;
+    use chrono::Utc;
+
+    fn container(id: &str, status: ContainerStatus) -> ContainerInfo {
+        ContainerInfo {
+            id: id.to_string(),
+            name: format!("echokit-server-{}", id),
+            port: 10000,
+            ws_url: "ws://localhost:10000/ws".to_string(),
+            status,
+            created_at: Utc::now(),
+            health: None,
+            active_connections: None,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn counts_containers_by_status() {
+        let containers = vec![
+            container("a", ContainerStatus::Running),
+            container("b", ContainerStatus::Running),
+            container("c", ContainerStatus::Stopped),
+        ];
+
+        let counts = count_by_status(&containers);
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts["running"], 2);
+        assert_eq!(counts["stopped"], 1);
+        assert!(count_by_status(&[]).is_empty());
+    }
+}
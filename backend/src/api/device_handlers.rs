@@ -1,19 +1,51 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     Json,
 };
+use serde::Deserialize;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
+use super::handlers::too_many_requests;
+use crate::docker::{DockerManager, ProxyRateLimited};
 use crate::models::{
-    ApiError, BindServerRequest, Device, DeviceStatus, RegisterDeviceRequest,
+    normalize_device_id, parse_mac_address, ApiError, BindServerRequest, BindServerResponse,
+    ConnectionEvent, ConnectionLogFilter, ContainerInfo, DeviceConnectionParams, Device,
+    DeviceRouteTest, DeviceStatus, DeviceWsTarget, HealthStatus, ImportDeviceEntry,
+    ImportDeviceOutcome, ImportDeviceResult, RegisterDeviceRequest,
 };
 use crate::store::PgDeviceStore;
 
 pub type DeviceStoreState = Arc<PgDeviceStore>;
 
+/// 检查目标服务器健康状态，不健康或无法确认时返回警告信息
+pub(crate) async fn check_target_health(
+    manager: &DockerManager,
+    container_id: &str,
+) -> Option<String> {
+    match manager.get_container(container_id).await {
+        Ok(container) => target_health_warning(&container),
+        Err(_) => Some("Target server health could not be verified".to_string()),
+    }
+}
+
+/// 根据容器详情中的健康检查结果生成警告，健康时返回 None
+fn target_health_warning(container: &ContainerInfo) -> Option<String> {
+    match &container.health {
+        Some(health) if health.status == HealthStatus::Healthy => None,
+        Some(health) => Some(format!(
+            "Target server is unhealthy: {}",
+            health.error_message.as_deref().unwrap_or("unknown reason")
+        )),
+        None => Some(format!(
+            "Target server is not running (status: {})",
+            container.status.as_str()
+        )),
+    }
+}
+
 /// 获取设备列表
 pub async fn list_devices(State(store): State<DeviceStoreState>) -> impl IntoResponse {
     info!("获取设备列表");
@@ -33,11 +65,38 @@ pub async fn list_devices(State(store): State<DeviceStoreState>) -> impl IntoRes
     }
 }
 
+/// 获取绑定到指定容器的设备列表
+pub async fn list_container_devices(
+    State(store): State<DeviceStoreState>,
+    Path(container_id): Path<String>,
+) -> impl IntoResponse {
+    info!("获取容器绑定的设备列表: {}", container_id);
+
+    match store.list_by_container(&container_id).await {
+        Ok(devices) => {
+            info!("容器 {} 绑定了 {} 个设备", container_id, devices.len());
+            (StatusCode::OK, Json(devices)).into_response()
+        }
+        Err(e) => {
+            error!("获取容器绑定的设备列表失败: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError {
+                    error: "InternalError".to_string(),
+                    message: "Failed to fetch devices for container".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
 /// 获取单个设备
 pub async fn get_device(
     State(store): State<DeviceStoreState>,
     Path(device_id): Path<String>,
 ) -> impl IntoResponse {
+    let device_id = normalize_device_id(&device_id);
     info!("获取设备: {}", device_id);
 
     match store.get(&device_id).await {
@@ -73,8 +132,10 @@ pub async fn get_device(
 /// 注册新设备
 pub async fn register_device(
     State(store): State<DeviceStoreState>,
-    Json(request): Json<RegisterDeviceRequest>,
+    Json(mut request): Json<RegisterDeviceRequest>,
 ) -> impl IntoResponse {
+    request.device_id = normalize_device_id(&request.device_id);
+    request.mac_address = normalize_device_id(&request.mac_address);
     info!("注册新设备: {} ({})", request.name, request.mac_address);
 
     // 检查设备是否已存在
@@ -90,6 +151,33 @@ pub async fn register_device(
             .into_response();
     }
 
+    // 检查设备名称是否重复
+    match store.name_exists(&request.name).await {
+        Ok(true) => {
+            info!("设备名称已被使用: {}", request.name);
+            return (
+                StatusCode::CONFLICT,
+                Json(ApiError {
+                    error: "NameConflict".to_string(),
+                    message: format!("设备名称 {} 已被使用，请换一个名称", request.name),
+                }),
+            )
+                .into_response();
+        }
+        Ok(false) => {}
+        Err(e) => {
+            error!("检查设备名称失败: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError {
+                    error: "InternalError".to_string(),
+                    message: "Failed to register device".to_string(),
+                }),
+            )
+                .into_response();
+        }
+    }
+
     let now = chrono::Utc::now().timestamp();
     let device = Device {
         device_id: request.device_id.clone(),
@@ -98,6 +186,8 @@ pub async fn register_device(
         bound_container_id: request.bound_container_id,
         created_at: now,
         last_connected_at: Some(now),
+        last_ip: None,
+        last_user_agent: None,
         status: DeviceStatus::Unknown,
     };
 
@@ -107,6 +197,15 @@ pub async fn register_device(
             (StatusCode::CREATED, Json(device)).into_response()
         }
         Err(e) => {
+            // 并发注册绕过了上面的检查，由数据库唯一约束兜底
+            if let Some(constraint) = unique_violation(&e) {
+                info!("设备注册冲突: {}, 约束: {}", device.device_id, constraint);
+                return (
+                    StatusCode::CONFLICT,
+                    Json(register_conflict(&constraint, &device)),
+                )
+                    .into_response();
+            }
             error!("设备注册失败: {:?}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -120,11 +219,251 @@ pub async fn register_device(
     }
 }
 
+/// 数据库唯一约束冲突（SQLSTATE 23505）时返回冲突的约束名
+fn unique_violation(e: &anyhow::Error) -> Option<String> {
+    let db_error = e.downcast_ref::<sqlx::Error>()?.as_database_error()?;
+    (db_error.code().as_deref() == Some("23505"))
+        .then(|| db_error.constraint().unwrap_or_default().to_string())
+}
+
+/// 按冲突的约束生成与前置检查一致的 409 错误
+fn register_conflict(constraint: &str, device: &Device) -> ApiError {
+    if constraint == "idx_devices_name_unique" {
+        ApiError {
+            error: "NameConflict".to_string(),
+            message: format!("设备名称 {} 已被使用，请换一个名称", device.name),
+        }
+    } else {
+        ApiError {
+            error: "AlreadyRegistered".to_string(),
+            message: format!("设备 {} 已经注册过，无需重复注册", device.device_id),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ConnectionsQuery {
+    pub limit: Option<i64>,
+}
+
+/// 获取设备连接日志
+pub async fn list_device_connections(
+    State(store): State<DeviceStoreState>,
+    Path(device_id): Path<String>,
+    Query(query): Query<ConnectionsQuery>,
+) -> impl IntoResponse {
+    let device_id = normalize_device_id(&device_id);
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    info!("获取设备连接日志: {}", device_id);
+
+    match store.list_connections(&device_id, limit).await {
+        Ok(connections) => (StatusCode::OK, Json(connections)).into_response(),
+        Err(e) => {
+            error!("获取设备连接日志失败: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError {
+                    error: "InternalError".to_string(),
+                    message: "Failed to fetch device connections".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ConnectionLogQuery {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub event: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+fn invalid_request(message: String) -> axum::response::Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ApiError {
+            error: "InvalidRequest".to_string(),
+            message,
+        }),
+    )
+        .into_response()
+}
+
+/// 分页查询设备连接日志
+///
+/// 支持按时间范围（from/to，Unix 秒，闭区间）和事件类型（open/close）过滤，
+/// 返回当前页记录与满足条件的总数
+pub async fn query_device_connection_log(
+    State(store): State<DeviceStoreState>,
+    Path(device_id): Path<String>,
+    Query(query): Query<ConnectionLogQuery>,
+) -> impl IntoResponse {
+    let device_id = normalize_device_id(&device_id);
+
+    let event = match query
+        .event
+        .as_deref()
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+    {
+        Some(value) => match ConnectionEvent::parse(value) {
+            Some(event) => Some(event),
+            None => {
+                return invalid_request(format!(
+                    "Invalid event: {value} (expected \"open\" or \"close\")"
+                ))
+            }
+        },
+        None => None,
+    };
+    if query.from.is_some_and(|from| from < 0) || query.to.is_some_and(|to| to < 0) {
+        return invalid_request("from and to must be non-negative Unix timestamps".to_string());
+    }
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return invalid_request(format!("from ({from}) must not be later than to ({to})"));
+        }
+    }
+    if query.offset.is_some_and(|offset| offset < 0) {
+        return invalid_request("offset must be non-negative".to_string());
+    }
+
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = query.offset.unwrap_or(0);
+    let filter = ConnectionLogFilter {
+        from: query.from,
+        to: query.to,
+        event,
+    };
+    info!("查询设备连接日志: {} ({:?})", device_id, filter);
+
+    match store
+        .query_connection_log(&device_id, &filter, limit, offset)
+        .await
+    {
+        Ok(page) => (StatusCode::OK, Json(page)).into_response(),
+        Err(e) => {
+            error!("查询设备连接日志失败: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError {
+                    error: "InternalError".to_string(),
+                    message: "Failed to fetch device connection log".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// 批量导入设备
+///
+/// 逐行校验并在单个事务中插入，单行失败不会中断整个导入
+pub async fn import_devices(
+    State(store): State<DeviceStoreState>,
+    Json(entries): Json<Vec<ImportDeviceEntry>>,
+) -> impl IntoResponse {
+    info!("批量导入设备: {} 条", entries.len());
+
+    let now = chrono::Utc::now().timestamp();
+    let mut results: Vec<ImportDeviceResult> = Vec::with_capacity(entries.len());
+    let mut devices = Vec::new();
+    // 记录每个待插入设备在 results 中的位置
+    let mut pending = Vec::new();
+
+    for entry in entries {
+        match import_entry_device(entry, now) {
+            Ok(device) => {
+                pending.push(results.len());
+                results.push(ImportDeviceResult {
+                    device_id: device.device_id.clone(),
+                    outcome: ImportDeviceOutcome::Created,
+                    message: None,
+                });
+                devices.push(device);
+            }
+            Err(invalid) => results.push(invalid),
+        }
+    }
+
+    match store.import(&devices).await {
+        Ok(inserted) => {
+            for (index, created) in pending.into_iter().zip(inserted) {
+                if !created {
+                    results[index].outcome = ImportDeviceOutcome::SkippedDuplicate;
+                    results[index].message = Some("Device id, MAC address or name already in use".to_string());
+                }
+            }
+            let created = results
+                .iter()
+                .filter(|r| r.outcome == ImportDeviceOutcome::Created)
+                .count();
+            info!("批量导入完成: 新增 {} 条, 共 {} 条", created, results.len());
+            (StatusCode::OK, Json(results)).into_response()
+        }
+        Err(e) => {
+            error!("批量导入设备失败: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError {
+                    error: "InternalError".to_string(),
+                    message: "Failed to import devices".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// 校验单条导入记录并转换为待插入的设备，不合法时返回 invalid 结果
+fn import_entry_device(entry: ImportDeviceEntry, now: i64) -> Result<Device, ImportDeviceResult> {
+    let invalid = |device_id: String, message: String| ImportDeviceResult {
+        device_id,
+        outcome: ImportDeviceOutcome::Invalid,
+        message: Some(message),
+    };
+
+    let Some(device_id) = parse_mac_address(&entry.device_id) else {
+        return Err(invalid(
+            entry.device_id.clone(),
+            format!("Invalid device id: {}", entry.device_id),
+        ));
+    };
+    let Some(mac_address) = parse_mac_address(&entry.mac_address) else {
+        return Err(invalid(
+            device_id,
+            format!("Invalid MAC address: {}", entry.mac_address),
+        ));
+    };
+    if entry.name.trim().is_empty() {
+        return Err(invalid(
+            device_id,
+            "Device name must not be empty".to_string(),
+        ));
+    }
+
+    Ok(Device {
+        device_id,
+        name: entry.name,
+        mac_address,
+        bound_container_id: None,
+        created_at: now,
+        last_connected_at: None,
+        last_ip: None,
+        last_user_agent: None,
+        status: DeviceStatus::Unknown,
+    })
+}
+
 /// 删除设备
 pub async fn delete_device(
     State(store): State<DeviceStoreState>,
     Path(device_id): Path<String>,
 ) -> impl IntoResponse {
+    let device_id = normalize_device_id(&device_id);
     info!("删除设备: {}", device_id);
 
     // 检查设备是否存在
@@ -162,11 +501,13 @@ pub async fn delete_device(
 /// 绑定设备到服务器
 pub async fn bind_device_to_server(
     State(store): State<DeviceStoreState>,
+    State(manager): State<Arc<DockerManager>>,
     Path(device_id): Path<String>,
     Json(request): Json<BindServerRequest>,
 ) -> impl IntoResponse {
-    // 将 device_id 转换为小写无冒号格式
-    let device_id_normalized = device_id.replace(":", "").to_lowercase();
+    // 统一为存储格式，日志中使用小写无分隔符格式
+    let device_id = normalize_device_id(&device_id);
+    let device_id_normalized = device_id.replace(':', "").to_lowercase();
 
     // 获取设备当前信息（包括之前绑定的服务器）
     let device = match store.get(&device_id).await {
@@ -229,7 +570,20 @@ pub async fn bind_device_to_server(
                 "[后端] 切换服务器成功: device_id={}, 原服务器={} -> 新服务器={}",
                 device_id_normalized, previous_server_url, target_server_url
             );
-            StatusCode::NO_CONTENT.into_response()
+
+            if !request.validate_health {
+                return StatusCode::NO_CONTENT.into_response();
+            }
+
+            // 绑定不因健康检查失败而回滚，只提示用户
+            let warning = check_target_health(&manager, &request.container_id).await;
+            if let Some(ref warning) = warning {
+                info!(
+                    "[后端] 目标服务器健康检查未通过: device_id={}, 目标服务器={}, 原因={}",
+                    device_id_normalized, target_server_url, warning
+                );
+            }
+            (StatusCode::OK, Json(BindServerResponse { warning })).into_response()
         }
         Err(e) => {
             error!(
@@ -253,8 +607,9 @@ pub async fn unbind_device(
     State(store): State<DeviceStoreState>,
     Path(device_id): Path<String>,
 ) -> impl IntoResponse {
-    // 将 device_id 转换为小写无冒号格式
-    let device_id_normalized = device_id.replace(":", "").to_lowercase();
+    // 统一为存储格式，日志中使用小写无分隔符格式
+    let device_id = normalize_device_id(&device_id);
+    let device_id_normalized = device_id.replace(':', "").to_lowercase();
 
     // 获取设备当前信息（包括之前绑定的服务器）
     let device = match store.get(&device_id).await {
@@ -324,3 +679,766 @@ pub async fn unbind_device(
         }
     }
 }
+
+/// 按 Proxy 的路由规则解析设备的目标服务器
+///
+/// 服务器 URL 使用数据库中的标准 device_id 拼接，与调用方传入的 ID 格式无关。
+async fn resolve_ws_target(
+    store: &PgDeviceStore,
+    device: Device,
+) -> anyhow::Result<DeviceWsTarget> {
+    let mut target = DeviceWsTarget {
+        device_id: device.device_id,
+        routable: false,
+        container_id: None,
+        fallback: false,
+        server_url: None,
+        reason: None,
+    };
+
+    let container_id = match device.bound_container_id {
+        Some(container_id) => container_id,
+        None => match store.get_default_container_id().await? {
+            Some(container_id) => {
+                target.fallback = true;
+                container_id
+            }
+            None => {
+                target.reason = Some("Device is not bound to a server".to_string());
+                return Ok(target);
+            }
+        },
+    };
+    target.container_id = Some(container_id.clone());
+
+    let Some(route) = store.get_container_route(&container_id).await? else {
+        target.reason = Some(format!("Server {} does not exist", container_id));
+        return Ok(target);
+    };
+    target.server_url = Some(route.ws_url(&target.device_id));
+
+    if route.status != "running" {
+        target.reason = Some(format!("Server is not running (status: {})", route.status));
+    } else if route.draining {
+        target.reason = Some("Server is draining and rejects new connections".to_string());
+    } else {
+        target.routable = true;
+    }
+
+    Ok(target)
+}
+
+/// 获取设备当前应使用的连接参数（供设备在激活或重新绑定后主动拉取）
+pub async fn get_device_connection(
+    State(store): State<DeviceStoreState>,
+    State(manager): State<Arc<DockerManager>>,
+    Path(raw_device_id): Path<String>,
+) -> impl IntoResponse {
+    let device_id = normalize_device_id(&raw_device_id);
+
+    let device = match store.get(&device_id).await {
+        Ok(Some(device)) => device,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ApiError {
+                    error: "NotFound".to_string(),
+                    message: format!("Device {} not found", device_id),
+                }),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            error!("获取设备失败: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError {
+                    error: "InternalError".to_string(),
+                    message: "Failed to fetch device".to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    let target = match resolve_ws_target(&store, device).await {
+        Ok(target) => target,
+        Err(e) => {
+            error!("解析设备路由失败: {}, 错误: {:?}", device_id, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError {
+                    error: "InternalError".to_string(),
+                    message: "Failed to resolve device route".to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    // 配置了 Proxy 时设备始终连接 Proxy，由 Proxy 按最新绑定转发
+    let (url, via_proxy, token_expires_at) = match manager.device_proxy_ws_url(&raw_device_id) {
+        Some((url, token_expires_at)) => (Some(url), true, token_expires_at),
+        None => (target.server_url, false, None),
+    };
+    let params = DeviceConnectionParams {
+        device_id: target.device_id,
+        url,
+        via_proxy,
+        token_expires_at,
+        container_id: target.container_id,
+        routable: target.routable,
+        reason: target.reason,
+    };
+    (StatusCode::OK, Json(params)).into_response()
+}
+
+/// 预览设备将被 Proxy 路由到的服务器 WebSocket URL
+pub async fn get_device_ws_target(
+    State(store): State<DeviceStoreState>,
+    Path(raw_device_id): Path<String>,
+) -> impl IntoResponse {
+    let device_id = normalize_device_id(&raw_device_id);
+
+    let device = match store.get(&device_id).await {
+        Ok(Some(device)) => device,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ApiError {
+                    error: "NotFound".to_string(),
+                    message: format!("Device {} not found", device_id),
+                }),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            error!("获取设备失败: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError {
+                    error: "InternalError".to_string(),
+                    message: "Failed to fetch device".to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    match resolve_ws_target(&store, device).await {
+        Ok(target) => (StatusCode::OK, Json(target)).into_response(),
+        Err(e) => {
+            error!("解析设备路由失败: {}, 错误: {:?}", device_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError {
+                    error: "InternalError".to_string(),
+                    message: "Failed to resolve device route".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// 通过 Proxy 测试设备当前的路由（设备本身无需在线）
+pub async fn test_device_route(
+    State(store): State<DeviceStoreState>,
+    State(manager): State<Arc<DockerManager>>,
+    Path(raw_device_id): Path<String>,
+) -> impl IntoResponse {
+    let device_id = normalize_device_id(&raw_device_id);
+
+    let device = match store.get(&device_id).await {
+        Ok(Some(device)) => device,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ApiError {
+                    error: "NotFound".to_string(),
+                    message: format!("Device {} not found", device_id),
+                }),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            error!("获取设备失败: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError {
+                    error: "InternalError".to_string(),
+                    message: "Failed to fetch device".to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    // 先在本地判断路由，未绑定、服务器不存在或未运行时无需经过 Proxy
+    let target = match resolve_ws_target(&store, device).await {
+        Ok(target) => target,
+        Err(e) => {
+            error!("解析设备路由失败: {}, 错误: {:?}", device_id, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError {
+                    error: "InternalError".to_string(),
+                    message: "Failed to resolve device route".to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+    if !target.routable {
+        return (
+            StatusCode::OK,
+            Json(DeviceRouteTest {
+                ok: false,
+                container_id: target.container_id,
+                latency_ms: None,
+                reason: target.reason,
+            }),
+        )
+            .into_response();
+    }
+
+    match manager.test_device_route(&raw_device_id).await {
+        Ok(Some(result)) => {
+            info!("设备路由测试完成: {}, ok={}", device_id, result.ok);
+            (StatusCode::OK, Json(result)).into_response()
+        }
+        Ok(None) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiError {
+                error: "ProxyNotConfigured".to_string(),
+                message: "Proxy WebSocket URL is not configured (PROXY_WS_URL)".to_string(),
+            }),
+        )
+            .into_response(),
+        Err(e) => {
+            if let Some(limited) = e.downcast_ref::<ProxyRateLimited>() {
+                warn!("设备路由测试被 Proxy 限流: {}", device_id);
+                return too_many_requests("RateLimited", limited.to_string(), limited.retry_after);
+            }
+            error!("设备路由测试失败: {}, 错误: {:#}", device_id, e);
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(ApiError {
+                    error: "ProxyTestFailed".to_string(),
+                    message: format!("{:#}", e),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::models::{ContainerStatus, HealthCheckResult};
+
+    fn entry(device_id: &str, mac_address: &str, name: &str) -> ImportDeviceEntry {
+        ImportDeviceEntry {
+            device_id: device_id.to_string(),
+            name: name.to_string(),
+            mac_address: mac_address.to_string(),
+        }
+    }
+
+    fn container(status: ContainerStatus, health: Option<HealthCheckResult>) -> ContainerInfo {
+        ContainerInfo {
+            id: "abc123".to_string(),
+            name: "echokit-server-abc123".to_string(),
+            port: 10000,
+            ws_url: "ws://localhost:10000/ws".to_string(),
+            status,
+            created_at: chrono::Utc::now(),
+            health,
+            active_connections: None,
+            description: None,
+        }
+    }
+
+    fn health(status: HealthStatus, error_message: Option<&str>) -> HealthCheckResult {
+        HealthCheckResult {
+            http_reachable: status == HealthStatus::Healthy,
+            status,
+            container_running: true,
+            error_message: error_message.map(str::to_string),
+            logs_tail: None,
+            error_lines: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn target_health_warning_flags_unhealthy_servers() {
+        let healthy = container(
+            ContainerStatus::Running,
+            Some(health(HealthStatus::Healthy, None)),
+        );
+        assert_eq!(target_health_warning(&healthy), None);
+
+        let unhealthy = container(
+            ContainerStatus::Running,
+            Some(health(HealthStatus::Unhealthy, Some("HTTP 502"))),
+        );
+        assert_eq!(
+            target_health_warning(&unhealthy).as_deref(),
+            Some("Target server is unhealthy: HTTP 502")
+        );
+
+        let stopped = container(ContainerStatus::Stopped, None);
+        assert_eq!(
+            target_health_warning(&stopped).as_deref(),
+            Some("Target server is not running (status: stopped)")
+        );
+    }
+
+    #[test]
+    fn bind_response_carries_the_warning() {
+        let body = serde_json::to_value(BindServerResponse {
+            warning: Some("Target server is unhealthy: HTTP 502".to_string()),
+        })
+        .unwrap();
+        assert_eq!(body["warning"], "Target server is unhealthy: HTTP 502");
+
+        let body = serde_json::to_value(BindServerResponse { warning: None }).unwrap();
+        assert!(body.get("warning").is_none());
+    }
+
+    #[test]
+    fn register_conflict_names_the_violated_constraint() {
+        let device = import_entry_device(entry("98a316f0b1e5", "98a316f0b1e5", "kitchen"), 0)
+            .expect("valid entry");
+
+        assert_eq!(
+            register_conflict("idx_devices_name_unique", &device).error,
+            "NameConflict"
+        );
+        assert_eq!(
+            register_conflict("devices_pkey", &device).error,
+            "AlreadyRegistered"
+        );
+        assert_eq!(
+            register_conflict("devices_mac_address_key", &device).error,
+            "AlreadyRegistered"
+        );
+    }
+
+    #[test]
+    fn unique_violation_ignores_other_errors() {
+        assert_eq!(unique_violation(&anyhow::anyhow!("boom")), None);
+        assert_eq!(
+            unique_violation(&anyhow::Error::new(sqlx::Error::RowNotFound)),
+            None
+        );
+    }
+
+    #[test]
+    fn import_entry_normalizes_device_id_and_mac() {
+        let device = import_entry_device(entry("98a316f0b1e5", "98-a3-16-f0-b1-e5", "kitchen"), 42)
+            .expect("valid entry");
+        assert_eq!(device.device_id, "98:A3:16:F0:B1:E5");
+        assert_eq!(device.mac_address, "98:A3:16:F0:B1:E5");
+        assert_eq!(device.created_at, 42);
+    }
+
+    #[test]
+    fn import_entry_rejects_non_mac_device_id() {
+        let result =
+            import_entry_device(entry("not-a-mac", "98:A3:16:F0:B1:E5", "kitchen"), 0).unwrap_err();
+        assert_eq!(result.outcome, ImportDeviceOutcome::Invalid);
+        assert_eq!(result.device_id, "not-a-mac");
+    }
+
+    #[test]
+    fn import_entry_rejects_bad_mac_and_empty_name() {
+        let bad_mac =
+            import_entry_device(entry("98:A3:16:F0:B1:E5", "xyz", "kitchen"), 0).unwrap_err();
+        assert_eq!(bad_mac.outcome, ImportDeviceOutcome::Invalid);
+
+        let empty_name =
+            import_entry_device(entry("98:A3:16:F0:B1:E5", "98:A3:16:F0:B1:E5", "  "), 0)
+                .unwrap_err();
+        assert_eq!(empty_name.outcome, ImportDeviceOutcome::Invalid);
+    }
+
+    async fn connection_params(
+        store: &DeviceStoreState,
+        manager: DockerManager,
+        device_id: &str,
+    ) -> serde_json::Value {
+        let response = get_device_connection(
+            State(store.clone()),
+            State(Arc::new(manager)),
+            Path(device_id.to_string()),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn connection_params_follow_rebinds() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL 未设置，跳过数据库测试");
+            return;
+        };
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&url)
+            .await
+            .unwrap();
+        let store: DeviceStoreState = Arc::new(PgDeviceStore::new(pool.clone()));
+        let suffix = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+        let device_id = format!("test-conn-{suffix}");
+        let old_server = format!("test-old-{suffix}");
+        let new_server = format!("test-new-{suffix}");
+
+        for (id, port) in [(&old_server, 10101), (&new_server, 10102)] {
+            sqlx::query(
+                r#"
+                INSERT INTO containers (id, name, host, port, created_at, status)
+                VALUES ($1, $1, 'dallas.echokit.dev', $2, 0, 'running')
+                "#,
+            )
+            .bind(id)
+            .bind(port)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO devices (device_id, name, mac_address, created_at, bound_container_id)
+            VALUES ($1, $1, $1, 0, $2)
+            "#,
+        )
+        .bind(&device_id)
+        .bind(&old_server)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let direct = || DockerManager::for_tests(AppConfig::default());
+        let proxied = || {
+            DockerManager::for_tests(AppConfig {
+                device_proxy_url: Some("wss://proxy.echokit.dev".to_string()),
+                ..AppConfig::default()
+            })
+        };
+
+        let before = connection_params(&store, direct(), &device_id).await;
+        let proxied_before = connection_params(&store, proxied(), &device_id).await;
+        sqlx::query("UPDATE devices SET bound_container_id = $2 WHERE device_id = $1")
+            .bind(&device_id)
+            .bind(&new_server)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let after = connection_params(&store, direct(), &device_id).await;
+        let proxied_after = connection_params(&store, proxied(), &device_id).await;
+
+        sqlx::query("DELETE FROM devices WHERE device_id = $1")
+            .bind(&device_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM containers WHERE id = ANY($1)")
+            .bind(vec![old_server.clone(), new_server.clone()])
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(before["containerId"], old_server.as_str());
+        assert_eq!(
+            before["url"],
+            format!("ws://dallas.echokit.dev:10101/ws/{device_id}")
+        );
+        assert_eq!(before["routable"], true);
+        assert_eq!(after["containerId"], new_server.as_str());
+        assert_eq!(
+            after["url"],
+            format!("ws://dallas.echokit.dev:10102/ws/{device_id}")
+        );
+
+        // 经 Proxy 连接时地址不随绑定变化，由 Proxy 按最新绑定转发
+        assert_eq!(proxied_before["viaProxy"], true);
+        assert_eq!(
+            proxied_before["url"],
+            format!("wss://proxy.echokit.dev/ws/{device_id}")
+        );
+        assert_eq!(proxied_after["url"], proxied_before["url"]);
+        assert_eq!(proxied_after["containerId"], new_server.as_str());
+    }
+
+    #[tokio::test]
+    async fn connection_log_rejects_invalid_queries() {
+        // 校验失败时不会访问数据库
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/echokit")
+            .unwrap();
+        let store = Arc::new(PgDeviceStore::new(pool));
+        let query = |from, to, event: Option<&str>, offset| ConnectionLogQuery {
+            from,
+            to,
+            event: event.map(str::to_string),
+            limit: None,
+            offset,
+        };
+
+        for invalid in [
+            query(Some(200), Some(100), None, None),
+            query(Some(-1), None, None, None),
+            query(None, None, Some("reboot"), None),
+            query(None, None, Some("close"), Some(-5)),
+        ] {
+            let response = query_device_connection_log(
+                State(store.clone()),
+                Path("98:A3:16:F0:B1:E5".to_string()),
+                Query(invalid),
+            )
+            .await
+            .into_response();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[test]
+    fn connection_event_accepts_aliases() {
+        assert_eq!(
+            ConnectionEvent::parse("close"),
+            Some(ConnectionEvent::Close)
+        );
+        assert_eq!(
+            ConnectionEvent::parse("Disconnect"),
+            Some(ConnectionEvent::Close)
+        );
+        assert_eq!(ConnectionEvent::parse("OPEN"), Some(ConnectionEvent::Open));
+        assert_eq!(ConnectionEvent::parse("reboot"), None);
+    }
+
+    #[tokio::test]
+    async fn container_devices_lists_only_devices_bound_to_it() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL 未设置，跳过数据库测试");
+            return;
+        };
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&url)
+            .await
+            .unwrap();
+        let store: DeviceStoreState = Arc::new(PgDeviceStore::new(pool.clone()));
+        let suffix = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+        let server = format!("test-fleet-{suffix}");
+        let other_server = format!("test-other-{suffix}");
+
+        // (设备, 绑定的服务器, 状态, 创建时间)
+        let devices = [
+            (format!("test-online-{suffix}"), Some(&server), "online", 2),
+            (
+                format!("test-offline-{suffix}"),
+                Some(&server),
+                "offline",
+                1,
+            ),
+            (
+                format!("test-elsewhere-{suffix}"),
+                Some(&other_server),
+                "online",
+                3,
+            ),
+            (format!("test-unbound-{suffix}"), None, "unknown", 4),
+        ];
+        for (device_id, bound, status, created_at) in &devices {
+            sqlx::query(
+                r#"
+                INSERT INTO devices (device_id, name, mac_address, created_at, bound_container_id, status)
+                VALUES ($1, $1, $1, $2, $3, $4)
+                "#,
+            )
+            .bind(device_id)
+            .bind(created_at)
+            .bind(bound)
+            .bind(status)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let response = list_container_devices(State(store.clone()), Path(server.clone()))
+            .await
+            .into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let empty = list_container_devices(State(store), Path(format!("test-none-{suffix}")))
+            .await
+            .into_response();
+        let empty_body = axum::body::to_bytes(empty.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        for (device_id, ..) in &devices {
+            sqlx::query("DELETE FROM devices WHERE device_id = $1")
+                .bind(device_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(status, StatusCode::OK);
+        let body: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        let listed: Vec<(&str, &str)> = body
+            .iter()
+            .map(|d| {
+                (
+                    d["deviceId"].as_str().unwrap(),
+                    d["status"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                (devices[0].0.as_str(), "online"),
+                (devices[1].0.as_str(), "offline")
+            ]
+        );
+        assert!(body
+            .iter()
+            .all(|d| d["boundContainerId"] == server.as_str()));
+        assert_eq!(empty_body.as_ref(), b"[]");
+    }
+
+    /// 模拟 Proxy 的连接测试接口：与 Proxy 相同，通过关闭帧报告服务器不可达
+    async fn unreachable_route_test_proxy() -> String {
+        use axum::extract::ws::{close_code, CloseFrame, Message, WebSocketUpgrade};
+
+        let app = axum::Router::new().route(
+            "/ws-test/{device_id}",
+            axum::routing::get(|ws: WebSocketUpgrade| async move {
+                ws.on_upgrade(|mut socket| async move {
+                    let reason =
+                        serde_json::json!({ "ok": false, "reason": "EchoKit Server unreachable" });
+                    let _ = socket
+                        .send(Message::Close(Some(CloseFrame {
+                            code: close_code::ERROR,
+                            reason: reason.to_string().into(),
+                        })))
+                        .await;
+                })
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("ws://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn route_test_reports_unreachable_and_stopped_servers() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL 未设置，跳过数据库测试");
+            return;
+        };
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&url)
+            .await
+            .unwrap();
+        let store: DeviceStoreState = Arc::new(PgDeviceStore::new(pool.clone()));
+        let suffix = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+        let running = format!("test-running-{suffix}");
+        let stopped = format!("test-stopped-{suffix}");
+        let bound_to_running = format!("test-route-a-{suffix}");
+        let bound_to_stopped = format!("test-route-b-{suffix}");
+
+        for (id, port, status) in [(&running, 10103, "running"), (&stopped, 10104, "stopped")] {
+            sqlx::query(
+                r#"
+                INSERT INTO containers (id, name, host, port, created_at, status)
+                VALUES ($1, $1, 'dallas.echokit.dev', $2, 0, $3)
+                "#,
+            )
+            .bind(id)
+            .bind(port)
+            .bind(status)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        for (device_id, server) in [(&bound_to_running, &running), (&bound_to_stopped, &stopped)] {
+            sqlx::query(
+                r#"
+                INSERT INTO devices (device_id, name, mac_address, created_at, bound_container_id)
+                VALUES ($1, $1, $1, 0, $2)
+                "#,
+            )
+            .bind(device_id)
+            .bind(server)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let manager = Arc::new(DockerManager::for_tests(AppConfig {
+            proxy_ws_url: Some(unreachable_route_test_proxy().await),
+            ..AppConfig::default()
+        }));
+        let mut results = Vec::new();
+        for device_id in [&bound_to_running, &bound_to_stopped] {
+            let response = test_device_route(
+                State(store.clone()),
+                State(manager.clone()),
+                Path(device_id.clone()),
+            )
+            .await
+            .into_response();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            results.push((
+                status,
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            ));
+        }
+
+        for device_id in [&bound_to_running, &bound_to_stopped] {
+            sqlx::query("DELETE FROM devices WHERE device_id = $1")
+                .bind(device_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        for id in [&running, &stopped] {
+            sqlx::query("DELETE FROM containers WHERE id = $1")
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        // 绑定的服务器在运行但不可达：Proxy 报告失败
+        let (status, body) = &results[0];
+        assert_eq!(*status, StatusCode::OK);
+        assert_eq!(body["ok"], false);
+        assert_eq!(body["reason"], "EchoKit Server unreachable");
+
+        // 绑定的服务器已停止：无需经过 Proxy 即返回失败
+        let (status, body) = &results[1];
+        assert_eq!(*status, StatusCode::OK);
+        assert_eq!(body["ok"], false);
+        assert_eq!(body["containerId"], stopped.as_str());
+        assert_eq!(body["reason"], "Server is not running (status: stopped)");
+    }
+}
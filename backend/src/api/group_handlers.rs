@@ -0,0 +1,272 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use super::device_handlers::check_target_health;
+use crate::docker::DockerManager;
+use crate::models::{normalize_device_id, ApiError, BindServerRequest, GroupBindResponse, GroupRequest};
+use crate::store::PgGroupStore;
+
+pub type GroupStoreState = Arc<PgGroupStore>;
+
+fn not_found(group_id: i64) -> axum::response::Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ApiError {
+            error: "NotFound".to_string(),
+            message: format!("Group {} not found", group_id),
+        }),
+    )
+        .into_response()
+}
+
+fn internal_error(message: &str) -> axum::response::Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ApiError {
+            error: "InternalError".to_string(),
+            message: message.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// 校验分组请求（名称非空且未被其他分组使用），失败时返回错误响应
+fn validate_request(
+    name_conflict: bool,
+    request: &GroupRequest,
+) -> Option<axum::response::Response> {
+    if request.name.trim().is_empty() {
+        return Some(
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError {
+                    error: "InvalidRequest".to_string(),
+                    message: "Group name must not be empty".to_string(),
+                }),
+            )
+                .into_response(),
+        );
+    }
+    if name_conflict {
+        return Some(
+            (
+                StatusCode::CONFLICT,
+                Json(ApiError {
+                    error: "NameConflict".to_string(),
+                    message: format!("分组名称 {} 已被使用，请换一个名称", request.name.trim()),
+                }),
+            )
+                .into_response(),
+        );
+    }
+    None
+}
+
+/// 标准化成员设备 ID
+fn normalize_members(device_ids: &[String]) -> Vec<String> {
+    device_ids
+        .iter()
+        .map(|id| normalize_device_id(id))
+        .collect()
+}
+
+/// 获取所有分组
+pub async fn list_groups(State(store): State<GroupStoreState>) -> impl IntoResponse {
+    match store.list().await {
+        Ok(groups) => Json(groups).into_response(),
+        Err(e) => {
+            error!("获取分组列表失败: {:?}", e);
+            internal_error("Failed to list groups")
+        }
+    }
+}
+
+/// 获取单个分组
+pub async fn get_group(
+    State(store): State<GroupStoreState>,
+    Path(group_id): Path<i64>,
+) -> impl IntoResponse {
+    match store.get(group_id).await {
+        Ok(Some(group)) => Json(group).into_response(),
+        Ok(None) => not_found(group_id),
+        Err(e) => {
+            error!("获取分组失败: {}, 错误: {:?}", group_id, e);
+            internal_error("Failed to get group")
+        }
+    }
+}
+
+/// 创建分组
+pub async fn create_group(
+    State(store): State<GroupStoreState>,
+    Json(request): Json<GroupRequest>,
+) -> impl IntoResponse {
+    let name = request.name.trim();
+    let conflict = match store.name_exists(name, None).await {
+        Ok(conflict) => conflict,
+        Err(e) => {
+            error!("检查分组名称失败: {:?}", e);
+            return internal_error("Failed to create group");
+        }
+    };
+    if let Some(response) = validate_request(conflict, &request) {
+        return response;
+    }
+
+    let device_ids = normalize_members(&request.device_ids);
+    let group = match store.create(name, &device_ids).await {
+        Ok(group_id) => store.get(group_id).await,
+        Err(e) => Err(e),
+    };
+
+    match group {
+        Ok(Some(group)) => {
+            info!(
+                "分组创建成功: {} ({} 个成员)",
+                group.name, group.member_count
+            );
+            (StatusCode::CREATED, Json(group)).into_response()
+        }
+        Ok(None) => internal_error("Failed to create group"),
+        Err(e) => {
+            error!("分组创建失败: {:?}", e);
+            internal_error("Failed to create group")
+        }
+    }
+}
+
+/// 更新分组名称和成员（成员列表整体替换）
+pub async fn update_group(
+    State(store): State<GroupStoreState>,
+    Path(group_id): Path<i64>,
+    Json(request): Json<GroupRequest>,
+) -> impl IntoResponse {
+    let name = request.name.trim();
+    let conflict = match store.name_exists(name, Some(group_id)).await {
+        Ok(conflict) => conflict,
+        Err(e) => {
+            error!("检查分组名称失败: {:?}", e);
+            return internal_error("Failed to update group");
+        }
+    };
+    if let Some(response) = validate_request(conflict, &request) {
+        return response;
+    }
+
+    let device_ids = normalize_members(&request.device_ids);
+    match store.update(group_id, name, &device_ids).await {
+        Ok(true) => {}
+        Ok(false) => return not_found(group_id),
+        Err(e) => {
+            error!("分组更新失败: {}, 错误: {:?}", group_id, e);
+            return internal_error("Failed to update group");
+        }
+    }
+
+    match store.get(group_id).await {
+        Ok(Some(group)) => {
+            info!(
+                "分组更新成功: {} ({} 个成员)",
+                group.name, group.member_count
+            );
+            Json(group).into_response()
+        }
+        Ok(None) => not_found(group_id),
+        Err(e) => {
+            error!("获取分组失败: {}, 错误: {:?}", group_id, e);
+            internal_error("Failed to update group")
+        }
+    }
+}
+
+/// 删除分组（成员设备保留）
+pub async fn delete_group(
+    State(store): State<GroupStoreState>,
+    Path(group_id): Path<i64>,
+) -> impl IntoResponse {
+    match store.delete(group_id).await {
+        Ok(true) => {
+            info!("分组删除成功: {}", group_id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => not_found(group_id),
+        Err(e) => {
+            error!("分组删除失败: {}, 错误: {:?}", group_id, e);
+            internal_error("Failed to delete group")
+        }
+    }
+}
+
+/// 将分组所有成员绑定到服务器
+pub async fn bind_group_to_server(
+    State(store): State<GroupStoreState>,
+    State(manager): State<Arc<DockerManager>>,
+    Path(group_id): Path<i64>,
+    Json(request): Json<BindServerRequest>,
+) -> impl IntoResponse {
+    info!(
+        "[后端] 分组绑定请求: group_id={}, 目标服务器={}",
+        group_id, request.container_id
+    );
+
+    let updated_devices = match store.bind_to_server(group_id, &request.container_id).await {
+        Ok(Some(count)) => count,
+        Ok(None) => return not_found(group_id),
+        Err(e) => {
+            error!(
+                "[后端] 分组绑定失败: group_id={}, 目标服务器={}, 错误={:?}",
+                group_id, request.container_id, e
+            );
+            return internal_error("Failed to bind group");
+        }
+    };
+
+    info!(
+        "[后端] 分组绑定成功: group_id={}, 目标服务器={}, 设备数={}",
+        group_id, request.container_id, updated_devices
+    );
+
+    // 与单设备绑定一致：健康检查失败不回滚，只提示用户
+    let warning = if request.validate_health {
+        check_target_health(&manager, &request.container_id).await
+    } else {
+        None
+    };
+
+    Json(GroupBindResponse {
+        updated_devices,
+        warning,
+    })
+    .into_response()
+}
+
+/// 解绑分组所有成员
+pub async fn unbind_group(
+    State(store): State<GroupStoreState>,
+    Path(group_id): Path<i64>,
+) -> impl IntoResponse {
+    match store.unbind(group_id).await {
+        Ok(Some(count)) => {
+            info!(
+                "[后端] 分组解绑成功: group_id={}, 设备数={}",
+                group_id, count
+            );
+            Json(GroupBindResponse {
+                updated_devices: count,
+                warning: None,
+            })
+            .into_response()
+        }
+        Ok(None) => not_found(group_id),
+        Err(e) => {
+            error!("[后端] 分组解绑失败: group_id={}, 错误={:?}", group_id, e);
+            internal_error("Failed to unbind group")
+        }
+    }
+}
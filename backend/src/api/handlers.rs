@@ -1,41 +1,159 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{
+        rejection::JsonRejection,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
 use serde::Deserialize;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use crate::docker::DockerManager;
-use crate::models::{ApiError, DeployRequest};
+use crate::docker::{
+    parse_logs_cursor, DeployError, DeployTarget, DockerManager, MAX_LOG_STREAM_CONTAINERS,
+};
+use crate::models::{
+    ASRConfig, ApiError, BuildInfo, ConfigValidationResponse, DeployRequest, EchoKitConfig,
+    FieldError, MigrateContainerRequest, PlatformsResponse, RateLimitedError, RotateKeysRequest,
+    TTSConfig, UpdateContainerRequest,
+};
 
 pub type AppState = Arc<DockerManager>;
 
+/// 部署配置校验失败的 400 响应
+fn invalid_deploy_config(message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(
+            serde_json::to_value(ApiError {
+                error: "invalid_config".to_string(),
+                message,
+            })
+            .unwrap(),
+        ),
+    )
+}
+
+/// 将 JSON 请求体解析失败转换为 ApiError 响应
+///
+/// 字段有误（如包含未知字段）时返回 400 并带上 serde 给出的字段信息，
+/// 其他拒绝（如请求体过大）保留原状态码
+fn invalid_json_request(rejection: JsonRejection) -> (StatusCode, Json<serde_json::Value>) {
+    let status = match rejection.status() {
+        StatusCode::UNPROCESSABLE_ENTITY => StatusCode::BAD_REQUEST,
+        status => status,
+    };
+    (
+        status,
+        Json(
+            serde_json::to_value(ApiError {
+                error: "invalid_request".to_string(),
+                message: rejection.body_text(),
+            })
+            .unwrap(),
+        ),
+    )
+}
+
+/// 部署失败时的状态码与错误标识
+///
+/// 指定端口无效或已被占用时在部署前返回 400/409，便于前端提示用户更换端口；
+/// Docker 不可达时返回 503，其他错误使用 `fallback` 与 500
+fn deploy_error_status(e: &anyhow::Error, fallback: &'static str) -> (StatusCode, &'static str) {
+    match e.downcast_ref::<DeployError>() {
+        Some(DeployError::Invalid(_)) => (StatusCode::BAD_REQUEST, "invalid_config"),
+        Some(DeployError::HostPortInUse(_))
+        | Some(DeployError::PortAllocated { .. })
+        | Some(DeployError::PortReserved(_)) => (StatusCode::CONFLICT, "port_in_use"),
+        Some(DeployError::DockerUnavailable(_)) => {
+            (StatusCode::SERVICE_UNAVAILABLE, "docker_unavailable")
+        }
+        None => (StatusCode::INTERNAL_SERVER_ERROR, fallback),
+    }
+}
+
 /// 部署新的 EchoKit 实例
 pub async fn deploy(
     State(manager): State<AppState>,
-    Json(request): Json<DeployRequest>,
+    payload: Result<Json<DeployRequest>, JsonRejection>,
 ) -> impl IntoResponse {
+    let request = match payload {
+        Ok(Json(request)) => request,
+        Err(rejection) => {
+            error!("部署请求解析失败: {}", rejection.body_text());
+            return invalid_json_request(rejection);
+        }
+    };
     let instance_name = &request.config.name;
-    let tts_platform = get_tts_platform_name(&request.config.tts);
+    let asr_platform = request.config.asr.display_name();
+    let tts_platform = request.config.tts.display_name();
 
     info!("========== 开始部署 EchoKit 实例 ==========");
     info!(
-        "实例名称: {}, TTS平台: {}, 指定端口: {:?}",
-        instance_name, tts_platform, request.port
+        "实例名称: {}, ASR平台: {}, TTS平台: {}, 指定端口: {:?}",
+        instance_name, asr_platform, tts_platform, request.port
     );
 
+    if let Err(message) = manager
+        .validate_config(&request.config)
+        .map_err(|errors| FieldError::join(&errors))
+        .and_then(|_| manager.validate_extra_ports(&request.extra_ports))
+        .and_then(|_| match request.advertised_host.as_deref() {
+            Some(host) => manager.validate_advertised_host(host),
+            None => Ok(()),
+        })
+        .and_then(|_| match request.hostname.as_deref() {
+            Some(hostname) => manager.validate_hostname(hostname),
+            None => Ok(()),
+        })
+        .and_then(|_| match request.description.as_deref() {
+            Some(description) => manager.validate_description(description),
+            None => Ok(()),
+        })
+    {
+        error!("部署配置校验失败: 实例: {}, 错误: {}", instance_name, message);
+        return invalid_deploy_config(message);
+    }
+
+    let network = manager.resolve_network(request.network.as_deref());
+    if let Some(network) = network {
+        if let Err(message) = manager.validate_network(network).await {
+            error!(
+                "部署网络校验失败: 实例: {}, 错误: {}",
+                instance_name, message
+            );
+            return invalid_deploy_config(message);
+        }
+    }
+
     let start_time = std::time::Instant::now();
 
-    match manager.deploy(request.config.clone(), request.port).await {
+    match manager
+        .deploy(
+            request.config.clone(),
+            DeployTarget {
+                port: request.port,
+                extra_ports: &request.extra_ports,
+                advertised_host: request.advertised_host.as_deref(),
+                hostname: request.hostname.as_deref(),
+                network,
+                image: None,
+                start: request.start,
+                wait_for_health: request.wait_for_health,
+            },
+        )
+        .await {
         Ok(response) => {
             let elapsed = start_time.elapsed();
-            let health_status = if response.health.status == crate::models::HealthStatus::Healthy {
-                "✓ 健康"
-            } else {
-                "✗ 不健康"
+            let health_status = match response.health.status {
+                crate::models::HealthStatus::Healthy => "✓ 健康",
+                crate::models::HealthStatus::Unknown => "未检查",
+                _ => "✗ 不健康",
             };
 
             info!("========== 部署完成 ==========");
@@ -49,7 +167,22 @@ pub async fn deploy(
             );
             info!("WebSocket地址: {}", response.ws_url);
 
-            if response.health.status != crate::models::HealthStatus::Healthy {
+            if request.auto_stop {
+                if let Err(e) = manager.set_auto_stop(&response.container_id, true).await {
+                    error!("开启空闲自动停止失败: {:#}", e);
+                }
+            }
+
+            if let Some(description) = request.description.as_deref() {
+                if let Err(e) = manager.set_description(&response.container_id, description).await {
+                    error!("保存容器描述失败: {:#}", e);
+                }
+            }
+
+            if request.start
+                && request.wait_for_health
+                && response.health.status != crate::models::HealthStatus::Healthy
+            {
                 if let Some(ref err_msg) = response.health.error_message {
                     error!("健康检查失败: {}", err_msg);
                 }
@@ -69,11 +202,12 @@ pub async fn deploy(
                 error_chain
             );
 
+            let (status, error) = deploy_error_status(&e, "deploy_failed");
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
+                status,
                 Json(
                     serde_json::to_value(ApiError {
-                        error: "deploy_failed".to_string(),
+                        error: error.to_string(),
                         message: error_chain,
                     })
                     .unwrap(),
@@ -83,18 +217,29 @@ pub async fn deploy(
     }
 }
 
-/// 获取 TTS 平台名称
-fn get_tts_platform_name(tts: &crate::models::TTSConfig) -> &'static str {
-    use crate::models::TTSConfig;
-    match tts {
-        TTSConfig::Openai { .. } => "OpenAI",
-        TTSConfig::Groq { .. } => "Groq",
-        TTSConfig::Elevenlabs { .. } => "ElevenLabs",
-        TTSConfig::GSV { .. } => "GSV",
-        TTSConfig::StreamGSV { .. } => "StreamGSV",
-        TTSConfig::Fish { .. } => "Fish",
-        TTSConfig::CosyVoice { .. } => "CosyVoice",
-    }
+/// 获取支持的 ASR / TTS 平台列表
+pub async fn list_platforms() -> impl IntoResponse {
+    Json(PlatformsResponse {
+        asr: ASRConfig::PLATFORMS,
+        tts: TTSConfig::PLATFORMS,
+    })
+}
+
+/// 校验 EchoKit 配置（与部署时的校验相同），返回所有字段错误
+pub async fn validate_config(
+    State(manager): State<AppState>,
+    payload: Result<Json<EchoKitConfig>, JsonRejection>,
+) -> impl IntoResponse {
+    let config = match payload {
+        Ok(Json(config)) => config,
+        Err(rejection) => return invalid_json_request(rejection).into_response(),
+    };
+    let errors = manager.validate_config(&config).err().unwrap_or_default();
+    Json(ConfigValidationResponse {
+        valid: errors.is_empty(),
+        errors,
+    })
+    .into_response()
 }
 
 /// 获取所有容器列表
@@ -121,16 +266,41 @@ pub async fn list_containers(State(manager): State<AppState>) -> impl IntoRespon
     }
 }
 
+/// 对所有运行中的容器执行健康检查
+pub async fn health_check_all_containers(State(manager): State<AppState>) -> impl IntoResponse {
+    match manager.health_check_all().await {
+        Ok(results) => (StatusCode::OK, Json(serde_json::to_value(results).unwrap())),
+        Err(e) => {
+            let error_chain = format!("{:#}", e);
+            error!("Failed to health check containers: {}", error_chain);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    serde_json::to_value(ApiError {
+                        error: "health_check_failed".to_string(),
+                        message: error_chain,
+                    })
+                    .unwrap(),
+                ),
+            )
+        }
+    }
+}
+
 /// 获取单个容器信息
 pub async fn get_container(
     State(manager): State<AppState>,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
     match manager.get_container(&id).await {
-        Ok(container) => (
-            StatusCode::OK,
-            Json(serde_json::to_value(container).unwrap()),
-        ),
+        Ok(mut container) => {
+            container.active_connections =
+                manager.fetch_proxy_container_connections(&container.id).await;
+            (
+                StatusCode::OK,
+                Json(serde_json::to_value(container).unwrap()),
+            )
+        }
         Err(e) => {
             let error_chain = format!("{:#}", e);
             error!("Failed to get container '{}': {}", id, error_chain);
@@ -206,6 +376,371 @@ pub async fn start_container(
     }
 }
 
+/// 从保存的结构化配置重新生成 config.toml 并重启容器
+pub async fn regenerate_container_config(
+    State(manager): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("Regenerating config for container: {}", id);
+    match manager.regenerate_config(&id).await {
+        Ok(()) => {
+            info!("Config regenerated: {}", id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => {
+            let error_chain = format!("{:#}", e);
+            error!(
+                "Failed to regenerate config for container '{}': {}",
+                id, error_chain
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    serde_json::to_value(ApiError {
+                        error: "regenerate_failed".to_string(),
+                        message: error_chain,
+                    })
+                    .unwrap(),
+                ),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// 轮换容器使用的 API 密钥并重启容器
+pub async fn rotate_container_keys(
+    State(manager): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<RotateKeysRequest>,
+) -> impl IntoResponse {
+    // 不记录密钥内容，只记录更新了哪些组件
+    info!(
+        "Rotating keys for container '{}': asr={}, llm={}, tts={}",
+        id,
+        request.asr.is_some(),
+        request.llm.is_some(),
+        request.tts.is_some()
+    );
+    match manager.rotate_keys(&id, request).await {
+        Ok(()) => {
+            info!("Keys rotated: {}", id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => {
+            let error_chain = format!("{:#}", e);
+            error!(
+                "Failed to rotate keys for container '{}': {}",
+                id, error_chain
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    serde_json::to_value(ApiError {
+                        error: "rotate_keys_failed".to_string(),
+                        message: error_chain,
+                    })
+                    .unwrap(),
+                ),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// 以源容器的配置部署新容器（可指定新镜像、端口、主机名、网络），
+/// 可选地将设备转到新容器并删除源容器
+pub async fn migrate_container(
+    State(manager): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<MigrateContainerRequest>,
+) -> impl IntoResponse {
+    info!(
+        "Migrating container '{}': image={:?}, rebind_devices={}, delete_source={}",
+        id, request.image, request.rebind_devices, request.delete_source
+    );
+    match manager.migrate_container(&id, &request).await {
+        Ok(response) => {
+            info!(
+                "Container migrated: {} -> {}",
+                response.source_container_id, response.deployment.container_id
+            );
+            (
+                StatusCode::OK,
+                Json(serde_json::to_value(response).unwrap()),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            let error_chain = format!("{:#}", e);
+            error!("Failed to migrate container '{}': {}", id, error_chain);
+            let (status, error) = deploy_error_status(&e, "migrate_failed");
+            (
+                status,
+                Json(
+                    serde_json::to_value(ApiError {
+                        error: error.to_string(),
+                        message: error_chain,
+                    })
+                    .unwrap(),
+                ),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub include_secrets: bool,
+}
+
+/// 导出文件内容：只包含结构化配置的部署请求，其余选项使用部署默认值
+fn export_deploy_request(config: EchoKitConfig) -> DeployRequest {
+    DeployRequest {
+        config,
+        port: None,
+        extra_ports: Vec::new(),
+        advertised_host: None,
+        hostname: None,
+        network: None,
+        auto_stop: false,
+        start: true,
+        wait_for_health: true,
+        description: None,
+    }
+}
+
+/// 导出容器配置为可下载的 JSON 文件（格式与部署请求一致，可直接重新部署）
+pub async fn export_container_config(
+    State(manager): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> impl IntoResponse {
+    info!(
+        "Exporting config for container: {} (include_secrets={})",
+        id, query.include_secrets
+    );
+    match manager.export_config(&id, query.include_secrets).await {
+        Ok(config) => {
+            let disposition = format!("attachment; filename=\"{}.echokit.json\"", config.name);
+            (
+                [(header::CONTENT_DISPOSITION, disposition)],
+                Json(export_deploy_request(config)),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            let error_chain = format!("{:#}", e);
+            error!(
+                "Failed to export config for container '{}': {}",
+                id, error_chain
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    serde_json::to_value(ApiError {
+                        error: "export_failed".to_string(),
+                        message: error_chain,
+                    })
+                    .unwrap(),
+                ),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// 比较容器已保存的配置与磁盘上的 config.toml
+pub async fn get_container_config_diff(
+    State(manager): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match manager.config_diff(&id).await {
+        Ok(diff) => {
+            if diff.drifted {
+                warn!("Config drift detected for container: {}", id);
+            }
+            (StatusCode::OK, Json(serde_json::to_value(diff).unwrap()))
+        }
+        Err(e) => {
+            let error_chain = format!("{:#}", e);
+            error!(
+                "Failed to diff config for container '{}': {}",
+                id, error_chain
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    serde_json::to_value(ApiError {
+                        error: "diff_failed".to_string(),
+                        message: error_chain,
+                    })
+                    .unwrap(),
+                ),
+            )
+        }
+    }
+}
+
+/// 开启容器排空模式
+pub async fn drain_container(
+    State(manager): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    set_container_draining(&manager, &id, true).await
+}
+
+/// 关闭容器排空模式
+pub async fn undrain_container(
+    State(manager): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    set_container_draining(&manager, &id, false).await
+}
+
+async fn set_container_draining(
+    manager: &DockerManager,
+    id: &str,
+    draining: bool,
+) -> axum::response::Response {
+    info!("Setting drain mode for container '{}': {}", id, draining);
+    match manager.set_draining(id, draining).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(
+                serde_json::to_value(ApiError {
+                    error: "not_found".to_string(),
+                    message: format!("Container {} not found", id),
+                })
+                .unwrap(),
+            ),
+        )
+            .into_response(),
+        Err(e) => {
+            let error_chain = format!("{:#}", e);
+            error!(
+                "Failed to set drain mode for container '{}': {}",
+                id, error_chain
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    serde_json::to_value(ApiError {
+                        error: "drain_failed".to_string(),
+                        message: error_chain,
+                    })
+                    .unwrap(),
+                ),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// 开启容器空闲自动停止
+pub async fn enable_auto_stop(
+    State(manager): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    set_container_auto_stop(&manager, &id, true).await
+}
+
+/// 关闭容器空闲自动停止
+pub async fn disable_auto_stop(
+    State(manager): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    set_container_auto_stop(&manager, &id, false).await
+}
+
+async fn set_container_auto_stop(
+    manager: &DockerManager,
+    id: &str,
+    enabled: bool,
+) -> axum::response::Response {
+    info!("Setting auto-stop for container '{}': {}", id, enabled);
+    match manager.set_auto_stop(id, enabled).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(
+                serde_json::to_value(ApiError {
+                    error: "not_found".to_string(),
+                    message: format!("Container {} not found", id),
+                })
+                .unwrap(),
+            ),
+        )
+            .into_response(),
+        Err(e) => {
+            let error_chain = format!("{:#}", e);
+            error!(
+                "Failed to set auto-stop for container '{}': {}",
+                id, error_chain
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    serde_json::to_value(ApiError {
+                        error: "auto_stop_failed".to_string(),
+                        message: error_chain,
+                    })
+                    .unwrap(),
+                ),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// 更新容器信息（目前为描述）
+pub async fn update_container(
+    State(manager): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<UpdateContainerRequest>,
+) -> impl IntoResponse {
+    let Some(description) = request.description else {
+        return StatusCode::NO_CONTENT.into_response();
+    };
+    if let Err(message) = manager.validate_description(&description) {
+        return invalid_deploy_config(message).into_response();
+    }
+
+    info!("Updating description for container '{}'", id);
+    match manager.set_description(&id, &description).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(
+                serde_json::to_value(ApiError {
+                    error: "not_found".to_string(),
+                    message: format!("Container {} not found", id),
+                })
+                .unwrap(),
+            ),
+        )
+            .into_response(),
+        Err(e) => {
+            let error_chain = format!("{:#}", e);
+            error!("Failed to update container '{}': {}", id, error_chain);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    serde_json::to_value(ApiError {
+                        error: "update_failed".to_string(),
+                        message: error_chain,
+                    })
+                    .unwrap(),
+                ),
+            )
+                .into_response()
+        }
+    }
+}
+
 /// 删除容器
 pub async fn delete_container(
     State(manager): State<AppState>,
@@ -238,15 +773,75 @@ pub async fn delete_container(
 #[derive(Deserialize)]
 pub struct LogsQuery {
     pub tail: Option<usize>,
+    /// 分页游标：返回早于该时间戳的日志
+    pub before: Option<String>,
+    /// 分页大小，设置 before 或 limit 时返回 JSON 分页结果
+    pub limit: Option<usize>,
+    /// 只返回容器本次（重新）启动之后的日志
+    #[serde(default)]
+    pub since_deploy: bool,
 }
 
+/// 默认分页大小
+const DEFAULT_LOGS_PAGE_LIMIT: usize = 200;
+/// 日志分页大小上限
+const MAX_LOGS_PAGE_LIMIT: usize = 1000;
+
 /// 获取容器日志
 pub async fn get_container_logs(
     State(manager): State<AppState>,
     Path(id): Path<String>,
     Query(query): Query<LogsQuery>,
 ) -> impl IntoResponse {
-    match manager.get_container_logs(&id, query.tail).await {
+    if query.before.is_some() || query.limit.is_some() {
+        if let Some(cursor) = query.before.as_deref() {
+            if parse_logs_cursor(cursor).is_none() {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(
+                        serde_json::to_value(ApiError {
+                            error: "invalid_request".to_string(),
+                            message: format!("Invalid logs cursor: {}", cursor),
+                        })
+                        .unwrap(),
+                    ),
+                )
+                    .into_response();
+            }
+        }
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_LOGS_PAGE_LIMIT)
+            .clamp(1, MAX_LOGS_PAGE_LIMIT);
+        return match manager
+            .get_container_logs_page(&id, query.before.as_deref(), limit)
+            .await
+        {
+            Ok(page) => (StatusCode::OK, Json(serde_json::to_value(page).unwrap())).into_response(),
+            Err(e) => {
+                let error_chain = format!("{:#}", e);
+                error!(
+                    "Failed to get logs page for container '{}': {}",
+                    id, error_chain
+                );
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(
+                        serde_json::to_value(ApiError {
+                            error: "logs_failed".to_string(),
+                            message: error_chain,
+                        })
+                        .unwrap(),
+                    ),
+                )
+                    .into_response()
+            }
+        };
+    }
+
+    match manager
+        .get_container_logs(&id, query.tail, query.since_deploy)
+        .await {
         Ok(logs) => (StatusCode::OK, logs).into_response(),
         Err(e) => {
             let error_chain = format!("{:#}", e);
@@ -266,9 +861,188 @@ pub async fn get_container_logs(
     }
 }
 
+#[derive(Deserialize)]
+pub struct LogStreamQuery {
+    /// 逗号分隔的容器 ID 或名称
+    pub ids: String,
+    /// 开始跟随前先输出每个容器最近的行数
+    pub tail: Option<usize>,
+}
+
+/// 聚合日志流默认先输出的历史行数
+const DEFAULT_LOG_STREAM_TAIL: usize = 50;
+
+/// 通过 WebSocket 推送多个容器的聚合日志流，每行带容器名前缀
+pub async fn stream_containers_logs(
+    State(manager): State<AppState>,
+    Query(query): Query<LogStreamQuery>,
+    ws: WebSocketUpgrade,
+) -> axum::response::Response {
+    let mut ids: Vec<String> = Vec::new();
+    for id in query
+        .ids
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+    {
+        if !ids.iter().any(|existing| existing == id) {
+            ids.push(id.to_string());
+        }
+    }
+
+    let invalid = |message: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(
+                serde_json::to_value(ApiError {
+                    error: "invalid_request".to_string(),
+                    message,
+                })
+                .unwrap(),
+            ),
+        )
+            .into_response()
+    };
+    if ids.is_empty() {
+        return invalid("ids must list at least one container".to_string());
+    }
+    if ids.len() > MAX_LOG_STREAM_CONTAINERS {
+        return invalid(format!(
+            "At most {} containers can be streamed at once",
+            MAX_LOG_STREAM_CONTAINERS
+        ));
+    }
+
+    let tail = query.tail.unwrap_or(DEFAULT_LOG_STREAM_TAIL);
+    let lines = match manager.follow_containers_logs(&ids, tail).await {
+        Ok(lines) => lines,
+        Err(e) => {
+            let error_chain = format!("{:#}", e);
+            warn!("Failed to open aggregated log stream: {}", error_chain);
+            return (
+                StatusCode::NOT_FOUND,
+                Json(
+                    serde_json::to_value(ApiError {
+                        error: "not_found".to_string(),
+                        message: error_chain,
+                    })
+                    .unwrap(),
+                ),
+            )
+                .into_response();
+        }
+    };
+
+    info!("开始推送聚合日志流: {}", ids.join(","));
+    ws.on_upgrade(move |socket| forward_log_stream(socket, lines))
+}
+
+/// 将日志行逐条推送给客户端，直到客户端断开或所有容器日志流结束
+///
+/// 返回时丢弃日志流，所有容器的子日志流随之关闭。
+async fn forward_log_stream(mut socket: WebSocket, mut lines: BoxStream<'static, String>) {
+    loop {
+        tokio::select! {
+            line = lines.next() => match line {
+                Some(line) => {
+                    if socket.send(Message::Text(line.into())).await.is_err() {
+                        break;
+                    }
+                }
+                None => {
+                    let _ = socket.send(Message::Close(None)).await;
+                    break;
+                }
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    info!("聚合日志流已关闭");
+}
+
+/// 限流响应：429 带 Retry-After 头（秒，向上取整）与响应体中的 retry_after_ms
+///
+/// 所有返回 429 的接口都应使用该函数，保证客户端得到一致的退避提示。
+pub(super) fn too_many_requests(
+    error: &str,
+    message: String,
+    retry_after: std::time::Duration,
+) -> axum::response::Response {
+    let retry_after_ms = retry_after.as_millis().max(1) as u64;
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(
+            header::RETRY_AFTER,
+            retry_after_ms.div_ceil(1000).to_string(),
+        )],
+        Json(RateLimitedError {
+            error: ApiError {
+                error: error.to_string(),
+                message,
+            },
+            retry_after_ms,
+        }),
+    )
+        .into_response()
+}
+
 /// 健康检查（服务自身）
 pub async fn health_check() -> impl IntoResponse {
-    (StatusCode::OK, Json(serde_json::json!({ "status": "ok" })))
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "status": "ok", "version": BuildInfo::current() })),
+    )
+}
+
+/// 为超时中间件产生的 504 响应补充 JSON 错误体
+pub async fn timeout_error_body(response: Response) -> Response {
+    let is_timeout = response.status() == StatusCode::GATEWAY_TIMEOUT
+        && !response.headers().contains_key(header::CONTENT_TYPE);
+    if !is_timeout {
+        return response;
+    }
+    (
+        StatusCode::GATEWAY_TIMEOUT,
+        Json(ApiError {
+            error: "timeout".to_string(),
+            message: "Request timed out".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// 将请求体大小限制产生的纯文本 413 响应替换为 JSON 错误体
+pub async fn payload_too_large_body(response: Response) -> Response {
+    let is_plain_text = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/plain"));
+    if response.status() != StatusCode::PAYLOAD_TOO_LARGE || !is_plain_text {
+        return response;
+    }
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(ApiError {
+            error: "payload_too_large".to_string(),
+            message: "Request body too large".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// 未匹配路由（返回统一的 JSON 错误）
+pub async fn route_not_found() -> impl IntoResponse {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ApiError {
+            error: "not_found".to_string(),
+            message: "Route not found".to_string(),
+        }),
+    )
 }
 
 /// 获取容器健康检查
@@ -295,3 +1069,186 @@ pub async fn get_container_health(
         ),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::extract::FromRequest;
+    use axum::http::{header, Request};
+
+    async fn rejection_for(body: &'static str) -> JsonRejection {
+        let request = Request::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        Json::<EchoKitConfig>::from_request(request, &())
+            .await
+            .expect_err("body should be rejected")
+    }
+
+    #[tokio::test]
+    async fn empty_elevenlabs_token_is_rejected_before_deploy() {
+        let mut config = serde_json::to_value(EchoKitConfig::sample()).unwrap();
+        config["tts"] = serde_json::json!({
+            "platform": "Elevenlabs",
+            "token": "  ",
+            "voice": "Rachel",
+        });
+        let payload = Json::from_bytes(
+            serde_json::to_vec(&serde_json::json!({ "config": config }))
+                .unwrap()
+                .as_slice(),
+        );
+
+        // 测试用的 Docker 地址不可达：若校验未拦截，将返回 503 而不是 400
+        let manager = Arc::new(DockerManager::for_tests(crate::config::AppConfig::default()));
+        let response = deploy(State(manager), payload).await.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = body_json(response).await;
+        assert!(
+            body["message"]
+                .as_str()
+                .unwrap()
+                .contains("tts.token is required for ElevenLabs"),
+            "{body}"
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable_docker_deploy_returns_503() {
+        let payload = Json::from_bytes(
+            serde_json::to_vec(&serde_json::json!({ "config": EchoKitConfig::sample() }))
+                .unwrap()
+                .as_slice(),
+        );
+
+        // 测试用的 Docker 地址不可达
+        let manager = Arc::new(DockerManager::for_tests(crate::config::AppConfig::default()));
+        let response = deploy(State(manager), payload).await.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = body_json(response).await;
+        assert_eq!(body["error"], "docker_unavailable");
+        assert!(
+            body["message"]
+                .as_str()
+                .unwrap()
+                .starts_with("Docker daemon unavailable"),
+            "{body}"
+        );
+    }
+
+    #[tokio::test]
+    async fn rate_limited_response_carries_retry_hint() {
+        let response = too_many_requests(
+            "RateLimited",
+            "try again later".to_string(),
+            std::time::Duration::from_millis(7000),
+        );
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "7");
+        let body = body_json(response).await;
+        assert_eq!(body["error"], "RateLimited");
+        assert_eq!(body["message"], "try again later");
+        assert_eq!(body["retry_after_ms"], 7000);
+    }
+
+    #[test]
+    fn export_masks_secrets_unless_requested() {
+        let Query(query) =
+            Query::<ExportQuery>::try_from_uri(&"/api/containers/abc/export".parse().unwrap())
+                .unwrap();
+        assert!(!query.include_secrets);
+
+        let Query(query) = Query::<ExportQuery>::try_from_uri(
+            &"/api/containers/abc/export?include_secrets=true"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(query.include_secrets);
+    }
+
+    #[test]
+    fn masked_export_can_be_reimported() {
+        let mut config = EchoKitConfig::sample();
+        config.mask_secrets();
+        let exported = serde_json::to_string(&export_deploy_request(config)).unwrap();
+        assert!(!exported.contains("sk-"));
+
+        let request: DeployRequest = serde_json::from_str(&exported).unwrap();
+        assert!(request.config.has_masked_secrets());
+        assert_eq!(request.config.llm.model, "gpt-4o-mini");
+    }
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn health_reports_build_info() {
+        let response = health_check().await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_json(response).await;
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["version"]["version"], env!("CARGO_PKG_VERSION"));
+        assert!(!body["version"]["git_sha"].as_str().unwrap().is_empty());
+        assert!(body["version"]["build_timestamp"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn deploy_error_status_maps_busy_host_port_to_409() {
+        let e = anyhow::Error::new(DeployError::HostPortInUse(8080))
+            .context("Failed to deploy container");
+        assert_eq!(
+            deploy_error_status(&e, "deploy_failed"),
+            (StatusCode::CONFLICT, "port_in_use")
+        );
+        let e = anyhow::Error::new(DeployError::PortReserved(8080));
+        assert_eq!(
+            deploy_error_status(&e, "deploy_failed"),
+            (StatusCode::CONFLICT, "port_in_use")
+        );
+        assert_eq!(
+            deploy_error_status(&anyhow::anyhow!("boom"), "deploy_failed"),
+            (StatusCode::INTERNAL_SERVER_ERROR, "deploy_failed")
+        );
+    }
+
+    #[tokio::test]
+    async fn payload_too_large_body_replaces_plain_text_413() {
+        let plain = (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            [(header::CONTENT_TYPE, "text/plain")],
+            "length limit exceeded",
+        )
+            .into_response();
+        let response = payload_too_large_body(plain).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(body_json(response).await["error"], "payload_too_large");
+
+        // 其他响应保持不变
+        let ok = payload_too_large_body(StatusCode::OK.into_response()).await;
+        assert_eq!(ok.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn invalid_json_request_maps_unknown_fields_to_400() {
+        let (status, Json(body)) = invalid_json_request(rejection_for(r#"{"nmae":"x"}"#).await);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_request");
+        assert!(body["message"].as_str().unwrap().contains("nmae"));
+    }
+
+    #[tokio::test]
+    async fn invalid_json_request_keeps_syntax_error_status() {
+        let (status, Json(body)) = invalid_json_request(rejection_for("{").await);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_request");
+    }
+}
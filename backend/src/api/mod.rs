@@ -1,4 +1,6 @@
+mod admin_handlers;
 mod device_handlers;
+mod group_handlers;
 mod handlers;
 pub mod router;
 
@@ -1,61 +1,338 @@
 use axum::{
-    routing::{delete, get, post},
+    extract::{DefaultBodyLimit, FromRef},
+    http::StatusCode,
+    middleware,
+    routing::{delete, get, patch, post, put},
     Router,
 };
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
+use tower_http::services::{ServeDir, ServeFile};
+use tower_http::timeout::TimeoutLayer;
 
+use super::admin_handlers::{admin_overview, admin_ports};
 use super::device_handlers::{
-    bind_device_to_server, delete_device, get_device, list_devices, register_device,
-    unbind_device,
+    bind_device_to_server, delete_device, get_device, get_device_connection, get_device_ws_target,
+    import_devices, list_container_devices, list_device_connections, list_devices,
+    query_device_connection_log, register_device, test_device_route, unbind_device,
+};
+use super::group_handlers::{
+    bind_group_to_server, create_group, delete_group, get_group, list_groups, unbind_group,
+    update_group,
 };
 use super::handlers::{
-    delete_container, deploy, get_container, get_container_health, get_container_logs,
-    health_check, list_containers, start_container, stop_container,
+    delete_container, deploy, disable_auto_stop, drain_container, enable_auto_stop,
+    export_container_config, get_container, get_container_config_diff, get_container_health,
+    get_container_logs, health_check, health_check_all_containers, list_containers, list_platforms,
+    migrate_container, payload_too_large_body, regenerate_container_config, rotate_container_keys,
+    route_not_found, start_container, stop_container, stream_containers_logs, timeout_error_body,
+    undrain_container, update_container, validate_config,
 };
 use crate::docker::DockerManager;
-use crate::store::PgDeviceStore;
+use crate::store::{PgDeviceStore, PgGroupStore};
 
-#[derive(Clone)]
+#[derive(Clone, FromRef)]
 pub struct AppState {
     pub docker_manager: Arc<DockerManager>,
     pub device_store: Arc<PgDeviceStore>,
+    pub group_store: Arc<PgGroupStore>,
 }
 
-pub fn create_router(state: AppState) -> Router {
+pub fn create_router(
+    state: AppState,
+    static_dir: Option<&str>,
+    max_request_body_bytes: usize,
+    request_timeout: Duration,
+    deploy_timeout: Duration,
+) -> Router {
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
         .allow_headers(Any);
 
+    // 部署路由（可能需要拉取镜像，使用单独的更长超时）
+    let deploy_routes = Router::new()
+        .route("/deploy", post(deploy))
+        .route("/containers/{id}/migrate", post(migrate_container))
+        .layer(TimeoutLayer::with_status_code(
+            StatusCode::GATEWAY_TIMEOUT,
+            deploy_timeout,
+        ))
+        .with_state(state.docker_manager.clone());
+
     // 容器管理路由
     let container_routes = Router::new()
-        .route("/deploy", post(deploy))
+        .route("/config/platforms", get(list_platforms))
+        .route("/config/validate", post(validate_config))
         .route("/containers", get(list_containers))
+        .route(
+            "/containers/health-check-all",
+            get(health_check_all_containers),
+        )
         .route("/containers/{id}", get(get_container))
         .route("/containers/{id}", delete(delete_container))
+        .route("/containers/{id}", patch(update_container))
         .route("/containers/{id}/start", post(start_container))
         .route("/containers/{id}/stop", post(stop_container))
         .route("/containers/{id}/logs", get(get_container_logs))
+        .route("/containers/logs/stream", get(stream_containers_logs))
         .route("/containers/{id}/health", get(get_container_health))
+        .route(
+            "/containers/{id}/regenerate-config",
+            post(regenerate_container_config),
+        )
+        .route("/containers/{id}/rotate-keys", post(rotate_container_keys))
+        .route("/containers/{id}/export", get(export_container_config))
+        .route("/containers/{id}/config/diff", get(get_container_config_diff))
+        .route("/containers/{id}/drain", post(drain_container))
+        .route("/containers/{id}/drain", delete(undrain_container))
+        .route("/containers/{id}/auto-stop", post(enable_auto_stop))
+        .route("/containers/{id}/auto-stop", delete(disable_auto_stop))
         .with_state(state.docker_manager.clone());
 
     // 设备管理路由
     let device_routes = Router::new()
         .route("/devices", get(list_devices))
         .route("/devices", post(register_device))
+        .route("/devices/import", post(import_devices))
         .route("/devices/{id}", get(get_device))
         .route("/devices/{id}", delete(delete_device))
         .route("/devices/{id}/bind", post(bind_device_to_server))
         .route("/devices/{id}/unbind", post(unbind_device))
-        .with_state(state.device_store);
+        .route("/devices/{id}/connections", get(list_device_connections))
+        .route(
+            "/devices/{id}/connection-log",
+            get(query_device_connection_log),
+        )
+        .route("/devices/{id}/ws-target", get(get_device_ws_target))
+        .route("/devices/{id}/connection", get(get_device_connection))
+        .route("/devices/{id}/test-route", post(test_device_route))
+        .route("/containers/{id}/devices", get(list_container_devices))
+        .with_state(state.clone());
+
+    // 设备分组路由
+    let group_routes = Router::new()
+        .route("/groups", get(list_groups))
+        .route("/groups", post(create_group))
+        .route("/groups/{id}", get(get_group))
+        .route("/groups/{id}", put(update_group))
+        .route("/groups/{id}", delete(delete_group))
+        .route("/groups/{id}/bind", post(bind_group_to_server))
+        .route("/groups/{id}/unbind", post(unbind_group))
+        .with_state(state.clone());
+
+    // 管理路由
+    let admin_routes = Router::new()
+        .route("/admin/overview", get(admin_overview))
+        .route("/admin/ports", get(admin_ports))
+        .with_state(state);
 
     let api_routes = Router::new()
         .merge(container_routes)
-        .merge(device_routes);
+        .merge(device_routes)
+        .merge(group_routes)
+        .merge(admin_routes)
+        // 未匹配的 /api 路径始终返回 JSON 错误，不回退到前端页面
+        .fallback(route_not_found)
+        .layer(TimeoutLayer::with_status_code(
+            StatusCode::GATEWAY_TIMEOUT,
+            request_timeout,
+        ))
+        .merge(deploy_routes)
+        .layer(middleware::map_response(timeout_error_body))
+        // 限制请求体大小，超出时返回 413（替代 axum 默认的 2 MiB 提取器限制）
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(max_request_body_bytes))
+        .layer(middleware::map_response(payload_too_large_body));
 
-    Router::new()
+    let mut router = Router::new()
         .route("/health", get(health_check))
-        .nest("/api", api_routes)
+        .nest("/api", api_routes);
+
+    // 可选：提供前端静态文件，未匹配的路径回退到 index.html（SPA 路由）
+    if let Some(dir) = static_dir {
+        let index = Path::new(dir).join("index.html");
+        router = router.fallback_service(ServeDir::new(dir).fallback(ServeFile::new(index)));
+    } else {
+        router = router.fallback(route_not_found);
+    }
+
+    router
+        // 根据 Accept-Encoding 压缩响应（默认跳过已压缩内容、图片和 SSE 流）
+        .layer(CompressionLayer::new())
         .layer(cors)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use axum::body::Body;
+    use axum::http::{header, Request};
+    use tower::ServiceExt;
+
+    fn test_router(static_dir: Option<&str>) -> Router {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/echokit")
+            .unwrap();
+        let state = AppState {
+            docker_manager: Arc::new(DockerManager::for_tests(AppConfig::default())),
+            device_store: Arc::new(PgDeviceStore::new(pool.clone())),
+            group_store: Arc::new(PgGroupStore::new(pool)),
+        };
+        create_router(
+            state,
+            static_dir,
+            1024 * 1024,
+            Duration::from_secs(5),
+            Duration::from_secs(5),
+        )
+    }
+
+    async fn get(router: Router, uri: &str) -> (StatusCode, String, Vec<u8>) {
+        let response = router
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, content_type, body.to_vec())
+    }
+
+    fn assert_not_found_json(status: StatusCode, content_type: &str, body: &[u8]) {
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(content_type, "application/json");
+        let body: serde_json::Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(body["error"], "not_found");
+        assert_eq!(body["message"], "Route not found");
+    }
+
+    #[tokio::test]
+    async fn unknown_routes_return_json_404() {
+        let (status, content_type, body) = get(test_router(None), "/api/no-such-route").await;
+        assert_not_found_json(status, &content_type, &body);
+
+        let (status, content_type, body) = get(test_router(None), "/no-such-page").await;
+        assert_not_found_json(status, &content_type, &body);
+    }
+
+    #[tokio::test]
+    async fn static_fallback_does_not_shadow_api_404() {
+        let dir = std::env::temp_dir().join(format!("echokit-static-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("index.html"), "<html>echokit</html>").unwrap();
+        let static_dir = dir.to_string_lossy().into_owned();
+
+        let (status, _, body) = get(test_router(Some(&static_dir)), "/devices/abc").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"<html>echokit</html>");
+
+        let (status, content_type, body) =
+            get(test_router(Some(&static_dir)), "/api/no-such-route").await;
+        assert_not_found_json(status, &content_type, &body);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn static_assets_are_served_without_shadowing_health() {
+        let dir = std::env::temp_dir().join(format!("echokit-assets-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(dir.join("index.html"), "<html>echokit</html>").unwrap();
+        std::fs::write(dir.join("assets/app.js"), "console.log('echokit')").unwrap();
+        let static_dir = dir.to_string_lossy().into_owned();
+
+        let (status, content_type, body) =
+            get(test_router(Some(&static_dir)), "/assets/app.js").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, "text/javascript");
+        assert_eq!(body, b"console.log('echokit')");
+
+        // 深层前端路由同样回退到 index.html
+        let (status, _, body) = get(test_router(Some(&static_dir)), "/containers/abc/logs").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"<html>echokit</html>");
+
+        let (status, content_type, body) = get(test_router(Some(&static_dir)), "/health").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, "application/json");
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["status"], "ok");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn json_responses_are_compressed_on_request() {
+        let fetch = |accept_encoding: Option<&'static str>| async move {
+            let mut request = Request::get("/api/config/platforms");
+            if let Some(encoding) = accept_encoding {
+                request = request.header(header::ACCEPT_ENCODING, encoding);
+            }
+            let response = test_router(None)
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let encoding = response
+                .headers()
+                .get(header::CONTENT_ENCODING)
+                .map(|v| v.to_str().unwrap().to_string());
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (encoding, body)
+        };
+
+        let (encoding, plain) = fetch(None).await;
+        assert_eq!(encoding, None);
+        assert!(serde_json::from_slice::<serde_json::Value>(&plain).is_ok());
+
+        let (encoding, gzipped) = fetch(Some("gzip")).await;
+        assert_eq!(encoding.as_deref(), Some("gzip"));
+        // gzip 魔数
+        assert_eq!(&gzipped[..2], &[0x1f, 0x8b]);
+        assert!(gzipped.len() < plain.len());
+
+        let (encoding, _) = fetch(Some("br")).await;
+        assert_eq!(encoding.as_deref(), Some("br"));
+    }
+
+    #[tokio::test]
+    async fn slow_handler_is_cut_off_with_json_504() {
+        // 与 create_router 相同的超时层组合
+        let router = Router::new()
+            .route(
+                "/slow",
+                axum::routing::get(|| async {
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                    "done"
+                }),
+            )
+            .layer(TimeoutLayer::with_status_code(
+                StatusCode::GATEWAY_TIMEOUT,
+                Duration::from_millis(50),
+            ))
+            .layer(middleware::map_response(timeout_error_body));
+
+        let started = std::time::Instant::now();
+        let (status, content_type, body) = get(router, "/slow").await;
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(content_type, "application/json");
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "timeout");
+    }
+}
@@ -1,5 +1,7 @@
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::path::PathBuf;
 
 /// 应用配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,12 +18,78 @@ pub struct AppConfig {
     pub record_dir: String,
     /// 默认 hello.wav 路径
     pub hello_wav_path: String,
+    /// 相对路径的基准目录（可选，未设置时使用启动时的工作目录）
+    pub data_base_dir: Option<String>,
     /// 容器端口范围起始
     pub port_range_start: u16,
     /// 容器端口范围结束
     pub port_range_end: u16,
     /// 外部访问地址（可选，用于替换 localhost）
     pub external_host: Option<String>,
+    /// LLM 对话历史轮数默认值（请求未指定时使用）
+    pub llm_history_default: u32,
+    /// LLM 对话历史轮数上限
+    pub llm_history_max: u32,
+    /// Proxy 健康检查地址（可选，用于获取活跃连接数）
+    pub proxy_health_url: Option<String>,
+    /// Proxy WebSocket 地址（可选，如 ws://localhost:10086，用于设备路由测试）
+    pub proxy_ws_url: Option<String>,
+    /// 设备访问 Proxy 使用的对外 WebSocket 地址（可选，如 wss://echokit.example.com）
+    pub device_proxy_url: Option<String>,
+    /// 前端静态文件目录（可选，设置后由后端直接提供前端页面）
+    pub static_dir: Option<String>,
+    /// API 请求体大小上限（字节），超出返回 413
+    pub max_request_body_bytes: usize,
+    /// 启动时连接数据库的最大尝试次数（数据库尚未就绪时按退避间隔重试）
+    pub db_connect_max_attempts: u32,
+    /// 数据库连接重试的初始间隔（秒），每次失败后翻倍，最长 30 秒
+    pub db_connect_retry_interval_secs: u64,
+    /// API 请求处理超时（秒），超时返回 504
+    pub request_timeout_secs: u64,
+    /// 部署请求处理超时（秒，部署可能需要拉取镜像，默认更长）
+    pub deploy_timeout_secs: u64,
+    /// 部署后等待容器就绪的默认超时（秒）
+    pub ready_timeout_secs: u64,
+    /// 按镜像名或 TTS 平台覆盖的就绪超时（秒），如本地 GSV 模型冷启动较慢
+    pub ready_timeout_overrides: Vec<(String, u64)>,
+    /// 允许的 ASR/LLM/TTS 服务端点主机白名单（为空时允许任意公网主机）
+    pub allowed_endpoint_hosts: Vec<String>,
+    /// 容器 HTTP 健康检查路径
+    pub health_check_path: String,
+    /// 健康检查视为健康的状态码区间（闭区间，为空时任意响应都视为健康）
+    pub health_check_accepted_statuses: Vec<(u16, u16)>,
+    /// Docker 与 containers 表对账间隔（秒，0 表示关闭）
+    pub reconcile_interval_secs: u64,
+    /// 对账时删除 Docker 中已不存在的容器记录
+    pub reconcile_remove_stale: bool,
+    /// 对账时为缺少记录的受管容器补录（作为无结构化配置的容器）
+    pub reconcile_adopt_orphans: bool,
+    /// 空闲自动停止的检查间隔（秒，0 表示关闭）
+    pub auto_stop_check_interval_secs: u64,
+    /// 开启自动停止的容器无设备连接多久后停止（秒）
+    pub auto_stop_idle_secs: u64,
+    /// 数据库中密钥字段的加密密钥（base64 编码的 32 字节，可选）
+    #[serde(skip_serializing)]
+    pub secrets_encryption_key: Option<String>,
+    /// 设备连接令牌的签名密钥（与 Proxy 共享，可选；设置后连接参数中的 Proxy 地址带签名令牌）
+    #[serde(skip_serializing)]
+    pub device_token_secret: Option<String>,
+    /// 设备连接令牌有效期（秒）
+    pub device_token_ttl_secs: u64,
+    /// config.toml 的写入目录（可选，如 tmpfs 路径，未设置时使用 config_dir）
+    pub rendered_config_dir: Option<String>,
+    /// 允许作为容器对外主机名的受管域名（容器可使用其子域名）
+    pub managed_domains: Vec<String>,
+    /// 容器默认加入的 Docker 网络（未设置时使用默认 bridge 网络）
+    pub docker_network: Option<String>,
+    /// 请求未指定服务地址时使用的默认端点
+    pub endpoint_defaults: EndpointDefaults,
+    /// 系统提示词等长文本字段的最大字符数
+    pub max_prompt_length: usize,
+    /// 名称、模型等短文本字段的最大字符数
+    pub max_text_field_length: usize,
+    /// 生成的 config.toml 最大字节数
+    pub max_config_toml_bytes: usize,
 }
 
 impl Default for AppConfig {
@@ -33,9 +101,41 @@ impl Default for AppConfig {
             config_dir: "./data/configs".to_string(),
             record_dir: "./data/records".to_string(),
             hello_wav_path: "./data/hello.wav".to_string(),
+            data_base_dir: None,
             port_range_start: 8080,
             port_range_end: 8180,
             external_host: None,
+            llm_history_default: 5,
+            llm_history_max: 50,
+            proxy_health_url: None,
+            proxy_ws_url: None,
+            device_proxy_url: None,
+            static_dir: None,
+            max_request_body_bytes: 1024 * 1024,
+            db_connect_max_attempts: 10,
+            db_connect_retry_interval_secs: 2,
+            request_timeout_secs: 30,
+            deploy_timeout_secs: 300,
+            ready_timeout_secs: 30,
+            ready_timeout_overrides: Vec::new(),
+            allowed_endpoint_hosts: Vec::new(),
+            health_check_path: "/".to_string(),
+            health_check_accepted_statuses: Vec::new(),
+            reconcile_interval_secs: 300,
+            reconcile_remove_stale: false,
+            reconcile_adopt_orphans: false,
+            auto_stop_check_interval_secs: 15,
+            auto_stop_idle_secs: 1800,
+            secrets_encryption_key: None,
+            device_token_secret: None,
+            device_token_ttl_secs: 3600,
+            rendered_config_dir: None,
+            managed_domains: Vec::new(),
+            docker_network: None,
+            endpoint_defaults: EndpointDefaults::default(),
+            max_prompt_length: 16 * 1024,
+            max_text_field_length: 256,
+            max_config_toml_bytes: 64 * 1024,
         }
     }
 }
@@ -55,6 +155,7 @@ impl AppConfig {
             record_dir: env::var("RECORD_DIR").unwrap_or_else(|_| "./data/records".to_string()),
             hello_wav_path: env::var("HELLO_WAV_PATH")
                 .unwrap_or_else(|_| "./data/hello.wav".to_string()),
+            data_base_dir: env::var("DATA_BASE_DIR").ok(),
             port_range_start: env::var("PORT_RANGE_START")
                 .ok()
                 .and_then(|s| s.parse().ok())
@@ -64,7 +165,192 @@ impl AppConfig {
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(8180),
             external_host: env::var("EXTERNAL_HOST").ok(),
+            llm_history_default: env::var("LLM_HISTORY_DEFAULT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(5),
+            llm_history_max: env::var("LLM_HISTORY_MAX")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(50),
+            proxy_health_url: env::var("PROXY_HEALTH_URL").ok(),
+            proxy_ws_url: env::var("PROXY_WS_URL").ok().filter(|s| !s.is_empty()),
+            device_proxy_url: env::var("DEVICE_PROXY_URL").ok().filter(|s| !s.is_empty()),
+            static_dir: env::var("STATIC_DIR").ok(),
+            max_request_body_bytes: env::var("MAX_REQUEST_BODY_BYTES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|limit| *limit > 0)
+                .unwrap_or(1024 * 1024),
+            db_connect_max_attempts: env::var("DB_CONNECT_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|attempts| *attempts > 0)
+                .unwrap_or(10),
+            db_connect_retry_interval_secs: env::var("DB_CONNECT_RETRY_INTERVAL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(2),
+            request_timeout_secs: env::var("REQUEST_TIMEOUT_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|secs| *secs > 0)
+                .unwrap_or(30),
+            deploy_timeout_secs: env::var("DEPLOY_TIMEOUT_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|secs| *secs > 0)
+                .unwrap_or(300),
+            ready_timeout_secs: env::var("READY_TIMEOUT_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|secs| *secs > 0)
+                .unwrap_or(30),
+            ready_timeout_overrides: env::var("READY_TIMEOUT_OVERRIDES")
+                .map(|s| parse_timeout_overrides(&s))
+                .unwrap_or_default(),
+            allowed_endpoint_hosts: env::var("ALLOWED_ENDPOINT_HOSTS")
+                .map(|s| {
+                    s.split(',')
+                        .map(|host| host.trim().to_string())
+                        .filter(|host| !host.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            health_check_path: env::var("HEALTH_CHECK_PATH")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| "/".to_string()),
+            health_check_accepted_statuses: env::var("HEALTH_CHECK_ACCEPTED_STATUS")
+                .map(|s| parse_status_ranges(&s))
+                .unwrap_or_default(),
+            reconcile_interval_secs: env::var("RECONCILE_INTERVAL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(300),
+            reconcile_remove_stale: env::var("RECONCILE_REMOVE_STALE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+            reconcile_adopt_orphans: env::var("RECONCILE_ADOPT_ORPHANS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+            auto_stop_check_interval_secs: env::var("AUTO_STOP_CHECK_INTERVAL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(15),
+            auto_stop_idle_secs: env::var("AUTO_STOP_IDLE_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1800),
+            secrets_encryption_key: env::var("SECRETS_ENCRYPTION_KEY")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            device_token_secret: env::var("DEVICE_TOKEN_SECRET")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            device_token_ttl_secs: env::var("DEVICE_TOKEN_TTL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|secs| *secs > 0)
+                .unwrap_or(3600),
+            rendered_config_dir: env::var("RENDERED_CONFIG_DIR")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            managed_domains: env::var("MANAGED_DOMAINS")
+                .map(|s| {
+                    s.split(',')
+                        .map(|domain| domain.trim().to_string())
+                        .filter(|domain| !domain.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            docker_network: env::var("DOCKER_NETWORK")
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
+            endpoint_defaults: EndpointDefaults::from_env(),
+            max_prompt_length: env::var("MAX_PROMPT_LENGTH")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|limit| *limit > 0)
+                .unwrap_or(16 * 1024),
+            max_text_field_length: env::var("MAX_TEXT_FIELD_LENGTH")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|limit| *limit > 0)
+                .unwrap_or(256),
+            max_config_toml_bytes: env::var("MAX_CONFIG_TOML_BYTES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|limit| *limit > 0)
+                .unwrap_or(64 * 1024),
+        }
+    }
+
+    /// 健康检查是否接受该状态码
+    pub fn is_accepted_health_status(&self, status: u16) -> bool {
+        self.health_check_accepted_statuses.is_empty()
+            || self
+                .health_check_accepted_statuses
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&status))
+    }
+
+    /// 部署后等待容器就绪的超时（秒）
+    ///
+    /// 镜像名的覆盖优先于 TTS 平台（平台标识不区分大小写），都未配置时使用默认值。
+    pub fn ready_timeout_for(&self, image: &str, tts_platform: &str) -> u64 {
+        let overrides = &self.ready_timeout_overrides;
+        overrides
+            .iter()
+            .find(|(key, _)| key == image)
+            .or_else(|| {
+                overrides
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(tts_platform))
+            })
+            .map(|(_, secs)| *secs)
+            .unwrap_or(self.ready_timeout_secs)
+    }
+
+    /// 将数据目录和文件路径解析为绝对路径，使行为与启动时的工作目录无关
+    ///
+    /// 相对路径基于 `data_base_dir`（未设置时为当前工作目录）解析；
+    /// 配置目录、录音目录（以及 config.toml 写入目录）会被创建，无法创建时直接返回错误。
+    pub fn resolve_paths(&mut self) -> Result<()> {
+        let base = match &self.data_base_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => env::current_dir().context("Failed to get current directory")?,
+        };
+        let base = if base.is_absolute() {
+            base
+        } else {
+            env::current_dir()
+                .context("Failed to get current directory")?
+                .join(base)
+        };
+
+        let dirs = [&mut self.config_dir, &mut self.record_dir]
+            .into_iter()
+            .chain(self.rendered_config_dir.as_mut());
+        for dir in dirs {
+            let path = base.join(dir.as_str());
+            std::fs::create_dir_all(&path)
+                .with_context(|| format!("Failed to create directory: {}", path.display()))?;
+            let path = path
+                .canonicalize()
+                .with_context(|| format!("Failed to resolve directory: {}", path.display()))?;
+            *dir = path.to_string_lossy().into_owned();
         }
+
+        // hello.wav 是可选文件，不存在时只做路径拼接
+        let hello_wav = base.join(&self.hello_wav_path);
+        let hello_wav = hello_wav.canonicalize().unwrap_or(hello_wav);
+        self.hello_wav_path = hello_wav.to_string_lossy().into_owned();
+
+        Ok(())
     }
 
     /// 获取容器的 host 地址
@@ -73,3 +359,176 @@ impl AppConfig {
         self.external_host.as_deref().unwrap_or("localhost")
     }
 }
+
+/// 生成 config.toml 时各平台的默认服务地址（请求未指定 url 时使用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointDefaults {
+    /// OpenAI ASR 地址
+    pub openai_asr_url: String,
+    /// ASR 使用的 VAD 服务地址
+    pub asr_vad_url: String,
+    /// OpenAI TTS 地址
+    pub openai_tts_url: String,
+    /// Groq TTS 地址
+    pub groq_tts_url: String,
+}
+
+impl Default for EndpointDefaults {
+    fn default() -> Self {
+        Self {
+            openai_asr_url: "https://api.openai.com/v1/audio/transcriptions".to_string(),
+            asr_vad_url: "http://host.docker.internal:8000/v1/audio/vad".to_string(),
+            openai_tts_url: "https://api.openai.com/v1/audio/speech".to_string(),
+            groq_tts_url: "https://api.groq.com/openai/v1/audio/speech".to_string(),
+        }
+    }
+}
+
+impl EndpointDefaults {
+    /// 从环境变量加载，未设置的使用内置默认值
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str, default: String| {
+            env::var(name)
+                .ok()
+                .filter(|s| !s.is_empty())
+                .unwrap_or(default)
+        };
+        Self {
+            openai_asr_url: var("DEFAULT_OPENAI_ASR_URL", defaults.openai_asr_url),
+            asr_vad_url: var("DEFAULT_ASR_VAD_URL", defaults.asr_vad_url),
+            openai_tts_url: var("DEFAULT_OPENAI_TTS_URL", defaults.openai_tts_url),
+            groq_tts_url: var("DEFAULT_GROQ_TTS_URL", defaults.groq_tts_url),
+        }
+    }
+}
+
+/// 解析就绪超时覆盖，例如 "GSV=120,StreamGSV=120,my/echokit-gsv:latest=180"，
+/// 无法解析或超时为 0 的部分被忽略
+fn parse_timeout_overrides(value: &str) -> Vec<(String, u64)> {
+    value
+        .split(',')
+        .filter_map(|part| {
+            let (key, secs) = part.split_once('=')?;
+            let key = key.trim();
+            let secs: u64 = secs.trim().parse().ok()?;
+            (!key.is_empty() && secs > 0).then(|| (key.to_string(), secs))
+        })
+        .collect()
+}
+
+/// 解析状态码集合，例如 "200-399" 或 "200,204,300-399"，无法解析的部分被忽略
+fn parse_status_ranges(value: &str) -> Vec<(u16, u16)> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .filter_map(|part| match part.split_once('-') {
+            Some((start, end)) => Some((start.trim().parse().ok()?, end.trim().parse().ok()?)),
+            None => part.parse().ok().map(|code| (code, code)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每个测试独立的临时目录
+    fn temp_base(name: &str) -> PathBuf {
+        let base = env::temp_dir().join(format!("echokit-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        base
+    }
+
+    #[test]
+    fn parses_accepted_status_ranges() {
+        assert_eq!(parse_status_ranges("200-399"), vec![(200, 399)]);
+        assert_eq!(
+            parse_status_ranges(" 200, 204 ,300-399,bad,"),
+            vec![(200, 200), (204, 204), (300, 399)]
+        );
+        assert!(parse_status_ranges("").is_empty());
+    }
+
+    #[test]
+    fn accepts_any_status_without_configured_ranges() {
+        let mut config = AppConfig::default();
+        assert!(config.is_accepted_health_status(503));
+
+        config.health_check_accepted_statuses = parse_status_ranges("200-299");
+        assert!(config.is_accepted_health_status(200));
+        assert!(config.is_accepted_health_status(299));
+        assert!(!config.is_accepted_health_status(503));
+    }
+
+    #[test]
+    fn ready_timeout_prefers_image_then_platform_override() {
+        let config = AppConfig {
+            ready_timeout_secs: 30,
+            ready_timeout_overrides: parse_timeout_overrides(
+                " gsv = 120 ,my/echokit-gsv:latest=180,bad,Zero=0,=5",
+            ),
+            ..AppConfig::default()
+        };
+        assert_eq!(
+            config.ready_timeout_overrides,
+            vec![
+                ("gsv".to_string(), 120),
+                ("my/echokit-gsv:latest".to_string(), 180)
+            ]
+        );
+
+        assert_eq!(config.ready_timeout_for("echokit:latest", "GSV"), 120);
+        assert_eq!(
+            config.ready_timeout_for("my/echokit-gsv:latest", "GSV"),
+            180
+        );
+        let platform = crate::models::EchoKitConfig::sample().tts.platform_id();
+        assert_eq!(config.ready_timeout_for("echokit:latest", platform), 30);
+    }
+
+    #[test]
+    fn resolve_paths_joins_relative_dirs_with_base() {
+        let base = temp_base("resolve-paths");
+        let mut config = AppConfig {
+            data_base_dir: Some(base.to_string_lossy().into_owned()),
+            ..AppConfig::default()
+        };
+
+        config.resolve_paths().unwrap();
+        let base = base.canonicalize().unwrap();
+        assert_eq!(PathBuf::from(&config.config_dir), base.join("data/configs"));
+        assert_eq!(PathBuf::from(&config.record_dir), base.join("data/records"));
+        assert!(base.join("data/configs").is_dir());
+        assert!(base.join("data/records").is_dir());
+        // hello.wav 不存在时只做路径拼接
+        assert_eq!(
+            PathBuf::from(&config.hello_wav_path),
+            base.join("./data/hello.wav")
+        );
+
+        // 已解析的绝对路径再次解析保持不变
+        let resolved = config.config_dir.clone();
+        config.resolve_paths().unwrap();
+        assert_eq!(config.config_dir, resolved);
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn resolve_paths_fails_when_dir_cannot_be_created() {
+        let base = temp_base("resolve-paths-file");
+        std::fs::create_dir_all(&base).unwrap();
+        // configs 已是普通文件，无法作为目录创建
+        std::fs::write(base.join("configs"), b"").unwrap();
+        let mut config = AppConfig {
+            data_base_dir: Some(base.to_string_lossy().into_owned()),
+            config_dir: "configs".to_string(),
+            ..AppConfig::default()
+        };
+
+        assert!(config.resolve_paths().is_err());
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -1,7 +1,8 @@
-use crate::models::{ASRConfig, EchoKitConfig, TTSConfig};
+use crate::config::EndpointDefaults;
+use crate::models::{ASRConfig, EchoKitConfig, TTSConfig, MASKED_SECRET};
 
 /// 生成 ASR 配置部分
-fn generate_asr_config(asr: &ASRConfig) -> String {
+fn generate_asr_config(asr: &ASRConfig, defaults: &EndpointDefaults) -> String {
     match asr {
         ASRConfig::Openai {
             api_key,
@@ -9,10 +10,21 @@ fn generate_asr_config(asr: &ASRConfig) -> String {
             lang,
             prompt,
             url,
+            vad,
         } => {
-            let url = url
-                .as_deref()
-                .unwrap_or("https://api.openai.com/v1/audio/transcriptions");
+            let url = url.as_deref().unwrap_or(&defaults.openai_asr_url);
+            let vad_url = vad
+                .as_ref()
+                .map(|vad| vad.url.as_str())
+                .unwrap_or(&defaults.asr_vad_url);
+            let vad_provider_line = match vad {
+                Some(vad) => format!("vad_provider = \"{}\"\n", vad.provider),
+                None => String::new(),
+            };
+            let vad_threshold_line = match vad.as_ref().and_then(|vad| vad.threshold) {
+                Some(t) => format!("vad_threshold = {t}\n"),
+                None => String::new(),
+            };
             let prompt_value = prompt
                 .as_deref()
                 .unwrap_or("Hello\n你好\n(noise)\n(bgm)\n(silence)\n");
@@ -24,8 +36,8 @@ model = "{model}"
 lang = "{lang}"
 prompt = """
 {prompt_value}"""
-vad_url = "http://host.docker.internal:8000/v1/audio/vad"
-"#
+vad_url = "{vad_url}"
+{vad_provider_line}{vad_threshold_line}"#
             )
         }
         ASRConfig::Paraformer { paraformer_token } => {
@@ -39,7 +51,7 @@ paraformer_token = "{paraformer_token}"
 }
 
 /// 生成 TTS 配置部分
-fn generate_tts_config(tts: &TTSConfig) -> String {
+fn generate_tts_config(tts: &TTSConfig, defaults: &EndpointDefaults) -> String {
     match tts {
         TTSConfig::Openai {
             api_key,
@@ -47,9 +59,7 @@ fn generate_tts_config(tts: &TTSConfig) -> String {
             voice,
             url,
         } => {
-            let url = url
-                .as_deref()
-                .unwrap_or("https://api.openai.com/v1/audio/speech");
+            let url = url.as_deref().unwrap_or(&defaults.openai_tts_url);
             format!(
                 r#"[tts]
 platform = "Openai"
@@ -66,9 +76,7 @@ url = "{url}"
             voice,
             url,
         } => {
-            let url = url
-                .as_deref()
-                .unwrap_or("https://api.groq.com/openai/v1/audio/speech");
+            let url = url.as_deref().unwrap_or(&defaults.groq_tts_url);
             format!(
                 r#"[tts]
 platform = "Groq"
@@ -172,11 +180,11 @@ token = "{token}"
     }
 }
 
-/// 生成 EchoKit Server 的 config.toml 内容
-pub fn generate_config_toml(config: &EchoKitConfig) -> String {
+/// 生成 EchoKit Server 的 config.toml 内容（未指定的服务地址使用 `defaults`）
+pub fn generate_config_toml(config: &EchoKitConfig, defaults: &EndpointDefaults) -> String {
     let llm_history = config.llm.history.unwrap_or(5);
-    let tts_config = generate_tts_config(&config.tts);
-    let asr_config = generate_asr_config(&config.asr);
+    let tts_config = generate_tts_config(&config.tts, defaults);
+    let asr_config = generate_asr_config(&config.asr, defaults);
 
     format!(
         r#"addr = "0.0.0.0:8080"
@@ -205,3 +213,112 @@ content = """
         llm_prompt = config.llm.system_prompt,
     )
 }
+
+/// config.toml 中保存密钥的字段
+const SECRET_KEYS: &[&str] = &["api_key", "token", "paraformer_token"];
+
+/// 将 config.toml 文本中的密钥字段值替换为占位符（用于展示差异）
+pub fn mask_config_toml_secrets(content: &str) -> String {
+    content
+        .lines()
+        .map(|line| match line.split_once('=') {
+            Some((key, _)) if SECRET_KEYS.contains(&key.trim()) => {
+                format!("{}= \"{}\"", key, MASKED_SECRET)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{RotateKeysRequest, VadConfig};
+
+    #[test]
+    fn rotated_keys_reach_generated_config() {
+        let mut config = EchoKitConfig::sample();
+        RotateKeysRequest {
+            asr: None,
+            llm: Some("sk-llm-rotated".to_string()),
+            tts: Some("sk-tts-rotated".to_string()),
+        }
+        .apply_to(&mut config);
+
+        let toml = generate_config_toml(&config, &EndpointDefaults::default());
+        assert!(toml.contains(r#"api_key = "sk-llm-rotated""#));
+        assert!(toml.contains(r#"api_key = "sk-tts-rotated""#));
+        assert!(!toml.contains("sk-llm\""));
+        assert!(!toml.contains("sk-tts\""));
+        // 未提供的密钥保持不变
+        assert!(toml.contains(r#"api_key = "sk-asr""#));
+    }
+
+    #[test]
+    fn configured_default_urls_fill_omitted_urls() {
+        let defaults = EndpointDefaults {
+            openai_tts_url: "http://tts.proxy.internal/v1/audio/speech".to_string(),
+            openai_asr_url: "http://asr.proxy.internal/v1/audio/transcriptions".to_string(),
+            ..EndpointDefaults::default()
+        };
+        let toml = generate_config_toml(&EchoKitConfig::sample(), &defaults);
+        assert!(toml.contains(r#"url = "http://tts.proxy.internal/v1/audio/speech""#));
+        assert!(toml.contains(r#"url = "http://asr.proxy.internal/v1/audio/transcriptions""#));
+        assert!(!toml.contains("api.openai.com/v1/audio"));
+
+        // 请求中显式指定的地址优先于默认值
+        let mut config = EchoKitConfig::sample();
+        if let TTSConfig::Openai { url, .. } = &mut config.tts {
+            *url = Some("https://tts.example.com/speech".to_string());
+        }
+        let toml = generate_config_toml(&config, &defaults);
+        assert!(toml.contains(r#"url = "https://tts.example.com/speech""#));
+        assert!(!toml.contains("tts.proxy.internal"));
+    }
+
+    #[test]
+    fn configured_vad_provider_renders_into_asr_block() {
+        let defaults = EndpointDefaults {
+            asr_vad_url: "http://vad.default.internal/v1/vad".to_string(),
+            ..EndpointDefaults::default()
+        };
+        let asr_block = |config: &EchoKitConfig| {
+            let toml: toml::Value = generate_config_toml(config, &defaults).parse().unwrap();
+            toml["asr"].clone()
+        };
+
+        // 未配置时保持原有输出
+        let asr = asr_block(&EchoKitConfig::sample());
+        assert_eq!(
+            asr["vad_url"].as_str(),
+            Some("http://vad.default.internal/v1/vad")
+        );
+        assert!(asr.get("vad_provider").is_none());
+        assert!(asr.get("vad_threshold").is_none());
+
+        let mut config = EchoKitConfig::sample();
+        if let ASRConfig::Openai { vad, .. } = &mut config.asr {
+            *vad = Some(VadConfig {
+                provider: "silero".to_string(),
+                url: "https://vad.example.com/detect".to_string(),
+                threshold: Some(0.5),
+            });
+        }
+        let asr = asr_block(&config);
+        assert_eq!(
+            asr["vad_url"].as_str(),
+            Some("https://vad.example.com/detect")
+        );
+        assert_eq!(asr["vad_provider"].as_str(), Some("silero"));
+        assert_eq!(asr["vad_threshold"].as_float(), Some(0.5));
+
+        // 未指定阈值时使用服务默认值
+        if let ASRConfig::Openai { vad: Some(vad), .. } = &mut config.asr {
+            vad.threshold = None;
+        }
+        let asr = asr_block(&config);
+        assert_eq!(asr["vad_provider"].as_str(), Some("silero"));
+        assert!(asr.get("vad_threshold").is_none());
+    }
+}
@@ -0,0 +1,232 @@
+use reqwest::Url;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::models::{ASRConfig, EchoKitConfig, FieldError, TTSConfig};
+
+/// 收集配置中所有用户可指定的服务端点 URL（字段名, URL）
+fn endpoint_urls(config: &EchoKitConfig) -> Vec<(&'static str, &str)> {
+    let mut urls = vec![("llm.url", config.llm.url.as_str())];
+
+    if let ASRConfig::Openai { url, vad, .. } = &config.asr {
+        if let Some(url) = url {
+            urls.push(("asr.url", url.as_str()));
+        }
+        if let Some(vad) = vad {
+            urls.push(("asr.vad.url", vad.url.as_str()));
+        }
+    }
+
+    match &config.tts {
+        TTSConfig::Openai { url: Some(url), .. } | TTSConfig::Groq { url: Some(url), .. } => {
+            urls.push(("tts.url", url.as_str()));
+        }
+        TTSConfig::GSV { url, .. } | TTSConfig::StreamGSV { url, .. } => {
+            urls.push(("tts.url", url.as_str()));
+        }
+        _ => {}
+    }
+
+    urls
+}
+
+fn is_internal_ipv4(ip: &Ipv4Addr) -> bool {
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        // 100.64.0.0/10 运营商级 NAT
+        || (ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64)
+}
+
+fn is_internal_ipv6(ip: &Ipv6Addr) -> bool {
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return is_internal_ipv4(&v4);
+    }
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // fc00::/7 唯一本地地址
+        || (first & 0xfe00) == 0xfc00
+        // fe80::/10 链路本地地址
+        || (first & 0xffc0) == 0xfe80
+}
+
+/// 判断主机是否指向内网/本机（仅基于字面量，不做 DNS 解析）
+fn is_internal_host(host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => is_internal_ipv4(&ip),
+        Ok(IpAddr::V6(ip)) => is_internal_ipv6(&ip),
+        Err(_) => {
+            let host = host.trim_end_matches('.').to_ascii_lowercase();
+            host == "localhost"
+                || host.ends_with(".localhost")
+                || host.ends_with(".local")
+                || host.ends_with(".internal")
+                || !host.contains('.')
+        }
+    }
+}
+
+/// 主机是否匹配白名单条目（条目匹配自身及其子域名）
+fn host_matches(host: &str, allowed: &str) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    let allowed = allowed.trim_end_matches('.').to_ascii_lowercase();
+    host == allowed || host.ends_with(&format!(".{allowed}"))
+}
+
+/// 校验服务端点 URL，返回面向用户的错误描述
+///
+/// 白名单为空时允许任意公网主机；白名单非空时只允许列出的主机。
+/// 内网/本机地址默认拒绝，除非被白名单显式列出。
+fn check_endpoint_url(field: &str, url: &str, allowed_hosts: &[String]) -> Result<(), String> {
+    let parsed = Url::parse(url).map_err(|_| format!("{field} is not a valid URL: {url}"))?;
+
+    if !matches!(parsed.scheme(), "http" | "https" | "ws" | "wss") {
+        return Err(format!(
+            "{field} must use http(s) or ws(s), got {}",
+            parsed.scheme()
+        ));
+    }
+
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| format!("{field} must include a host: {url}"))?;
+
+    let allowlisted = allowed_hosts
+        .iter()
+        .any(|allowed| host_matches(host, allowed));
+    if allowlisted {
+        return Ok(());
+    }
+    if !allowed_hosts.is_empty() {
+        return Err(format!(
+            "{field} host {host} is not in the allowed endpoint hosts"
+        ));
+    }
+    if is_internal_host(host) {
+        return Err(format!(
+            "{field} host {host} is a private or loopback address and is not allowed"
+        ));
+    }
+    Ok(())
+}
+
+/// 校验部署配置中的所有服务端点，返回每个不合法端点的错误
+pub fn validate_endpoints(config: &EchoKitConfig, allowed_hosts: &[String]) -> Vec<FieldError> {
+    endpoint_urls(config)
+        .into_iter()
+        .filter_map(|(field, url)| {
+            check_endpoint_url(field, url, allowed_hosts)
+                .err()
+                .map(|message| FieldError::new(field, message))
+        })
+        .collect()
+}
+
+/// 是否为合法的主机名（RFC 1123：以点分隔的标签，每段 1-63 个字母、数字或连字符，
+/// 不以连字符开头或结尾，总长度不超过 253）
+pub fn is_valid_hostname(host: &str) -> bool {
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// 校验容器对外展示的主机名，只允许受管域名本身或其子域名
+pub fn validate_advertised_host(host: &str, managed_domains: &[String]) -> Result<(), String> {
+    let valid_chars = host
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+    if host.is_empty() || !valid_chars || host.starts_with('.') || host.starts_with('-') {
+        return Err(format!("advertisedHost is not a valid host name: {host}"));
+    }
+    if !managed_domains
+        .iter()
+        .any(|domain| host_matches(host, domain))
+    {
+        return Err(format!(
+            "advertisedHost {host} is not within a managed domain"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_llm_url(url: &str) -> EchoKitConfig {
+        let mut config = EchoKitConfig::sample();
+        config.llm.url = url.to_string();
+        config
+    }
+
+    fn errors(config: &EchoKitConfig, allowed_hosts: &[&str]) -> Vec<String> {
+        let allowed_hosts: Vec<String> = allowed_hosts.iter().map(|h| h.to_string()).collect();
+        validate_endpoints(config, &allowed_hosts)
+            .into_iter()
+            .map(|e| e.field)
+            .collect()
+    }
+
+    #[test]
+    fn rejects_internal_endpoints_by_default() {
+        for url in [
+            "http://10.0.0.5/v1/chat/completions",
+            "http://127.0.0.1:8080/v1",
+            "http://169.254.169.254/latest",
+            "http://[::1]/v1",
+            "http://[fd00::1]/v1",
+            "http://100.64.0.1/v1",
+            "http://localhost/v1",
+            "http://llm.internal/v1",
+            "http://llm/v1",
+        ] {
+            assert_eq!(
+                errors(&config_with_llm_url(url), &[]),
+                vec!["llm.url"],
+                "{url}"
+            );
+        }
+        assert!(errors(&config_with_llm_url("https://api.openai.com/v1"), &[]).is_empty());
+        assert_eq!(
+            errors(&config_with_llm_url("file:///etc/passwd"), &[]),
+            vec!["llm.url"]
+        );
+    }
+
+    #[test]
+    fn allowlist_admits_listed_hosts_only() {
+        let allowed = ["10.0.0.5", "example.com"];
+        assert!(errors(&config_with_llm_url("http://10.0.0.5/v1"), &allowed).is_empty());
+        assert!(errors(&config_with_llm_url("https://llm.Example.com/v1"), &allowed).is_empty());
+        assert_eq!(
+            errors(&config_with_llm_url("https://api.openai.com/v1"), &allowed),
+            vec!["llm.url"]
+        );
+        // 后缀相同但不是子域名
+        assert_eq!(
+            errors(&config_with_llm_url("https://badexample.com/v1"), &allowed),
+            vec!["llm.url"]
+        );
+    }
+
+    #[test]
+    fn checks_asr_and_tts_urls() {
+        let mut config = EchoKitConfig::sample();
+        if let ASRConfig::Openai { url, .. } = &mut config.asr {
+            *url = Some("http://192.168.1.10/v1".to_string());
+        }
+        if let TTSConfig::Openai { url, .. } = &mut config.tts {
+            *url = Some("http://172.16.0.1/v1".to_string());
+        }
+        assert_eq!(errors(&config, &[]), vec!["asr.url", "tts.url"]);
+    }
+}
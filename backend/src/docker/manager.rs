@@ -1,14 +1,17 @@
 use anyhow::{Context, Result};
-use bollard::models::{ContainerSummaryStateEnum, HostConfig, PortBinding};
+use bollard::models::{ContainerInspectResponse, ContainerSummaryStateEnum, HostConfig, PortBinding};
 use bollard::query_parameters::{
-    CreateContainerOptions, InspectContainerOptions, ListContainersOptions, LogsOptions,
-    RemoveContainerOptions, StartContainerOptions, StopContainerOptions,
+    CreateContainerOptions, InspectContainerOptions, InspectNetworkOptions, ListContainersOptions,
+    LogsOptions, RemoveContainerOptions, RestartContainerOptions, StartContainerOptions,
+    StopContainerOptions,
 };
+use bollard::container::LogOutput;
 use bollard::secret::ContainerCreateBody;
 use bollard::Docker;
 use chrono::{DateTime, Utc};
+use futures_util::stream::BoxStream;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::fs;
@@ -17,27 +20,50 @@ use tracing::{debug, error, info, warn};
 
 use crate::config::AppConfig;
 use crate::models::{
-    ContainerInfo, ContainerStatus, DeployResponse, EchoKitConfig, HealthCheckResult, HealthStatus,
+    ASRConfig, ConfigDiff, ContainerInfo, ContainerStatus, DeployResponse, DeviceRouteTest,
+    EchoKitConfig, FieldError, HealthCheckResult, HealthStatus, LogLine, MigrateContainerRequest,
+    MigrateContainerResponse, LogsPage, PortMapping, PortUtilization, PortsReport,
+    RotateKeysRequest, VadConfig,
 };
-
-use super::generate_config_toml;
+use crate::secrets::{sign_device_token, SecretCipher};
+
+use super::endpoint_policy::{is_valid_hostname, validate_advertised_host, validate_endpoints};
+use super::{generate_config_toml, mask_config_toml_secrets};
+
+/// 常见错误模式及对应提示
+const ERROR_PATTERNS: &[(&str, &str)] = &[
+    (
+        "TOML parse error",
+        "Configuration file (config.toml) has invalid TOML syntax",
+    ),
+    (
+        "data did not match any variant",
+        "Configuration format mismatch - check TTS/ASR/LLM settings",
+    ),
+    ("missing field", "Missing required configuration field"),
+    (
+        "unknown field",
+        "Unknown configuration field - check spelling",
+    ),
+    (
+        "Address already in use",
+        "Port 8080 is already in use inside the container",
+    ),
+    (
+        "Connection refused",
+        "Cannot connect to external service - check API endpoints",
+    ),
+    ("No such file or directory", "Required file not found"),
+    (
+        "Permission denied",
+        "Permission error - check file permissions",
+    ),
+    ("panicked at", "Application crashed - check configuration"),
+];
 
 /// 从容器日志中提取错误提示
 fn extract_error_hint(logs: &str) -> Option<String> {
-    // 常见错误模式
-    let error_patterns = [
-        ("TOML parse error", "Configuration file (config.toml) has invalid TOML syntax"),
-        ("data did not match any variant", "Configuration format mismatch - check TTS/ASR/LLM settings"),
-        ("missing field", "Missing required configuration field"),
-        ("unknown field", "Unknown configuration field - check spelling"),
-        ("Address already in use", "Port 8080 is already in use inside the container"),
-        ("Connection refused", "Cannot connect to external service - check API endpoints"),
-        ("No such file or directory", "Required file not found"),
-        ("Permission denied", "Permission error - check file permissions"),
-        ("panicked at", "Application crashed - check configuration"),
-    ];
-
-    for (pattern, hint) in error_patterns {
+    for (pattern, hint) in ERROR_PATTERNS {
         if logs.contains(pattern) {
             // 尝试提取更具体的错误信息
             if let Some(line) = logs.lines().find(|l| l.contains(pattern)) {
@@ -55,18 +81,303 @@ fn extract_error_hint(logs: &str) -> Option<String> {
     None
 }
 
+/// 为日志块中的每一行加上 `[容器名] ` 前缀
+fn prefix_log_lines(name: &str, chunk: &str) -> Vec<String> {
+    chunk
+        .lines()
+        .map(|line| format!("[{}] {}", name, line))
+        .collect()
+}
+
+/// 将多个容器的日志流合并为按到达顺序交错的行流，每行带 `[容器名] ` 前缀
+///
+/// 丢弃返回的流即丢弃全部子日志流。
+fn merge_container_logs<S, E>(streams: Vec<(String, S)>) -> BoxStream<'static, String>
+where
+    S: futures_util::Stream<Item = Result<LogOutput, E>> + Send + 'static,
+    E: std::fmt::Display,
+{
+    use futures_util::StreamExt;
+
+    let streams = streams.into_iter().map(|(name, logs)| {
+        logs.flat_map(move |chunk| {
+            let lines = match chunk {
+                Ok(chunk) => prefix_log_lines(&name, &chunk.to_string()),
+                Err(e) => vec![format!("[{}] 日志流中断: {}", name, e)],
+            };
+            futures_util::stream::iter(lines)
+        })
+        .boxed()
+    });
+    futures_util::stream::select_all(streams).boxed()
+}
+
+/// 提取日志中所有匹配错误模式（或包含 "error" 关键词）的行
+fn extract_error_lines(logs: &str) -> Vec<String> {
+    logs.lines()
+        .filter(|line| {
+            ERROR_PATTERNS
+                .iter()
+                .any(|(pattern, _)| line.contains(pattern))
+                || line.to_lowercase().contains("error")
+        })
+        .map(|line| line.trim().to_string())
+        .collect()
+}
+
+/// 解析 Docker 返回的 RFC3339 启动时间，未启动时的零值（0001-01-01）视为无效
+fn parse_started_at(started_at: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(started_at)
+        .ok()
+        .map(|time| time.timestamp())
+        .filter(|timestamp| *timestamp > 0)
+}
+
+/// 解析日志分页游标（RFC3339 时间戳），返回传给 Docker 的 until（秒，向上取整）
+pub fn parse_logs_cursor(cursor: &str) -> Option<i32> {
+    let timestamp = DateTime::parse_from_rfc3339(cursor).ok()?.timestamp();
+    i32::try_from(timestamp + 1).ok()
+}
+
+/// 下一页游标：取满一页或 Docker 可能还有更早的日志时，指向本页第一行
+fn logs_page_cursor(lines: &[LogLine], limit: usize, more_available: bool) -> Option<String> {
+    if lines.len() == limit || more_available {
+        lines.first().map(|line| line.timestamp.clone())
+    } else {
+        None
+    }
+}
+
+/// 对未停止的容器并发执行检查（最多同时 `concurrency` 个），返回容器 ID 到检查结果的映射
+async fn check_unstopped_containers<F, Fut>(
+    containers: Vec<ContainerInfo>,
+    concurrency: usize,
+    check: F,
+) -> HashMap<String, HealthCheckResult>
+where
+    F: Fn(ContainerInfo) -> Fut,
+    Fut: std::future::Future<Output = HealthCheckResult>,
+{
+    use futures_util::stream::{self, StreamExt};
+
+    let targets: Vec<_> = containers
+        .into_iter()
+        .filter(|c| c.status != ContainerStatus::Stopped)
+        .collect();
+    info!("批量健康检查: {} 个容器", targets.len());
+
+    stream::iter(targets)
+        .map(|container| {
+            let id = container.id.clone();
+            let health = check(container);
+            async move { (id, health.await) }
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await
+}
+
+/// 构建容器的宿主机配置（端口映射、卷挂载，以及可选的 Docker 网络）
+fn container_host_config(
+    port_bindings: HashMap<String, Option<Vec<PortBinding>>>,
+    binds: Vec<String>,
+    network: Option<&str>,
+) -> HostConfig {
+    HostConfig {
+        port_bindings: Some(port_bindings),
+        binds: Some(binds),
+        network_mode: network.map(str::to_string),
+        ..Default::default()
+    }
+}
+
+/// 构建创建容器的请求体
+///
+/// 容器主机名默认使用容器名称（名称不是合法主机名时沿用 Docker 默认的容器 ID）
+fn container_create_body(
+    image: &str,
+    container_name: &str,
+    hostname: Option<&str>,
+    exposed_ports: HashMap<String, HashMap<(), ()>>,
+    host_config: HostConfig,
+) -> ContainerCreateBody {
+    let env = vec![
+        "RUST_LOG=info".to_string(),
+        format!("CONTAINER_NAME={}", container_name),
+    ];
+
+    // 添加标签以标识 EchoKit 管理的容器
+    let mut labels = HashMap::new();
+    labels.insert("managed-by".to_string(), "echokit-console".to_string());
+
+    let hostname = hostname
+        .map(str::to_string)
+        .or_else(|| is_valid_hostname(container_name).then(|| container_name.to_string()));
+
+    ContainerCreateBody {
+        image: Some(image.to_string()),
+        hostname,
+        env: Some(env),
+        exposed_ports: Some(exposed_ports),
+        host_config: Some(host_config),
+        labels: Some(labels),
+        ..Default::default()
+    }
+}
+
+/// 比较期望渲染的 config.toml 与磁盘上的实际内容（差异中的密钥已脱敏）
+fn diff_config_toml(expected: &str, actual: &str) -> ConfigDiff {
+    let drifted = expected != actual;
+    let diff = if drifted {
+        similar::TextDiff::from_lines(
+            &mask_config_toml_secrets(expected),
+            &mask_config_toml_secrets(actual),
+        )
+        .unified_diff()
+        .header("config.toml (stored)", "config.toml (on disk)")
+        .to_string()
+    } else {
+        String::new()
+    };
+
+    ConfigDiff { drifted, diff }
+}
+
+/// 对比 Docker 中的受管容器与数据库记录，返回（Docker 中已不存在的记录 ID，缺少记录的容器）
+fn find_drift<'a>(
+    containers: &'a [ContainerInfo],
+    row_ids: &'a [String],
+) -> (Vec<&'a String>, Vec<&'a ContainerInfo>) {
+    let stale_ids = row_ids
+        .iter()
+        .filter(|id| !containers.iter().any(|c| &c.id == *id))
+        .collect();
+    let orphans = containers
+        .iter()
+        .filter(|c| !row_ids.contains(&c.id))
+        .collect();
+    (stale_ids, orphans)
+}
+
+/// 根据部署时的健康检查结果确定部署响应中的容器状态
+fn deployment_status(health: &HealthCheckResult) -> ContainerStatus {
+    if !health.container_running {
+        return ContainerStatus::Stopped;
+    }
+    match health.status {
+        HealthStatus::Healthy => ContainerStatus::Running,
+        // 已启动但未等待就绪
+        HealthStatus::Unknown => ContainerStatus::Starting,
+        // 容器在运行但健康检查失败
+        HealthStatus::Unhealthy => ContainerStatus::Error,
+    }
+}
+
+/// 写入数据库的容器状态
+///
+/// Proxy 只路由 running 的容器，因此只要 Docker 报告容器在运行就记为 running，
+/// 未等待就绪或健康检查暂未通过的容器也能被路由；就绪情况以部署响应中的状态与 health 为准
+fn stored_status(health: &HealthCheckResult) -> ContainerStatus {
+    if health.container_running {
+        ContainerStatus::Running
+    } else {
+        ContainerStatus::Stopped
+    }
+}
+
+/// 只创建未启动的容器的健康状态
+fn unstarted_health() -> HealthCheckResult {
+    HealthCheckResult {
+        status: HealthStatus::Unknown,
+        http_reachable: false,
+        container_running: false,
+        error_message: Some("Container was created without starting".to_string()),
+        logs_tail: None,
+        error_lines: Vec::new(),
+    }
+}
+
+/// 部署目标参数（端口、主机名、网络等），未指定的字段使用默认值
+#[derive(Debug, Clone, Copy)]
+pub struct DeployTarget<'a> {
+    /// 宿主机端口（未指定时自动分配）
+    pub port: Option<u16>,
+    /// 主端口之外需要额外暴露的容器端口
+    pub extra_ports: &'a [u16],
+    /// 对外展示的主机名
+    pub advertised_host: Option<&'a str>,
+    /// 容器内部主机名（未指定时使用容器名称）
+    pub hostname: Option<&'a str>,
+    /// 容器加入的 Docker 网络
+    pub network: Option<&'a str>,
+    /// 容器镜像（未指定时使用 DOCKER_IMAGE 配置）
+    pub image: Option<&'a str>,
+    /// 创建后是否立即启动（为 false 时只创建容器、写入配置与数据库记录）
+    pub start: bool,
+    /// 启动后是否等待服务就绪（为 false 时立即返回 Starting 状态，健康状态为 unknown，由调用方自行轮询健康检查）
+    pub wait_for_health: bool,
+}
+
+/// 部署过程中需要区别对待的错误
+#[derive(Debug, thiserror::Error)]
+pub enum DeployError {
+    /// 宿主机端口已被非托管进程占用
+    #[error("Host port {0} is already in use by another process")]
+    HostPortInUse(u16),
+    /// 指定的端口已被其他托管容器使用
+    #[error("Port {port} is already used by container {container}")]
+    PortAllocated { port: u16, container: String },
+    /// 指定的端口已被另一个进行中的部署预留
+    #[error("Port {0} is reserved by another deployment in progress")]
+    PortReserved(u16),
+    /// Docker 守护进程不可达（如 socket 在启动后被移除）
+    #[error("Docker daemon unavailable: {0}")]
+    DockerUnavailable(String),
+    /// 请求参数校验失败（如迁移目标名称冲突）
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// Proxy 对连接测试限流
+#[derive(Debug, thiserror::Error)]
+#[error("Proxy rate limited the connection test, try again in {}s", retry_after.as_secs().max(1))]
+pub struct ProxyRateLimited {
+    /// Proxy 建议的重试等待时间（来自 Retry-After 头，缺失时为 1 秒）
+    pub retry_after: Duration,
+}
+
+/// 检查宿主机端口是否空闲（尝试绑定后立即释放）
+fn is_host_port_free(port: u16) -> bool {
+    std::net::TcpListener::bind(("0.0.0.0", port)).is_ok()
+}
+
 /// 健康检查配置
 const HEALTH_CHECK_TIMEOUT_SECS: u64 = 5;
 const HEALTH_CHECK_RETRIES: u32 = 3;
 const HEALTH_CHECK_RETRY_DELAY_MS: u64 = 1000;
+/// EchoKit Server 在容器内监听的端口
+const ECHOKIT_CONTAINER_PORT: u16 = 8080;
+/// 等待 Proxy 连接测试结果的超时（Proxy 内部建连与等待 Pong 各有超时）
+const PROXY_ROUTE_TEST_TIMEOUT_SECS: u64 = 15;
+/// 批量健康检查时同时检查的容器数
+const HEALTH_CHECK_ALL_CONCURRENCY: usize = 8;
+/// 容器描述的最大字符数
+const MAX_DESCRIPTION_CHARS: usize = 1000;
+/// 分页读取日志时单次向 Docker 请求的最大行数（同一秒内日志过多时不再翻倍重试）
+const MAX_LOGS_PAGE_TAIL: usize = 10_000;
+/// 聚合日志流最多同时跟随的容器数
+pub const MAX_LOG_STREAM_CONTAINERS: usize = 10;
 
 /// Docker 容器管理器
 pub struct DockerManager {
     docker: Docker,
     config: AppConfig,
-    used_ports: Arc<RwLock<Vec<u16>>>,
+    /// 进行中的部署预留的宿主机端口，部署结束后释放
+    reserved_ports: Arc<RwLock<Vec<u16>>>,
     http_client: reqwest::Client,
     pool: sqlx::PgPool,
+    /// 数据库中密钥字段的加密器（未配置密钥时为 None）
+    cipher: Option<SecretCipher>,
 }
 
 impl DockerManager {
@@ -79,6 +390,16 @@ impl DockerManager {
         fs::create_dir_all(&config.config_dir).await?;
         fs::create_dir_all(&config.record_dir).await?;
 
+        let cipher = config
+            .secrets_encryption_key
+            .as_deref()
+            .map(SecretCipher::from_base64_key)
+            .transpose()
+            .context("Invalid SECRETS_ENCRYPTION_KEY")?;
+        if cipher.is_some() {
+            info!("已启用数据库密钥字段加密");
+        }
+
         // 创建 HTTP 客户端用于健康检查
         let http_client = reqwest::Client::builder()
             .timeout(Duration::from_secs(HEALTH_CHECK_TIMEOUT_SECS))
@@ -88,33 +409,369 @@ impl DockerManager {
         Ok(Self {
             docker,
             config,
-            used_ports: Arc::new(RwLock::new(Vec::new())),
+            reserved_ports: Arc::new(RwLock::new(Vec::new())),
             http_client,
             pool,
+            cipher,
+        })
+    }
+
+    /// 序列化用于保存到数据库的结构化配置（启用加密时密钥字段为密文）
+    fn stored_config_json(&self, echokit_config: &EchoKitConfig) -> Result<String> {
+        let mut stored = echokit_config.clone();
+        if let Some(cipher) = &self.cipher {
+            cipher.encrypt_config(&mut stored)?;
+        }
+        serde_json::to_string(&stored).context("Failed to serialize EchoKit config")
+    }
+
+    /// 解析数据库中保存的结构化配置（启用加密时解密密钥字段），与 `stored_config_json` 互逆
+    fn parse_stored_config(&self, config_json: &str) -> Result<EchoKitConfig> {
+        let mut echokit_config: EchoKitConfig =
+            serde_json::from_str(config_json).context("Stored config is not valid")?;
+        if let Some(cipher) = &self.cipher {
+            cipher.decrypt_config(&mut echokit_config)?;
+        }
+        Ok(echokit_config)
+    }
+
+    /// 统计端口范围内已被容器占用的端口数
+    pub async fn port_utilization(&self, containers: &[ContainerInfo]) -> PortUtilization {
+        let taken = self.host_ports_of(containers).await;
+        PortUtilization {
+            used: self.ports_in_range(&taken).len(),
+            total: (self.config.port_range_start..=self.config.port_range_end).count(),
+        }
+    }
+
+    /// 端口范围内被容器占用的端口（升序去重）
+    fn ports_in_range(&self, taken: &[(u16, String)]) -> Vec<u16> {
+        let range = self.config.port_range_start..=self.config.port_range_end;
+        let mut used: Vec<u16> = taken
+            .iter()
+            .map(|(port, _)| *port)
+            .filter(|port| range.contains(port))
+            .collect();
+        used.sort_unstable();
+        used.dedup();
+        used
+    }
+
+    /// 给定容器占用的宿主机端口及所属容器名（含额外端口）
+    ///
+    /// 已创建但未启动的容器在 Docker 列表中没有公开端口，因此同时计入数据库中记录的
+    /// 主端口与额外端口；数据库查询失败时只使用 Docker 报告的端口。
+    async fn host_ports_of(&self, containers: &[ContainerInfo]) -> Vec<(u16, String)> {
+        let mut taken: Vec<(u16, String)> = containers
+            .iter()
+            .filter(|c| c.port > 0)
+            .map(|c| (c.port, c.name.clone()))
+            .collect();
+
+        let ids: Vec<String> = containers.iter().map(|c| c.id.clone()).collect();
+        let rows = match sqlx::query!(
+            "SELECT id, port, extra_ports_json FROM containers WHERE id = ANY($1)",
+            &ids[..]
+        )
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(rows) => rows,
+            Err(e) => {
+                warn!("查询容器端口记录失败: {}", e);
+                return taken;
+            }
+        };
+
+        for row in rows {
+            let Some(name) = containers.iter().find(|c| c.id == row.id).map(|c| &c.name) else {
+                continue;
+            };
+            let extra_ports: Vec<PortMapping> = row
+                .extra_ports_json
+                .as_deref()
+                .and_then(|json| serde_json::from_str(json).ok())
+                .unwrap_or_default();
+            let stored = row
+                .port
+                .and_then(|port| u16::try_from(port).ok())
+                .into_iter()
+                .chain(extra_ports.iter().map(|m| m.host_port));
+            for port in stored {
+                if port > 0 && !taken.iter().any(|(p, _)| *p == port) {
+                    taken.push((port, name.clone()));
+                }
+            }
+        }
+        taken
+    }
+
+    /// 端口范围详情，下一个空闲端口与 `allocate_port` 的选择一致，但不做预留
+    pub async fn ports_report(&self) -> Result<PortsReport> {
+        let containers = self.list_containers().await?;
+        let taken = self.host_ports_of(&containers).await;
+        let used_ports = self.ports_in_range(&taken);
+
+        let next_free_port = {
+            let reserved = self.reserved_ports.read().await;
+            let mut taken: Vec<u16> = taken.iter().map(|(port, _)| *port).collect();
+            taken.extend(reserved.iter().copied());
+            self.first_free_port(&taken)
+        };
+
+        let total = (self.config.port_range_start..=self.config.port_range_end).count();
+        let utilization_percent = if total == 0 {
+            0.0
+        } else {
+            used_ports.len() as f64 * 100.0 / total as f64
+        };
+
+        Ok(PortsReport {
+            range_start: self.config.port_range_start,
+            range_end: self.config.port_range_end,
+            used_ports,
+            total,
+            utilization_percent,
+            next_free_port,
+        })
+    }
+
+    /// 查询 Proxy 当前活跃连接数
+    pub async fn fetch_proxy_active_connections(&self) -> Option<u64> {
+        let url = self.config.proxy_health_url.as_ref()?;
+        let response = match self.http_client.get(url).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Failed to reach proxy health endpoint {}: {}", url, e);
+                return None;
+            }
+        };
+        let bytes = response.bytes().await.ok()?;
+        let body: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+        body.get("active_connections").and_then(|v| v.as_u64())
+    }
+
+    /// 查询 Proxy 上连接到指定容器的设备数
+    pub async fn fetch_proxy_container_connections(&self, container_id: &str) -> Option<u64> {
+        let counts = self.fetch_proxy_container_counts().await?;
+        // 没有连接的容器不会出现在结果中
+        Some(counts.get(container_id).copied().unwrap_or(0))
+    }
+
+    /// 设备经 Proxy 连接时使用的 WebSocket URL（未配置 DEVICE_PROXY_URL 时返回 None）
+    ///
+    /// 配置了 DEVICE_TOKEN_SECRET 时附带签名令牌，同时返回令牌的过期时间。
+    pub fn device_proxy_ws_url(&self, raw_device_id: &str) -> Option<(String, Option<i64>)> {
+        let base_url = self.config.device_proxy_url.as_deref()?;
+        let url = format!("{}/ws/{}", base_url.trim_end_matches('/'), raw_device_id);
+
+        let Some(secret) = self.config.device_token_secret.as_deref() else {
+            return Some((url, None));
+        };
+        let expires_at = chrono::Utc::now().timestamp() + self.config.device_token_ttl_secs as i64;
+        let token = sign_device_token(secret, raw_device_id, expires_at);
+        Some((format!("{}?token={}", url, token), Some(expires_at)))
+    }
+
+    /// 通过 Proxy 的连接测试接口（`/ws-test/{device_id}`）测试设备路由
+    ///
+    /// 未配置 Proxy WebSocket 地址时返回 None。测试结果由 Proxy 放在关闭帧的原因中。
+    pub async fn test_device_route(&self, raw_device_id: &str) -> Result<Option<DeviceRouteTest>> {
+        use futures_util::StreamExt;
+        use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+
+        let Some(base_url) = self.config.proxy_ws_url.as_deref() else {
+            return Ok(None);
+        };
+        let url = format!(
+            "{}/ws-test/{}",
+            base_url.trim_end_matches('/'),
+            raw_device_id
+        );
+        let timeout = Duration::from_secs(PROXY_ROUTE_TEST_TIMEOUT_SECS);
+
+        let (mut ws, _) =
+            match tokio::time::timeout(timeout, tokio_tungstenite::connect_async(&url)).await {
+                Ok(Ok(connection)) => connection,
+                Ok(Err(WsError::Http(response))) if response.status().as_u16() == 429 => {
+                    let retry_after = response
+                        .headers()
+                        .get("retry-after")
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.trim().parse().ok())
+                        .map(Duration::from_secs)
+                        .unwrap_or(Duration::from_secs(1));
+                    return Err(ProxyRateLimited { retry_after }.into());
+                }
+                Ok(Err(e)) => return Err(e).context("Failed to connect to proxy"),
+                Err(_) => anyhow::bail!("Timed out connecting to proxy"),
+            };
+
+        let reason = tokio::time::timeout(timeout, async {
+            while let Some(message) = ws.next().await {
+                if let Message::Close(frame) = message.context("Failed to read from proxy")? {
+                    return Ok(frame.map(|f| f.reason.to_string()));
+                }
+            }
+            Ok::<_, anyhow::Error>(None)
         })
+        .await
+        .map_err(|_| anyhow::anyhow!("Timed out waiting for proxy test result"))??
+        .context("Proxy closed the connection without a result")?;
+
+        let result = serde_json::from_str(&reason).context("Invalid proxy test result")?;
+        Ok(Some(result))
+    }
+
+    /// 查询 Proxy 上各容器的设备连接数
+    ///
+    /// 连接数接口与健康检查位于同一端口（`/metrics/containers`）。
+    async fn fetch_proxy_container_counts(&self) -> Option<HashMap<String, u64>> {
+        let health_url = self.config.proxy_health_url.as_ref()?;
+        let url = match reqwest::Url::parse(health_url).and_then(|u| u.join("/metrics/containers"))
+        {
+            Ok(url) => url,
+            Err(e) => {
+                warn!("Invalid proxy health url {}: {}", health_url, e);
+                return None;
+            }
+        };
+        let response = match self.http_client.get(url.clone()).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Failed to reach proxy metrics endpoint {}: {}", url, e);
+                return None;
+            }
+        };
+        let bytes = response.bytes().await.ok()?;
+        let body: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+        let containers = body.get("containers")?.as_object()?;
+        Some(
+            containers
+                .iter()
+                .filter_map(|(id, count)| Some((id.clone(), count.as_u64()?)))
+                .collect(),
+        )
     }
 
     /// 分配可用端口
     async fn allocate_port(&self) -> Result<u16> {
-        let mut used_ports = self.used_ports.write().await;
-
-        // 获取已使用的端口
         let containers = self.list_containers().await?;
-        for container in &containers {
-            if !used_ports.contains(&container.port) {
-                used_ports.push(container.port);
+        let taken: Vec<u16> = self
+            .host_ports_of(&containers)
+            .await
+            .into_iter()
+            .map(|(port, _)| port)
+            .collect();
+        self.allocate_port_among(&taken).await
+    }
+
+    /// 跳过给定的已占用端口及进行中部署预留的端口分配可用端口，分配结果计入预留列表
+    async fn allocate_port_among(&self, taken: &[u16]) -> Result<u16> {
+        let mut reserved_ports = self.reserved_ports.write().await;
+
+        let mut unavailable = taken.to_vec();
+        unavailable.extend(reserved_ports.iter().copied());
+        let port = self
+            .first_free_port(&unavailable)
+            .context("No available ports in range")?;
+        reserved_ports.push(port);
+        Ok(port)
+    }
+
+    /// 释放部署预留的端口（部署结束后端口已由容器与数据库记录占用）
+    async fn release_ports(&self, ports: &[u16]) {
+        self.reserved_ports
+            .write()
+            .await
+            .retain(|port| !ports.contains(port));
+    }
+
+    /// 查找范围内第一个可用端口（跳过已占用及被宿主机其他进程占用的端口）
+    fn first_free_port(&self, taken: &[u16]) -> Option<u16> {
+        (self.config.port_range_start..=self.config.port_range_end).find(|port| {
+            if taken.contains(port) {
+                return false;
+            }
+            if !is_host_port_free(*port) {
+                debug!("端口 {} 已被宿主机其他进程占用，跳过", port);
+                return false;
             }
+            true
+        })
+    }
+
+    /// 校验并预留用户指定的端口
+    ///
+    /// 端口须在配置的端口范围内，且未被托管容器、进行中的部署或宿主机其他进程占用，
+    /// 在任何 Docker 操作之前返回明确的错误，而不是等到容器启动时绑定失败。
+    async fn reserve_port(&self, port: u16) -> Result<()> {
+        let (start, end) = (self.config.port_range_start, self.config.port_range_end);
+        if !(start..=end).contains(&port) {
+            return Err(DeployError::Invalid(format!(
+                "Port {} is outside the allowed range {}-{}",
+                port, start, end
+            ))
+            .into());
         }
 
-        // 查找可用端口
-        for port in self.config.port_range_start..=self.config.port_range_end {
-            if !used_ports.contains(&port) {
-                used_ports.push(port);
-                return Ok(port);
+        let mut reserved_ports = self.reserved_ports.write().await;
+        if reserved_ports.contains(&port) {
+            return Err(DeployError::PortReserved(port).into());
+        }
+        let containers = self.list_containers().await?;
+        let taken = self.host_ports_of(&containers).await;
+        if let Some((_, existing)) = taken.iter().find(|(p, _)| *p == port) {
+            return Err(DeployError::PortAllocated {
+                port,
+                container: existing.clone(),
             }
+            .into());
         }
+        if !is_host_port_free(port) {
+            return Err(DeployError::HostPortInUse(port).into());
+        }
+
+        reserved_ports.push(port);
+        Ok(())
+    }
+
+    /// 将容器 ID、ID 前缀或名称解析为完整的容器 ID
+    ///
+    /// 解析规则与 Docker 一致，保证后续的 Docker 操作与按 ID 更新数据库作用于同一容器；
+    /// 非本控制台管理的容器视为不存在。
+    async fn resolve_container_id(&self, id: &str) -> Result<String> {
+        let info = self.inspect_managed_container(id).await?;
+        info.id.context("Container not found")
+    }
+
+    /// 将容器 ID 前缀或名称解析为数据库记录使用的完整 ID
+    ///
+    /// Docker 中找不到的容器（如外部服务器）原样返回，仍按 ID 或名称匹配数据库记录
+    async fn canonical_container_id(&self, id: &str) -> String {
+        self.resolve_container_id(id)
+            .await
+            .unwrap_or_else(|_| id.to_string())
+    }
+
+    /// 查询本控制台管理的容器详情，非本控制台管理的容器视为不存在
+    async fn inspect_managed_container(&self, id: &str) -> Result<ContainerInspectResponse> {
+        let info = self
+            .docker
+            .inspect_container(id, None::<InspectContainerOptions>)
+            .await
+            .context("Container not found")?;
 
-        anyhow::bail!("No available ports in range")
+        let managed = info
+            .config
+            .as_ref()
+            .and_then(|c| c.labels.as_ref())
+            .and_then(|labels| labels.get("managed-by"))
+            .is_some_and(|value| value == "echokit-console");
+        if !managed {
+            anyhow::bail!("Container not found");
+        }
+        Ok(info)
     }
 
     /// 检查容器是否在运行
@@ -134,10 +791,13 @@ impl DockerManager {
 
     /// 执行 HTTP 健康检查
     async fn check_http_health(&self, port: u16) -> bool {
-        let url = format!("http://localhost:{}/", port);
+        let path = self.config.health_check_path.trim_start_matches('/');
+        let url = format!("http://localhost:{}/{}", port, path);
+        // 未配置状态码集合时，只要能收到响应就认为服务可用（即使是 404 也说明服务在运行）
         match self.http_client.get(&url).send().await {
-            // 只要能收到响应就认为服务可用（即使是 404 也说明服务在运行）
-            Ok(_) => true,
+            Ok(response) => self
+                .config
+                .is_accepted_health_status(response.status().as_u16()),
             Err(_) => false,
         }
     }
@@ -149,12 +809,13 @@ impl DockerManager {
 
         if !container_running {
             // 容器未运行，获取错误日志
-            let logs = self.get_container_logs(container_id, Some(50)).await.ok();
+            let logs = self.get_container_logs(container_id, Some(50), false).await.ok();
             return HealthCheckResult {
                 status: HealthStatus::Unhealthy,
                 http_reachable: false,
                 container_running: false,
                 error_message: Some("Container is not running".to_string()),
+                error_lines: logs.as_deref().map(extract_error_lines).unwrap_or_default(),
                 logs_tail: logs,
             };
         }
@@ -178,15 +839,17 @@ impl DockerManager {
                 container_running: true,
                 error_message: None,
                 logs_tail: None,
+                error_lines: Vec::new(),
             }
         } else {
             // HTTP 不可达，获取日志帮助诊断
-            let logs = self.get_container_logs(container_id, Some(50)).await.ok();
+            let logs = self.get_container_logs(container_id, Some(50), false).await.ok();
             HealthCheckResult {
                 status: HealthStatus::Unhealthy,
                 http_reachable: false,
                 container_running: true,
                 error_message: Some("Service is not responding to HTTP requests".to_string()),
+                error_lines: logs.as_deref().map(extract_error_lines).unwrap_or_default(),
                 logs_tail: logs,
             }
         }
@@ -214,7 +877,7 @@ impl DockerManager {
                     "Container {} stopped unexpectedly after starting. This usually indicates a configuration error or missing dependencies.",
                     container_id
                 );
-                let logs = self.get_container_logs(container_id, Some(100)).await.ok();
+                let logs = self.get_container_logs(container_id, Some(100), false).await.ok();
 
                 // 尝试从日志中提取错误信息
                 let error_hint = logs
@@ -236,6 +899,7 @@ impl DockerManager {
                     http_reachable: false,
                     container_running: false,
                     error_message: Some(error_message),
+                    error_lines: logs.as_deref().map(extract_error_lines).unwrap_or_default(),
                     logs_tail: logs,
                 };
             }
@@ -249,6 +913,7 @@ impl DockerManager {
                     container_running: true,
                     error_message: None,
                     logs_tail: None,
+                    error_lines: Vec::new(),
                 };
             }
 
@@ -267,7 +932,10 @@ impl DockerManager {
             container_id, max_wait_secs, is_running
         );
 
-        let logs = self.get_container_logs(container_id, Some(100)).await.ok();
+        let logs = self
+            .get_container_logs(container_id, Some(100), false)
+            .await
+            .ok();
 
         let error_message = if is_running {
             format!(
@@ -283,45 +951,272 @@ impl DockerManager {
             http_reachable: false,
             container_running: is_running,
             error_message: Some(error_message),
+            error_lines: logs.as_deref().map(extract_error_lines).unwrap_or_default(),
             logs_tail: logs,
         }
     }
 
+    /// 校验部署配置，返回面向用户的字段错误
+    ///
+    /// 返回全部字段错误而不是第一个，部署接口与 `POST /config/validate` 共用，保证提示一致。
+    pub fn validate_config(&self, echokit_config: &EchoKitConfig) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if let Some(history) = echokit_config.llm.history {
+            if history > self.config.llm_history_max {
+                errors.push(FieldError::new(
+                    "llm.history",
+                    format!(
+                        "llm.history must be between 0 and {}, got {}",
+                        self.config.llm_history_max, history
+                    ),
+                ));
+            }
+        }
+        if let ASRConfig::Openai {
+            vad:
+                Some(VadConfig {
+                    threshold: Some(threshold),
+                    ..
+                }),
+            ..
+        } = &echokit_config.asr
+        {
+            if !(0.0..=1.0).contains(threshold) {
+                errors.push(FieldError::new(
+                    "asr.vad.threshold",
+                    format!(
+                        "asr.vad.threshold must be between 0 and 1, got {}",
+                        threshold
+                    ),
+                ));
+            }
+        }
+        if echokit_config.has_masked_secrets() {
+            errors.push(FieldError::new(
+                "secrets",
+                "Config contains masked secrets, please fill in the API keys before deploying",
+            ));
+        }
+        self.validate_required_fields(echokit_config, &mut errors);
+        self.validate_field_lengths(echokit_config, &mut errors);
+        errors.extend(validate_endpoints(
+            echokit_config,
+            &self.config.allowed_endpoint_hosts,
+        ));
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// 校验所选平台的必填字段（密钥等）不为空
+    fn validate_required_fields(
+        &self,
+        echokit_config: &EchoKitConfig,
+        errors: &mut Vec<FieldError>,
+    ) {
+        for (field, value) in echokit_config.required_fields() {
+            if value.trim().is_empty() {
+                let platform = if field.starts_with("tts.") {
+                    format!(" for {}", echokit_config.tts.display_name())
+                } else {
+                    String::new()
+                };
+                errors.push(FieldError::new(
+                    field,
+                    format!("{} is required{}", field, platform),
+                ));
+            }
+        }
+    }
+
+    /// 校验文本字段长度以及生成的 config.toml 大小
+    fn validate_field_lengths(&self, echokit_config: &EchoKitConfig, errors: &mut Vec<FieldError>) {
+        let limits = [
+            (
+                echokit_config.prompt_fields(),
+                self.config.max_prompt_length,
+            ),
+            (
+                echokit_config.text_fields(),
+                self.config.max_text_field_length,
+            ),
+        ];
+        for (fields, max) in limits {
+            for (field, value) in fields {
+                let len = value.chars().count();
+                if len > max {
+                    errors.push(FieldError::new(
+                        field,
+                        format!("{} must be at most {} characters, got {}", field, max, len),
+                    ));
+                }
+            }
+        }
+
+        let toml_len = generate_config_toml(echokit_config, &self.config.endpoint_defaults).len();
+        if toml_len > self.config.max_config_toml_bytes {
+            errors.push(FieldError::new(
+                "config",
+                format!(
+                    "Generated config.toml must be at most {} bytes, got {}",
+                    self.config.max_config_toml_bytes, toml_len
+                ),
+            ));
+        }
+    }
+
+    /// 校验额外暴露的容器端口，返回面向用户的错误描述
+    pub fn validate_extra_ports(&self, extra_ports: &[u16]) -> Result<(), String> {
+        for (i, port) in extra_ports.iter().enumerate() {
+            if *port == 0 || *port == ECHOKIT_CONTAINER_PORT {
+                return Err(format!(
+                    "extraPorts must not contain 0 or the primary port {}, got {}",
+                    ECHOKIT_CONTAINER_PORT, port
+                ));
+            }
+            if extra_ports[..i].contains(port) {
+                return Err(format!("extraPorts contains duplicate port {}", port));
+            }
+        }
+        Ok(())
+    }
+
+    /// 校验容器对外展示的主机名，返回面向用户的错误描述
+    pub fn validate_advertised_host(&self, host: &str) -> Result<(), String> {
+        validate_advertised_host(host, &self.config.managed_domains)
+    }
+
+    /// 校验容器内部主机名，返回面向用户的错误描述
+    pub fn validate_hostname(&self, hostname: &str) -> Result<(), String> {
+        if !is_valid_hostname(hostname) {
+            return Err(format!("hostname is not a valid host name: {}", hostname));
+        }
+        Ok(())
+    }
+
+    /// 校验容器描述长度，返回面向用户的错误描述
+    pub fn validate_description(&self, description: &str) -> Result<(), String> {
+        if description.chars().count() > MAX_DESCRIPTION_CHARS {
+            return Err(format!(
+                "description must be at most {} characters",
+                MAX_DESCRIPTION_CHARS
+            ));
+        }
+        Ok(())
+    }
+
+    /// 构建展示给用户的 WebSocket 地址（优先使用容器的对外主机名）
+    fn container_ws_url(&self, advertised_host: Option<&str>, port: u16) -> String {
+        let host = advertised_host.unwrap_or_else(|| self.config.get_container_host());
+        format!("ws://{}:{}/ws/{{device_id}}", host, port)
+    }
+
+    /// 部署使用的 Docker 网络：请求指定的优先，其次为全局配置
+    pub fn resolve_network<'a>(&'a self, requested: Option<&'a str>) -> Option<&'a str> {
+        requested.or(self.config.docker_network.as_deref())
+    }
+
+    /// 校验 Docker 网络是否存在，返回面向用户的错误描述
+    pub async fn validate_network(&self, network: &str) -> Result<(), String> {
+        match self
+            .docker
+            .inspect_network(network, None::<InspectNetworkOptions>)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(bollard::errors::Error::DockerResponseServerError {
+                status_code: 404, ..
+            }) => Err(format!("Docker network does not exist: {}", network)),
+            Err(e) => {
+                error!("查询 Docker 网络失败: {}, 错误: {}", network, e);
+                Err(format!(
+                    "Failed to inspect Docker network {}: {}",
+                    network, e
+                ))
+            }
+        }
+    }
+
     /// 部署新的 EchoKit 容器
     pub async fn deploy(
         &self,
         echokit_config: EchoKitConfig,
-        port: Option<u16>,
+        target: DeployTarget<'_>,
+    ) -> Result<DeployResponse> {
+        // 无论部署成功与否都释放预留的端口，成功时端口已由容器与数据库记录占用
+        let mut reserved = Vec::new();
+        let result = self
+            .deploy_reserving(echokit_config, target, &mut reserved)
+            .await;
+        self.release_ports(&reserved).await;
+        result
+    }
+
+    /// 执行部署，预留的宿主机端口记入 `reserved` 由调用方释放
+    async fn deploy_reserving(
+        &self,
+        mut echokit_config: EchoKitConfig,
+        target: DeployTarget<'_>,
+        reserved: &mut Vec<u16>,
     ) -> Result<DeployResponse> {
+        let DeployTarget {
+            port,
+            extra_ports,
+            advertised_host,
+            hostname,
+            network,
+            image,
+            start,
+            wait_for_health,
+        } = target;
+        let image = image.unwrap_or(self.config.docker_image.as_str());
+        // 先确认 Docker 可达，避免在后续步骤中报出难以理解的错误
+        self.docker
+            .ping()
+            .await
+            .map_err(|e| DeployError::DockerUnavailable(e.to_string()))?;
+        echokit_config
+            .llm
+            .history
+            .get_or_insert(self.config.llm_history_default);
+
         let container_name = echokit_config.name.clone();
         let port = match port {
-            Some(p) => p,
+            Some(p) => {
+                self.reserve_port(p).await?;
+                p
+            }
             None => self.allocate_port().await.context("Failed to allocate port")?,
         };
+        reserved.push(port);
+
+        // 额外端口映射到自动分配的宿主机端口
+        let mut extra_port_mappings = Vec::with_capacity(extra_ports.len());
+        for container_port in extra_ports {
+            let host_port = self
+                .allocate_port()
+                .await
+                .context("Failed to allocate port for extra container port")?;
+            reserved.push(host_port);
+            extra_port_mappings.push(PortMapping {
+                container_port: *container_port,
+                host_port,
+            });
+        }
 
         info!(
             "[1/5] 准备部署: 容器名='{}', 端口={}, 镜像='{}'",
-            container_name, port, self.config.docker_image
+            container_name, port, image
         );
 
         // 生成配置文件
         info!("[2/5] 生成配置文件...");
-        let config_content = generate_config_toml(&echokit_config);
+        let config_path = self.write_config_file(&echokit_config).await?;
         let config_dir = Path::new(&self.config.config_dir).join(&container_name);
 
-        debug!("创建配置目录: {:?}", config_dir);
-        fs::create_dir_all(&config_dir)
-            .await
-            .context(format!("Failed to create config directory: {:?}", config_dir))?;
-
-        let config_path = config_dir.join("config.toml");
-        debug!("写入配置文件: {:?}", config_path);
-        debug!("生成的 config.toml 内容:\n{}", config_content);
-
-        fs::write(&config_path, &config_content)
-            .await
-            .context(format!("Failed to write config file: {:?}", config_path))?;
-
         // 复制 hello.wav
         let hello_wav_dest = config_dir.join("hello.wav");
         if Path::new(&self.config.hello_wav_path).exists() {
@@ -344,13 +1239,26 @@ impl DockerManager {
 
         // 配置端口映射
         let mut port_bindings = HashMap::new();
-        port_bindings.insert(
-            "8080/tcp".to_string(),
-            Some(vec![PortBinding {
-                host_ip: Some("0.0.0.0".to_string()),
-                host_port: Some(port.to_string()),
-            }]),
+        let mut exposed_ports = HashMap::new();
+        let mappings = std::iter::once((ECHOKIT_CONTAINER_PORT, port)).chain(
+            extra_port_mappings
+                .iter()
+                .map(|m| (m.container_port, m.host_port)),
         );
+        for (container_port, host_port) in mappings {
+            let key = format!("{}/tcp", container_port);
+            port_bindings.insert(
+                key.clone(),
+                Some(vec![PortBinding {
+                    host_ip: Some("0.0.0.0".to_string()),
+                    host_port: Some(host_port.to_string()),
+                }]),
+            );
+            exposed_ports.insert(key, HashMap::new());
+        }
+        if !extra_port_mappings.is_empty() {
+            info!("额外端口映射: {:?}", extra_port_mappings);
+        }
 
         // 配置卷挂载
         let config_path_abs = fs::canonicalize(&config_path)
@@ -374,29 +1282,19 @@ impl DockerManager {
 
         debug!("Volume bindings: {:?}", binds);
 
-        let host_config = HostConfig {
-            port_bindings: Some(port_bindings),
-            binds: Some(binds),
-            ..Default::default()
-        };
-
-        // 创建容器配置
-        let env = vec![
-            "RUST_LOG=info".to_string(),
-            format!("CONTAINER_NAME={}", container_name),
-        ];
-
-        // 添加标签以标识 EchoKit 管理的容器
-        let mut labels = HashMap::new();
-        labels.insert("managed-by".to_string(), "echokit-console".to_string());
+        let host_config = container_host_config(port_bindings, binds, network);
+        if let Some(network) = network {
+            info!("容器网络: {}", network);
+        }
 
-        let container_config = ContainerCreateBody {
-            image: Some(self.config.docker_image.clone()),
-            env: Some(env),
-            host_config: Some(host_config),
-            labels: Some(labels),
-            ..Default::default()
-        };
+        let container_config = container_create_body(
+            image,
+            &container_name,
+            hostname,
+            exposed_ports,
+            host_config,
+        );
+        debug!("容器主机名: {:?}", container_config.hostname);
 
         // 创建容器
         let options = CreateContainerOptions {
@@ -406,7 +1304,7 @@ impl DockerManager {
 
         info!(
             "[3/5] 创建 Docker 容器: 镜像='{}', 端口映射={}:8080",
-            self.config.docker_image, port
+            image, port
         );
 
         let response = self
@@ -415,7 +1313,7 @@ impl DockerManager {
             .await
             .context(format!(
                 "Failed to create container '{}'. Please check: 1) Docker daemon is running, 2) Image '{}' exists locally or can be pulled",
-                container_name, self.config.docker_image
+                container_name, image
             ))?;
 
         info!(
@@ -423,6 +1321,20 @@ impl DockerManager {
             &response.id[..12.min(response.id.len())]
         );
 
+        if !start {
+            info!("[4/5] 已按请求跳过启动，容器保持停止状态");
+            return self
+                .record_deployment(
+                    &response.id,
+                    &echokit_config,
+                    port,
+                    extra_port_mappings,
+                    advertised_host,
+                    unstarted_health(),
+                )
+                .await;
+        }
+
         // 启动容器
         info!("[4/5] 启动容器...");
         self.docker
@@ -435,9 +1347,37 @@ impl DockerManager {
 
         info!("[4/5] 容器启动成功");
 
+        if !wait_for_health {
+            info!("[5/5] 已按请求跳过就绪等待，可通过健康检查接口轮询状态");
+            // 以 Docker 报告的运行状态为准，运行中的容器直接记为 running 供 Proxy 路由
+            let health = HealthCheckResult {
+                status: HealthStatus::Unknown,
+                http_reachable: false,
+                container_running: self.is_container_running(&response.id).await,
+                error_message: None,
+                logs_tail: None,
+                error_lines: Vec::new(),
+            };
+            return self
+                .record_deployment(
+                    &response.id,
+                    &echokit_config,
+                    port,
+                    extra_port_mappings,
+                    advertised_host,
+                    health,
+                )
+                .await;
+        }
+
         // 等待容器就绪并进行健康检查
         info!("[5/5] 等待服务就绪，执行健康检查...");
-        let health = self.wait_for_container_ready(&response.id, port, 30).await;
+        let ready_timeout = self
+            .config
+            .ready_timeout_for(image, echokit_config.tts.platform_id());
+        let health = self
+            .wait_for_container_ready(&response.id, port, ready_timeout)
+            .await;
 
         if health.status == HealthStatus::Healthy {
             info!("[5/5] 健康检查通过，服务已就绪");
@@ -448,16 +1388,32 @@ impl DockerManager {
             );
         }
 
-        let status = if health.status == HealthStatus::Healthy {
-            ContainerStatus::Running
-        } else if health.container_running {
-            ContainerStatus::Error
-        } else {
-            ContainerStatus::Stopped
-        };
+        self.record_deployment(
+            &response.id,
+            &echokit_config,
+            port,
+            extra_port_mappings,
+            advertised_host,
+            health,
+        )
+        .await
+    }
+
+    /// 根据健康检查结果确定容器状态，写入数据库并生成部署响应
+    async fn record_deployment(
+        &self,
+        container_id: &str,
+        echokit_config: &EchoKitConfig,
+        port: u16,
+        extra_port_mappings: Vec<PortMapping>,
+        advertised_host: Option<&str>,
+        health: HealthCheckResult,
+    ) -> Result<DeployResponse> {
+        let container_name = echokit_config.name.clone();
+        let status = deployment_status(&health);
 
         let container_host = self.config.get_container_host();
-        let ws_url = format!("ws://{}:{}/ws/{{device_id}}", container_host, port);
+        let ws_url = self.container_ws_url(advertised_host, port);
 
         // 将容器信息写入数据库
         let now = std::time::SystemTime::now()
@@ -465,42 +1421,444 @@ impl DockerManager {
             .unwrap()
             .as_secs() as i64;
 
+        let config_json = self.stored_config_json(echokit_config)?;
+        let extra_ports_json = if extra_port_mappings.is_empty() {
+            None
+        } else {
+            Some(
+                serde_json::to_string(&extra_port_mappings)
+                    .context("Failed to serialize extra port mappings")?,
+            )
+        };
+
         sqlx::query!(
             r#"
-            INSERT INTO containers (id, name, host, port, use_tls, is_default, is_external, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            INSERT INTO containers (id, name, host, port, use_tls, is_default, is_external, created_at, config_json, status, extra_ports_json, advertised_host)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
             ON CONFLICT (id) DO UPDATE SET
                 name = EXCLUDED.name,
                 host = EXCLUDED.host,
                 port = EXCLUDED.port,
                 use_tls = EXCLUDED.use_tls,
+                config_json = EXCLUDED.config_json,
+                status = EXCLUDED.status,
+                extra_ports_json = EXCLUDED.extra_ports_json,
+                advertised_host = EXCLUDED.advertised_host,
                 updated_at = $8
             "#,
-            response.id,
+            container_id,
             container_name,
             container_host,
             port as i32,
             false, // use_tls
             false, // is_default
             false, // is_external
-            now
+            now,
+            config_json,
+            stored_status(&health).as_str(),
+            extra_ports_json,
+            advertised_host
         )
         .execute(&self.pool)
         .await
         .context("Failed to insert container info to database")?;
 
-        info!("容器信息已写入数据库: id={}, name={}, port={}", response.id, container_name, port);
+        info!(
+            "容器信息已写入数据库: id={}, name={}, port={}",
+            container_id, container_name, port
+        );
 
         Ok(DeployResponse {
-            container_id: response.id,
+            container_id: container_id.to_string(),
             container_name,
             port,
             ws_url,
             status,
             health,
+            extra_ports: extra_port_mappings,
+        })
+    }
+
+    /// 生成 config.toml 并写入容器对应的配置目录，返回文件路径
+    async fn write_config_file(&self, echokit_config: &EchoKitConfig) -> Result<PathBuf> {
+        let config_content = generate_config_toml(echokit_config, &self.config.endpoint_defaults);
+        let config_dir = self.rendered_config_dir(&echokit_config.name);
+
+        debug!("创建配置目录: {:?}", config_dir);
+        fs::create_dir_all(&config_dir).await.context(format!(
+            "Failed to create config directory: {:?}",
+            config_dir
+        ))?;
+
+        let config_path = config_dir.join("config.toml");
+        debug!("写入配置文件: {:?}", config_path);
+
+        fs::write(&config_path, &config_content)
+            .await
+            .context(format!("Failed to write config file: {:?}", config_path))?;
+
+        Ok(config_path)
+    }
+
+    /// 容器 config.toml 所在目录
+    fn rendered_config_dir(&self, container_name: &str) -> PathBuf {
+        // 可配置为 tmpfs 目录，避免明文密钥落盘
+        let base_dir = self
+            .config
+            .rendered_config_dir
+            .as_deref()
+            .unwrap_or(&self.config.config_dir);
+        Path::new(base_dir).join(container_name)
+    }
+
+    /// 比较数据库中的结构化配置（重新渲染）与磁盘上实际的 config.toml
+    pub async fn config_diff(&self, id: &str) -> Result<ConfigDiff> {
+        let (_, echokit_config) = self.load_stored_config(id).await?;
+        let expected = generate_config_toml(&echokit_config, &self.config.endpoint_defaults);
+
+        let config_path = self
+            .rendered_config_dir(&echokit_config.name)
+            .join("config.toml");
+        let actual = match fs::read_to_string(&config_path).await {
+            Ok(content) => content,
+            // 文件缺失同样视为漂移，差异显示为整个文件被删除
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(e).context(format!("Failed to read config file: {:?}", config_path))
+            }
+        };
+
+        Ok(diff_config_toml(&expected, &actual))
+    }
+
+    /// 根据数据库中保存的结构化配置重新生成 config.toml 并重启容器
+    pub async fn regenerate_config(&self, id: &str) -> Result<()> {
+        let (container, echokit_config) = self.load_stored_config(id).await?;
+        self.apply_config(&container, &echokit_config).await
+    }
+
+    /// 更新容器使用的密钥，保存后重新生成 config.toml 并重启容器
+    ///
+    /// 只替换请求中提供的密钥，其余配置保持不变
+    pub async fn rotate_keys(&self, id: &str, keys: RotateKeysRequest) -> Result<()> {
+        let (container, mut echokit_config) = self.load_stored_config(id).await?;
+
+        keys.apply_to(&mut echokit_config);
+
+        let config_json = self.stored_config_json(&echokit_config)?;
+        let now = chrono::Utc::now().timestamp();
+        sqlx::query!(
+            r#"
+            UPDATE containers
+            SET config_json = $2, updated_at = $3
+            WHERE id = $1
+            "#,
+            container.id,
+            config_json,
+            now
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to save rotated config")?;
+        info!("容器密钥已更新: {}", container.name);
+
+        self.apply_config(&container, &echokit_config).await
+    }
+
+    /// 读取数据库中保存的结构化配置
+    async fn load_stored_config(&self, id: &str) -> Result<(ContainerInfo, EchoKitConfig)> {
+        let id = self.resolve_container_id(id).await?;
+        let containers = self.list_containers().await?;
+        let container = containers
+            .into_iter()
+            .find(|c| c.id == id)
+            .context("Container not found")?;
+
+        let row = sqlx::query!(
+            r#"SELECT config_json FROM containers WHERE id = $1"#,
+            container.id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch stored config")?;
+
+        let config_json = row
+            .and_then(|r| r.config_json)
+            .context("No stored config for this container, please redeploy it")?;
+        let mut echokit_config = self.parse_stored_config(&config_json)?;
+        // 配置目录以容器名命名，保持与当前容器一致
+        echokit_config.name = container.name.clone();
+
+        Ok((container, echokit_config))
+    }
+
+    /// 导出容器的结构化配置，默认脱敏密钥
+    pub async fn export_config(&self, id: &str, include_secrets: bool) -> Result<EchoKitConfig> {
+        let (_, mut echokit_config) = self.load_stored_config(id).await?;
+        if !include_secrets {
+            echokit_config.mask_secrets();
+        }
+        Ok(echokit_config)
+    }
+
+    /// 写入 config.toml 并重启容器使配置生效
+    async fn apply_config(
+        &self,
+        container: &ContainerInfo,
+        echokit_config: &EchoKitConfig,
+    ) -> Result<()> {
+        let config_path = self.write_config_file(echokit_config).await?;
+        info!("配置文件已重新生成: {:?}", config_path);
+
+        self.docker
+            .restart_container(&container.id, None::<RestartContainerOptions>)
+            .await
+            .context("Failed to restart container")?;
+        self.update_container_status(&container.id, ContainerStatus::Running)
+            .await;
+        info!("容器已重启: {}", container.name);
+
+        Ok(())
+    }
+
+    /// 以源容器的配置部署新容器，可选地转移设备绑定并删除源容器
+    ///
+    /// 设备绑定与默认服务器标记在同一事务中转移，失败时删除新容器，源容器保持不变。
+    pub async fn migrate_container(
+        &self,
+        id: &str,
+        request: &MigrateContainerRequest,
+    ) -> Result<MigrateContainerResponse> {
+        let (source, mut echokit_config) = self.load_stored_config(id).await?;
+
+        echokit_config.name = request
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("{}-migrated", source.name));
+        if echokit_config.name == source.name {
+            return Err(DeployError::Invalid(
+                "name must differ from the source container".to_string(),
+            )
+            .into());
+        }
+        if let Some(image) = request.image.as_deref() {
+            if image.trim().is_empty() || image.chars().any(char::is_whitespace) {
+                return Err(
+                    DeployError::Invalid(format!("image is not valid: {:?}", image)).into(),
+                );
+            }
+        }
+        self.validate_config(&echokit_config)
+            .map_err(|errors| FieldError::join(&errors))
+            .and_then(|_| match request.advertised_host.as_deref() {
+                Some(host) => self.validate_advertised_host(host),
+                None => Ok(()),
+            })
+            .and_then(|_| match request.hostname.as_deref() {
+                Some(hostname) => self.validate_hostname(hostname),
+                None => Ok(()),
+            })
+            .map_err(DeployError::Invalid)?;
+        let network = self.resolve_network(request.network.as_deref());
+        if let Some(network) = network {
+            self.validate_network(network)
+                .await
+                .map_err(DeployError::Invalid)?;
+        }
+
+        info!(
+            "迁移容器: {} -> {} (镜像: {:?})",
+            source.name, echokit_config.name, request.image
+        );
+        let deployment = self
+            .deploy(
+                echokit_config,
+                DeployTarget {
+                    port: request.port,
+                    extra_ports: &[],
+                    advertised_host: request.advertised_host.as_deref(),
+                    hostname: request.hostname.as_deref(),
+                    network,
+                    image: request.image.as_deref(),
+                    start: true,
+                    wait_for_health: true,
+                },
+            )
+            .await
+            .context("Failed to deploy migrated container")?;
+
+        let rebound_devices = if request.rebind_devices {
+            match self
+                .transfer_bindings(&source.id, &deployment.container_id)
+                .await
+            {
+                Ok(count) => count,
+                Err(e) => {
+                    // 回滚：删除新容器，设备仍绑定在源容器上
+                    if let Err(remove_err) = self.delete_container(&deployment.container_id).await {
+                        error!(
+                            "回滚迁移失败，新容器未删除: {}, 错误: {:#}",
+                            deployment.container_id, remove_err
+                        );
+                    }
+                    return Err(e.context("Failed to rebind devices to migrated container"));
+                }
+            }
+        } else {
+            0
+        };
+        info!("设备已转移到新容器: {} 台", rebound_devices);
+
+        let source_deleted = if request.delete_source {
+            match self.delete_container(&source.id).await {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("迁移完成但删除源容器失败: {}, 错误: {:#}", source.id, e);
+                    false
+                }
+            }
+        } else {
+            false
+        };
+
+        Ok(MigrateContainerResponse {
+            source_container_id: source.id,
+            deployment,
+            rebound_devices,
+            source_deleted,
         })
     }
 
+    /// 在同一事务中将源容器的设备绑定与默认服务器标记转移到目标容器，返回转移的设备数
+    async fn transfer_bindings(&self, source_id: &str, target_id: &str) -> Result<u64> {
+        let now = chrono::Utc::now().timestamp();
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to begin transaction")?;
+
+        let rebound = sqlx::query!(
+            r#"
+            UPDATE devices
+            SET bound_container_id = $2, updated_at = $3
+            WHERE bound_container_id = $1
+            "#,
+            source_id,
+            target_id,
+            now
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to rebind devices")?
+        .rows_affected();
+
+        sqlx::query!(
+            r#"
+            UPDATE containers
+            SET is_default = true
+            WHERE id = $2
+              AND EXISTS (SELECT 1 FROM containers WHERE id = $1 AND is_default = true)
+            "#,
+            source_id,
+            target_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to move default server flag")?;
+        sqlx::query!(
+            r#"UPDATE containers SET is_default = false WHERE id = $1"#,
+            source_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to move default server flag")?;
+
+        tx.commit()
+            .await
+            .context("Failed to commit binding transfer")?;
+        Ok(rebound)
+    }
+
+    /// 设置或清除容器的排空标记
+    ///
+    /// 排空中的容器不再接受新的设备连接，已建立的连接不受影响。
+    /// 返回 false 表示数据库中没有该容器。
+    pub async fn set_draining(&self, id: &str, draining: bool) -> Result<bool> {
+        let id = &self.canonical_container_id(id).await;
+        let now = chrono::Utc::now().timestamp();
+        let result = sqlx::query!(
+            r#"
+            UPDATE containers
+            SET draining = $2, updated_at = $3
+            WHERE id = $1 OR name = $1
+            "#,
+            id,
+            draining,
+            now
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to update container drain flag")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 开启或关闭容器的空闲自动停止
+    pub async fn set_auto_stop(&self, id: &str, enabled: bool) -> Result<bool> {
+        let id = &self.canonical_container_id(id).await;
+        let now = chrono::Utc::now().timestamp();
+        let result = sqlx::query!(
+            r#"
+            UPDATE containers
+            SET auto_stop_enabled = $2, last_active_at = $3, updated_at = $3
+            WHERE id = $1 OR name = $1
+            "#,
+            id,
+            enabled,
+            now
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to update container auto-stop flag")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 设置容器描述（去除首尾空白，为空时清除）
+    pub async fn set_description(&self, id: &str, description: &str) -> Result<bool> {
+        let id = &self.canonical_container_id(id).await;
+        let description = Some(description.trim()).filter(|d| !d.is_empty());
+        let now = chrono::Utc::now().timestamp();
+        let result = sqlx::query!(
+            r#"
+            UPDATE containers
+            SET description = $2, updated_at = $3
+            WHERE id = $1 OR name = $1
+            "#,
+            id,
+            description,
+            now
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to update container description")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 对所有未停止的容器并发执行健康检查（限制并发数），返回容器 ID 到检查结果的映射
+    pub async fn health_check_all(&self) -> Result<HashMap<String, HealthCheckResult>> {
+        let containers = self.list_containers().await?;
+        let results = check_unstopped_containers(
+            containers,
+            HEALTH_CHECK_ALL_CONCURRENCY,
+            |container| async move { self.health_check(&container.id, container.port).await },
+        )
+        .await;
+        Ok(results)
+    }
+
     /// 获取所有 EchoKit 容器
     pub async fn list_containers(&self) -> Result<Vec<ContainerInfo>> {
         let mut filters = HashMap::new();
@@ -515,6 +1873,31 @@ impl DockerManager {
         let containers = self.docker.list_containers(Some(options)).await?;
         let mut result = Vec::new();
 
+        // 容器对外主机名与描述保存在数据库中，查询失败时回退到默认主机、不显示描述
+        let mut advertised_hosts: HashMap<String, String> = HashMap::new();
+        let mut descriptions: HashMap<String, String> = HashMap::new();
+        match sqlx::query!(
+            r#"
+            SELECT id, advertised_host, description FROM containers
+            WHERE advertised_host IS NOT NULL OR description IS NOT NULL
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(rows) => {
+                for row in rows {
+                    if let Some(host) = row.advertised_host {
+                        advertised_hosts.insert(row.id.clone(), host);
+                    }
+                    if let Some(description) = row.description {
+                        descriptions.insert(row.id, description);
+                    }
+                }
+            }
+            Err(e) => warn!("查询容器对外主机名与描述失败: {}", e),
+        }
+
         for container in containers {
             let id = container.id.unwrap_or_default();
             let name = container
@@ -524,12 +1907,14 @@ impl DockerManager {
                 .trim_start_matches('/')
                 .to_string();
 
+            // 主端口为容器内 8080 映射到的宿主机端口（可能还暴露了其他端口）
             let port = container
                 .ports
                 .and_then(|ports| {
                     ports
                         .iter()
-                        .find_map(|p| p.public_port.map(|pp| pp as u16))
+                        .filter(|p| p.private_port == ECHOKIT_CONTAINER_PORT)
+                        .find_map(|p| p.public_port)
                 })
                 .unwrap_or(0);
 
@@ -545,9 +1930,10 @@ impl DockerManager {
                 .map(|ts| DateTime::from_timestamp(ts, 0).unwrap_or_default())
                 .unwrap_or_else(Utc::now);
 
-            let container_host = self.config.get_container_host();
-            let ws_url = format!("ws://{}:{}/ws/{{device_id}}", container_host, port);
+            let ws_url =
+                self.container_ws_url(advertised_hosts.get(&id).map(String::as_str), port);
 
+            let description = descriptions.remove(&id);
             result.push(ContainerInfo {
                 id,
                 name,
@@ -556,6 +1942,8 @@ impl DockerManager {
                 status,
                 created_at,
                 health: None, // 列表查询不做健康检查，可通过单独接口获取
+                active_connections: None,
+                description,
             });
         }
 
@@ -564,10 +1952,11 @@ impl DockerManager {
 
     /// 获取单个容器信息（包含健康检查）
     pub async fn get_container(&self, id: &str) -> Result<ContainerInfo> {
+        let id = self.resolve_container_id(id).await?;
         let containers = self.list_containers().await?;
         let mut container = containers
             .into_iter()
-            .find(|c| c.id == id || c.name == id)
+            .find(|c| c.id == id)
             .context("Container not found")?;
 
         // 对单个容器查询执行健康检查
@@ -579,8 +1968,33 @@ impl DockerManager {
         Ok(container)
     }
 
+    /// 同步容器状态到数据库，供 Proxy 在转发前检查
+    ///
+    /// 手动启动、停止会清除自动停止标记；失败只记录日志，不影响 Docker 操作本身的结果
+    async fn update_container_status(&self, id: &str, status: ContainerStatus) {
+        let id = &self.canonical_container_id(id).await;
+        let now = chrono::Utc::now().timestamp();
+        let result = sqlx::query!(
+            r#"
+            UPDATE containers
+            SET status = $2, updated_at = $3, auto_stopped_at = NULL
+            WHERE id = $1 OR name = $1
+            "#,
+            id,
+            status.as_str(),
+            now
+        )
+        .execute(&self.pool)
+        .await;
+
+        if let Err(e) = result {
+            warn!("Failed to update container status for '{}': {}", id, e);
+        }
+    }
+
     /// 停止容器
     pub async fn stop_container(&self, id: &str) -> Result<()> {
+        let id = &self.resolve_container_id(id).await?;
         let options = StopContainerOptions {
             t: Some(10),
             ..Default::default()
@@ -589,20 +2003,39 @@ impl DockerManager {
             .stop_container(id, Some(options))
             .await
             .context("Failed to stop container")?;
+        self.update_container_status(id, ContainerStatus::Stopped)
+            .await;
         Ok(())
     }
 
     /// 启动容器
     pub async fn start_container(&self, id: &str) -> Result<()> {
+        let id = &self.resolve_container_id(id).await?;
         self.docker
             .start_container(id, None::<StartContainerOptions>)
             .await
             .context("Failed to start container")?;
+        self.update_container_status(id, ContainerStatus::Running)
+            .await;
+
+        // 重新开始计算空闲时间，避免刚启动的容器被立即自动停止
+        let now = chrono::Utc::now().timestamp();
+        if let Err(e) = sqlx::query!(
+            r#"UPDATE containers SET last_active_at = $2 WHERE id = $1"#,
+            id,
+            now
+        )
+        .execute(&self.pool)
+        .await
+        {
+            warn!("Failed to update last active time for '{}': {}", id, e);
+        }
         Ok(())
     }
 
     /// 删除容器
     pub async fn delete_container(&self, id: &str) -> Result<()> {
+        let id = &self.resolve_container_id(id).await?;
         // 先尝试停止
         let _ = self.stop_container(id).await;
 
@@ -617,16 +2050,255 @@ impl DockerManager {
         Ok(())
     }
 
+    /// 启动空闲自动停止的后台任务（间隔为 0 时不启动）
+    pub fn spawn_auto_stopper(self: Arc<Self>) {
+        let interval_secs = self.config.auto_stop_check_interval_secs;
+        if interval_secs == 0 {
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.wake_requested_containers().await {
+                    warn!("唤醒容器失败: {:#}", e);
+                }
+                if let Err(e) = self.stop_idle_containers().await {
+                    warn!("停止空闲容器失败: {:#}", e);
+                }
+            }
+        });
+    }
+
+    /// 启动 Proxy 请求唤醒的容器（设备连接到已自动停止的容器时由 Proxy 写入唤醒请求）
+    ///
+    /// 只唤醒由空闲自动停止的容器，手动停止的容器只清除唤醒请求
+    async fn wake_requested_containers(&self) -> Result<()> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, status, auto_stopped_at
+            FROM containers
+            WHERE auto_stop_enabled = true AND wake_requested_at IS NOT NULL
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch wake requests")?;
+
+        for row in rows {
+            if row.auto_stopped_at.is_some() && row.status != ContainerStatus::Running.as_str() {
+                match self.start_container(&row.id).await {
+                    Ok(()) => info!("已唤醒自动停止的容器: id={}", row.id),
+                    Err(e) => {
+                        warn!("唤醒容器失败: id={}, error={:#}", row.id, e);
+                        continue;
+                    }
+                }
+            }
+            sqlx::query!(
+                r#"UPDATE containers SET wake_requested_at = NULL WHERE id = $1"#,
+                row.id
+            )
+            .execute(&self.pool)
+            .await
+            .context("Failed to clear wake request")?;
+        }
+
+        Ok(())
+    }
+
+    /// 停止开启了自动停止且空闲超过阈值的容器
+    ///
+    /// 连接数来自 Proxy；Proxy 不可达时不停止任何容器。
+    async fn stop_idle_containers(&self) -> Result<()> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, created_at, last_active_at
+            FROM containers
+            WHERE auto_stop_enabled = true AND status = 'running'
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch auto-stop containers")?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let Some(counts) = self.fetch_proxy_container_counts().await else {
+            debug!("无法获取 Proxy 连接数，跳过空闲检查");
+            return Ok(());
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        for row in rows {
+            if counts.get(&row.id).copied().unwrap_or(0) > 0 {
+                sqlx::query!(
+                    r#"UPDATE containers SET last_active_at = $2 WHERE id = $1"#,
+                    row.id,
+                    now
+                )
+                .execute(&self.pool)
+                .await
+                .context("Failed to update last active time")?;
+                continue;
+            }
+
+            let idle_secs = now - row.last_active_at.unwrap_or(row.created_at);
+            if idle_secs < self.config.auto_stop_idle_secs as i64 {
+                continue;
+            }
+            match self.stop_container(&row.id).await {
+                Ok(()) => info!("已停止空闲容器: id={}, idle={}s", row.id, idle_secs),
+                Err(e) => {
+                    warn!("停止空闲容器失败: id={}, error={:#}", row.id, e);
+                    continue;
+                }
+            }
+            // stop_container 会清除标记，停止成功后再记录为自动停止
+            sqlx::query!(
+                r#"UPDATE containers SET auto_stopped_at = $2 WHERE id = $1"#,
+                row.id,
+                now
+            )
+            .execute(&self.pool)
+            .await
+            .context("Failed to record auto-stop time")?;
+        }
+
+        Ok(())
+    }
+
+    /// 启动后台对账任务（间隔为 0 时不启动）
+    pub fn spawn_reconciler(self: Arc<Self>) {
+        let interval_secs = self.config.reconcile_interval_secs;
+        if interval_secs == 0 {
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.reconcile().await {
+                    warn!("容器对账失败: {:#}", e);
+                }
+            }
+        });
+    }
+
+    /// 对账 Docker 中的受管容器与 containers 表
+    ///
+    /// - Docker 中已不存在的记录：记录日志，开启 `reconcile_remove_stale` 时删除
+    /// - 缺少记录的受管容器：记录日志，开启 `reconcile_adopt_orphans` 时补录（无结构化配置）
+    pub async fn reconcile(&self) -> Result<()> {
+        let containers = self.list_containers().await?;
+
+        let rows = sqlx::query!(r#"SELECT id FROM containers WHERE is_external = false"#)
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to fetch container rows")?;
+        let row_ids: Vec<String> = rows.into_iter().map(|row| row.id).collect();
+        let (stale_ids, orphans) = find_drift(&containers, &row_ids);
+
+        for stale_id in stale_ids {
+            if self.config.reconcile_remove_stale {
+                sqlx::query!(r#"DELETE FROM containers WHERE id = $1"#, stale_id)
+                    .execute(&self.pool)
+                    .await
+                    .context("Failed to remove stale container row")?;
+                info!("对账: 已删除 Docker 中不存在的容器记录: id={}", stale_id);
+            } else {
+                warn!("对账: 容器记录在 Docker 中不存在: id={}", stale_id);
+            }
+        }
+
+        for orphan in orphans {
+            if self.config.reconcile_adopt_orphans {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO containers (id, name, host, port, use_tls, is_default, is_external, created_at, status)
+                    VALUES ($1, $2, $3, $4, false, false, false, $5, $6)
+                    ON CONFLICT (id) DO NOTHING
+                    "#,
+                    orphan.id,
+                    orphan.name,
+                    self.config.get_container_host(),
+                    orphan.port as i32,
+                    orphan.created_at.timestamp(),
+                    orphan.status.as_str()
+                )
+                .execute(&self.pool)
+                .await
+                .context("Failed to adopt orphan container")?;
+                info!(
+                    "对账: 已补录缺少记录的容器: id={}, name={}",
+                    orphan.id, orphan.name
+                );
+            } else {
+                warn!(
+                    "对账: 受管容器缺少数据库记录: id={}, name={}",
+                    orphan.id, orphan.name
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 获取容器本次启动的时间（Unix 秒）
+    ///
+    /// 取 Docker 记录的 State.StartedAt，重启后随之更新；容器从未启动时回退到数据库中的部署时间
+    async fn deployed_at(&self, id: &str) -> Result<i64> {
+        let info = self.inspect_managed_container(id).await?;
+        if let Some(started_at) = info
+            .state
+            .as_ref()
+            .and_then(|state| state.started_at.as_deref())
+            .and_then(parse_started_at)
+        {
+            return Ok(started_at);
+        }
+
+        let id = &self.canonical_container_id(id).await;
+        let row = sqlx::query!(
+            r#"SELECT created_at FROM containers WHERE id = $1 OR name = $1"#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch container deploy time")?
+        .context("Container not found")?;
+
+        Ok(row.created_at)
+    }
+
     /// 获取容器日志
-    pub async fn get_container_logs(&self, id: &str, tail: Option<usize>) -> Result<String> {
+    ///
+    /// `since_deploy` 为 true 时只返回容器本次（重新）启动之后的日志，未指定 tail 时返回全部。
+    pub async fn get_container_logs(
+        &self,
+        id: &str,
+        tail: Option<usize>,
+        since_deploy: bool,
+    ) -> Result<String> {
         use futures_util::StreamExt;
 
+        let id = &self.resolve_container_id(id).await?;
+        let (since, default_tail) = if since_deploy {
+            (self.deployed_at(id).await? as i32, "all")
+        } else {
+            (0, "100")
+        };
+
         let options = LogsOptions {
             stdout: true,
             stderr: true,
+            since,
             tail: tail
                 .map(|t| t.to_string())
-                .unwrap_or_else(|| "100".to_string()),
+                .unwrap_or_else(|| default_tail.to_string()),
             ..Default::default()
         };
 
@@ -642,4 +2314,1740 @@ impl DockerManager {
 
         Ok(output)
     }
+
+    /// 同时跟随多个容器的日志，合并为一个按到达顺序交错的行流
+    ///
+    /// 每行带 `[容器名] ` 前缀，先输出各容器最近 `tail` 行。任一容器不存在时返回错误；
+    /// 丢弃返回的流即关闭全部子日志流。
+    pub async fn follow_containers_logs(
+        &self,
+        ids: &[String],
+        tail: usize,
+    ) -> Result<BoxStream<'static, String>> {
+        let mut streams = Vec::with_capacity(ids.len());
+        for id in ids {
+            let info = self
+                .inspect_managed_container(id)
+                .await
+                .with_context(|| format!("Container {} not found", id))?;
+            let container_id = info
+                .id
+                .with_context(|| format!("Container {} not found", id))?;
+            let name = info
+                .name
+                .map(|name| name.trim_start_matches('/').to_string())
+                .unwrap_or_else(|| container_id.clone());
+
+            let options = LogsOptions {
+                stdout: true,
+                stderr: true,
+                follow: true,
+                tail: tail.to_string(),
+                ..Default::default()
+            };
+            streams.push((name, self.docker.logs(&container_id, Some(options))));
+        }
+
+        Ok(merge_container_logs(streams))
+    }
+
+    /// 分页获取容器日志
+    ///
+    /// `before` 为上一页返回的游标（日志行的时间戳），返回严格早于该时间的最多
+    /// `limit` 行。Docker 的 `until` 只有秒级精度，因此按秒放宽后再按纳秒时间戳过滤，
+    /// 同一秒内日志过多时逐步扩大 tail 直到取满一页。
+    pub async fn get_container_logs_page(
+        &self,
+        id: &str,
+        before: Option<&str>,
+        limit: usize,
+    ) -> Result<LogsPage> {
+        let id = &self.resolve_container_id(id).await?;
+        let until = match before {
+            Some(cursor) => parse_logs_cursor(cursor).context("Invalid logs cursor")?,
+            None => 0,
+        };
+
+        let mut tail = limit.max(1);
+        let (lines, more_available) = loop {
+            let raw = self.fetch_timestamped_logs(id, tail, until).await?;
+            let more_available = raw.len() >= tail;
+            let mut lines: Vec<LogLine> = raw
+                .into_iter()
+                .filter(|line| before.is_none_or(|cursor| line.timestamp.as_str() < cursor))
+                .collect();
+
+            // 取满一页、Docker 已经没有更早的日志，或单次读取已达上限
+            if lines.len() >= limit || !more_available || tail >= MAX_LOGS_PAGE_TAIL {
+                let skip = lines.len().saturating_sub(limit);
+                break (lines.split_off(skip), more_available);
+            }
+            tail = (tail * 2).min(MAX_LOGS_PAGE_TAIL);
+        };
+
+        let prev_cursor = logs_page_cursor(&lines, limit, more_available);
+        Ok(LogsPage { lines, prev_cursor })
+    }
+
+    /// 获取带时间戳的日志行
+    async fn fetch_timestamped_logs(
+        &self,
+        id: &str,
+        tail: usize,
+        until: i32,
+    ) -> Result<Vec<LogLine>> {
+        use futures_util::StreamExt;
+
+        let options = LogsOptions {
+            stdout: true,
+            stderr: true,
+            timestamps: true,
+            until,
+            tail: tail.to_string(),
+            ..Default::default()
+        };
+
+        let mut logs = self.docker.logs(id, Some(options));
+        let mut output = String::new();
+
+        while let Some(log) = logs.next().await {
+            output.push_str(&log?.to_string());
+        }
+
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let (timestamp, message) = line.split_once(' ').unwrap_or((line, ""));
+                if timestamp.is_empty() {
+                    return None;
+                }
+                Some(LogLine {
+                    timestamp: timestamp.to_string(),
+                    message: message.to_string(),
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+impl DockerManager {
+    /// 不连接 Docker 与数据库的管理器，只用于测试不访问外部服务的方法
+    pub(crate) fn for_tests(config: AppConfig) -> Self {
+        DockerManager {
+            docker: Docker::connect_with_http(
+                "http://127.0.0.1:2375",
+                1,
+                bollard::API_DEFAULT_VERSION,
+            )
+            .unwrap(),
+            config,
+            reserved_ports: Arc::new(RwLock::new(Vec::new())),
+            http_client: reqwest::Client::new(),
+            pool: sqlx::postgres::PgPoolOptions::new()
+                .connect_lazy("postgres://localhost/echokit")
+                .unwrap(),
+            cipher: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(id: &str, port: u16, status: ContainerStatus) -> ContainerInfo {
+        ContainerInfo {
+            id: id.to_string(),
+            name: format!("echokit-server-{}", id),
+            port,
+            ws_url: format!("ws://localhost:{}/ws", port),
+            status,
+            created_at: Utc::now(),
+            health: None,
+            active_connections: None,
+            description: None,
+        }
+    }
+
+    fn error_fields(result: Result<(), Vec<FieldError>>) -> Vec<String> {
+        result
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| e.field)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn stored_config_round_trips() {
+        let manager = DockerManager::for_tests(AppConfig::default());
+        let config = EchoKitConfig::sample();
+
+        let stored = manager.stored_config_json(&config).unwrap();
+        let restored = manager.parse_stored_config(&stored).unwrap();
+        assert_eq!(
+            serde_json::to_value(&restored).unwrap(),
+            serde_json::to_value(&config).unwrap()
+        );
+        assert!(manager.parse_stored_config("{}").is_err());
+    }
+
+    #[tokio::test]
+    async fn stored_config_is_encrypted_with_cipher() {
+        use base64::Engine;
+        let key = base64::engine::general_purpose::STANDARD.encode([7u8; 32]);
+        let manager = DockerManager {
+            cipher: Some(SecretCipher::from_base64_key(&key).unwrap()),
+            ..DockerManager::for_tests(AppConfig::default())
+        };
+        let config = EchoKitConfig::sample();
+
+        let stored = manager.stored_config_json(&config).unwrap();
+        for secret in ["sk-asr", "sk-llm", "sk-tts"] {
+            assert!(!stored.contains(secret), "{secret} stored in plaintext");
+        }
+        let restored = manager.parse_stored_config(&stored).unwrap();
+        assert_eq!(
+            serde_json::to_value(&restored).unwrap(),
+            serde_json::to_value(&config).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn http_health_honors_path_and_accepted_statuses() {
+        use axum::{http::StatusCode, routing::get, Router};
+
+        let app = Router::new()
+            .route(
+                "/healthz",
+                get(|| async { StatusCode::SERVICE_UNAVAILABLE }),
+            )
+            .route("/ready", get(|| async { StatusCode::OK }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let manager = |path: &str, statuses: Vec<(u16, u16)>| {
+            DockerManager::for_tests(AppConfig {
+                health_check_path: path.to_string(),
+                health_check_accepted_statuses: statuses,
+                ..AppConfig::default()
+            })
+        };
+
+        let success = vec![(200, 299)];
+        assert!(
+            !manager("/healthz", success.clone())
+                .check_http_health(port)
+                .await
+        );
+        assert!(manager("/ready", success).check_http_health(port).await);
+        // 未配置状态码集合时任何响应都视为可用
+        assert!(
+            manager("/healthz", Vec::new())
+                .check_http_health(port)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn extra_ports_get_distinct_host_ports() {
+        let manager = DockerManager::for_tests(AppConfig {
+            port_range_start: 47100,
+            port_range_end: 47120,
+            ..AppConfig::default()
+        });
+        let taken = vec![47100];
+
+        assert!(manager.validate_extra_ports(&[9090]).is_ok());
+        assert!(manager.validate_extra_ports(&[9090, 9090]).is_err());
+        assert!(manager
+            .validate_extra_ports(&[ECHOKIT_CONTAINER_PORT])
+            .is_err());
+
+        // 主端口与额外端口依次分配，互不重复且跳过已有容器的端口
+        let primary = manager.allocate_port_among(&taken).await.unwrap();
+        let extra = manager.allocate_port_among(&taken).await.unwrap();
+        assert_ne!(primary, 47100);
+        assert_ne!(extra, 47100);
+        assert_ne!(primary, extra);
+
+        let response = serde_json::to_value(vec![PortMapping {
+            container_port: 9090,
+            host_port: extra,
+        }])
+        .unwrap();
+        assert_eq!(response[0]["containerPort"], 9090);
+        assert_eq!(response[0]["hostPort"], extra);
+    }
+
+    #[test]
+    fn create_body_applies_hostname() {
+        let body = |name: &str, hostname: Option<&str>| {
+            container_create_body(
+                "echokit:latest",
+                name,
+                hostname,
+                HashMap::new(),
+                HostConfig::default(),
+            )
+        };
+
+        assert_eq!(
+            body("echokit-acme", Some("acme-voice")).hostname.as_deref(),
+            Some("acme-voice")
+        );
+        // 未指定时默认使用容器名称
+        assert_eq!(
+            body("echokit-acme", None).hostname.as_deref(),
+            Some("echokit-acme")
+        );
+        // 容器名称不是合法主机名时交给 Docker 决定
+        assert_eq!(body("echokit_acme", None).hostname, None);
+
+        let created = body("echokit-acme", None);
+        assert_eq!(created.image.as_deref(), Some("echokit:latest"));
+        assert_eq!(created.labels.unwrap()["managed-by"], "echokit-console");
+    }
+
+    #[tokio::test]
+    async fn configured_network_reaches_create_body() {
+        let manager = DockerManager::for_tests(AppConfig {
+            docker_network: Some("echokit-net".to_string()),
+            ..AppConfig::default()
+        });
+        let network_mode = |requested: Option<&str>| {
+            let host_config = container_host_config(
+                HashMap::new(),
+                Vec::new(),
+                manager.resolve_network(requested),
+            );
+            container_create_body(
+                "echokit:latest",
+                "echokit-acme",
+                None,
+                HashMap::new(),
+                host_config,
+            )
+            .host_config
+            .unwrap()
+            .network_mode
+        };
+
+        assert_eq!(network_mode(None).as_deref(), Some("echokit-net"));
+        // 部署请求指定的网络优先于全局配置
+        assert_eq!(
+            network_mode(Some("voice-internal")).as_deref(),
+            Some("voice-internal")
+        );
+
+        let default_manager = DockerManager::for_tests(AppConfig::default());
+        assert_eq!(default_manager.resolve_network(None), None);
+    }
+
+    #[tokio::test]
+    async fn validate_hostname_rejects_illegal_names() {
+        let manager = DockerManager::for_tests(AppConfig::default());
+        assert!(manager.validate_hostname("acme-voice").is_ok());
+        assert!(manager.validate_hostname("voice.acme.internal").is_ok());
+        for hostname in [
+            "",
+            "-acme",
+            "acme-",
+            "acme_voice",
+            "acme..voice",
+            &"a".repeat(64),
+        ] {
+            assert!(manager.validate_hostname(hostname).is_err(), "{hostname:?}");
+        }
+    }
+
+    #[test]
+    fn config_diff_reports_hand_edits_without_secrets() {
+        let expected = generate_config_toml(
+            &EchoKitConfig::sample(),
+            &crate::config::EndpointDefaults::default(),
+        );
+        let unchanged = diff_config_toml(&expected, &expected);
+        assert!(!unchanged.drifted);
+        assert!(unchanged.diff.is_empty());
+
+        let edited = expected.replace("gpt-4o-mini", "gpt-4o");
+        let drift = diff_config_toml(&expected, &edited);
+        assert!(drift.drifted);
+        assert!(drift.diff.contains("-model = \"gpt-4o-mini\""));
+        assert!(drift.diff.contains("+model = \"gpt-4o\""));
+        assert!(!drift.diff.contains("sk-llm"));
+
+        // 文件缺失时整个文件显示为被删除
+        let missing = diff_config_toml(&expected, "");
+        assert!(missing.drifted);
+        assert!(missing.diff.contains("+++ config.toml (on disk)"));
+    }
+
+    #[tokio::test]
+    async fn merged_logs_interleave_prefixed_lines() {
+        use futures_util::StreamExt;
+        use tokio::sync::mpsc;
+
+        let stdout = |text: &str| LogOutput::StdOut {
+            message: text.to_string().into_bytes().into(),
+        };
+        let channel = || {
+            let (tx, rx) = mpsc::unbounded_channel::<Result<LogOutput, String>>();
+            let logs = futures_util::stream::unfold(rx, |mut rx| async move {
+                rx.recv().await.map(|chunk| (chunk, rx))
+            });
+            (tx, logs)
+        };
+        let (acme_tx, acme) = channel();
+        let (beta_tx, beta) = channel();
+        let mut lines = merge_container_logs(vec![
+            ("echokit-acme".to_string(), acme),
+            ("echokit-beta".to_string(), beta),
+        ]);
+
+        // 按到达顺序交错输出，一个日志块中的多行分别加前缀
+        acme_tx
+            .send(Ok(stdout("booting\nlistening on 8080\n")))
+            .unwrap();
+        assert_eq!(lines.next().await.unwrap(), "[echokit-acme] booting");
+        assert_eq!(
+            lines.next().await.unwrap(),
+            "[echokit-acme] listening on 8080"
+        );
+        beta_tx.send(Ok(stdout("booting\n"))).unwrap();
+        assert_eq!(lines.next().await.unwrap(), "[echokit-beta] booting");
+        acme_tx.send(Ok(stdout("device connected\n"))).unwrap();
+        assert_eq!(
+            lines.next().await.unwrap(),
+            "[echokit-acme] device connected"
+        );
+        beta_tx.send(Err("connection reset".to_string())).unwrap();
+        assert_eq!(
+            lines.next().await.unwrap(),
+            "[echokit-beta] 日志流中断: connection reset"
+        );
+
+        // 丢弃合并后的流即关闭全部子日志流
+        drop(lines);
+        assert!(acme_tx.is_closed());
+        assert!(beta_tx.is_closed());
+    }
+
+    #[test]
+    fn error_lines_collects_every_matching_line() {
+        let logs = "  INFO starting server\n\
+                    ERROR failed to load config.toml\n\
+                    INFO retrying\n\
+                    thread 'main' panicked at src/main.rs:10:5\n";
+        assert_eq!(
+            extract_error_lines(logs),
+            vec![
+                "ERROR failed to load config.toml".to_string(),
+                "thread 'main' panicked at src/main.rs:10:5".to_string(),
+            ]
+        );
+        assert!(extract_error_lines("INFO all good\n").is_empty());
+    }
+
+    #[test]
+    fn find_drift_reports_both_directions() {
+        let containers = vec![
+            container("live", 10000, ContainerStatus::Running),
+            container("orphan", 10001, ContainerStatus::Stopped),
+        ];
+        let row_ids = vec!["live".to_string(), "removed".to_string()];
+
+        let (stale_ids, orphans) = find_drift(&containers, &row_ids);
+        assert_eq!(stale_ids, vec!["removed"]);
+        assert_eq!(
+            orphans.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(),
+            vec!["orphan"]
+        );
+
+        let (stale_ids, orphans) = find_drift(&containers[..1], &row_ids[..1]);
+        assert!(stale_ids.is_empty() && orphans.is_empty());
+    }
+
+    #[tokio::test]
+    async fn ws_url_uses_advertised_host() {
+        let manager = DockerManager::for_tests(AppConfig {
+            external_host: Some("echokit.example.com".to_string()),
+            managed_domains: vec!["tenants.example.com".to_string()],
+            ..AppConfig::default()
+        });
+
+        assert_eq!(
+            manager.container_ws_url(Some("acme.tenants.example.com"), 10001),
+            "ws://acme.tenants.example.com:10001/ws/{device_id}"
+        );
+        assert_eq!(
+            manager.container_ws_url(None, 10001),
+            "ws://echokit.example.com:10001/ws/{device_id}"
+        );
+
+        assert!(manager
+            .validate_advertised_host("acme.tenants.example.com")
+            .is_ok());
+        assert!(manager
+            .validate_advertised_host("tenants.example.com")
+            .is_ok());
+        assert!(manager
+            .validate_advertised_host("acme.example.org")
+            .is_err());
+        assert!(manager
+            .validate_advertised_host("eviltenants.example.com")
+            .is_err());
+        assert!(manager
+            .validate_advertised_host("acme.tenants.example.com/ws")
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn port_utilization_counts_distinct_ports_in_range() {
+        let manager = DockerManager::for_tests(AppConfig {
+            port_range_start: 10000,
+            port_range_end: 10009,
+            ..AppConfig::default()
+        });
+        let containers = vec![
+            container("a", 10000, ContainerStatus::Running),
+            container("b", 10000, ContainerStatus::Stopped),
+            container("c", 10009, ContainerStatus::Error),
+            // 范围外的端口（如手动指定）不计入
+            container("d", 9000, ContainerStatus::Running),
+        ];
+
+        let utilization = manager.port_utilization(&containers).await;
+        assert_eq!(utilization.used, 2);
+        assert_eq!(utilization.total, 10);
+        let taken = manager.host_ports_of(&containers).await;
+        assert_eq!(manager.ports_in_range(&taken), vec![10000, 10009]);
+    }
+
+    #[tokio::test]
+    async fn validate_config_requires_platform_secrets() {
+        let manager = DockerManager::for_tests(AppConfig::default());
+        let mut config = EchoKitConfig::sample();
+        // 本地 LLM 服务可能不需要密钥
+        config.llm.api_key = String::new();
+        assert!(manager.validate_config(&config).is_ok());
+
+        config.asr = ASRConfig::Paraformer {
+            paraformer_token: String::new(),
+        };
+        config.tts = crate::models::TTSConfig::Elevenlabs {
+            token: " ".to_string(),
+            voice: String::new(),
+            model_id: None,
+            language_code: None,
+        };
+        let errors = manager.validate_config(&config).unwrap_err();
+        assert_eq!(
+            errors.iter().map(|e| e.field.as_str()).collect::<Vec<_>>(),
+            vec!["asr.paraformerToken", "tts.token", "tts.voice"]
+        );
+        assert_eq!(errors[1].message, "tts.token is required for ElevenLabs");
+        assert_eq!(errors[0].message, "asr.paraformerToken is required");
+    }
+
+    #[tokio::test]
+    async fn validate_config_checks_vad_fields() {
+        let manager = DockerManager::for_tests(AppConfig::default());
+        let mut config = EchoKitConfig::sample();
+        if let ASRConfig::Openai { vad, .. } = &mut config.asr {
+            *vad = Some(VadConfig {
+                provider: "silero".to_string(),
+                url: "https://vad.example.com/detect".to_string(),
+                threshold: Some(0.5),
+            });
+        }
+        assert!(manager.validate_config(&config).is_ok());
+
+        if let ASRConfig::Openai { vad: Some(vad), .. } = &mut config.asr {
+            vad.provider = String::new();
+            vad.threshold = Some(1.5);
+        }
+        let errors = manager.validate_config(&config).unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert!(fields.contains(&"asr.vad.provider"), "{fields:?}");
+        assert!(fields.contains(&"asr.vad.threshold"), "{fields:?}");
+    }
+
+    #[tokio::test]
+    async fn validate_config_rejects_over_long_text() {
+        let manager = DockerManager::for_tests(AppConfig {
+            max_prompt_length: 100,
+            max_text_field_length: 20,
+            max_config_toml_bytes: 64 * 1024,
+            ..AppConfig::default()
+        });
+        let mut config = EchoKitConfig::sample();
+
+        config.llm.system_prompt = "字".repeat(100);
+        assert!(manager.validate_config(&config).is_ok());
+
+        config.llm.system_prompt = "字".repeat(101);
+        config.name = "n".repeat(21);
+        assert_eq!(
+            error_fields(manager.validate_config(&config)),
+            vec!["llm.systemPrompt", "name"]
+        );
+
+        // 单个字段未超长，但生成的 config.toml 超过上限
+        let manager = DockerManager::for_tests(AppConfig {
+            max_config_toml_bytes: 256,
+            ..AppConfig::default()
+        });
+        assert_eq!(
+            error_fields(manager.validate_config(&EchoKitConfig::sample())),
+            vec!["config"]
+        );
+    }
+
+    #[tokio::test]
+    async fn validate_config_bounds_llm_history() {
+        let manager = DockerManager::for_tests(AppConfig {
+            llm_history_max: 10,
+            ..AppConfig::default()
+        });
+        let mut config = EchoKitConfig::sample();
+
+        config.llm.history = Some(10);
+        assert!(manager.validate_config(&config).is_ok());
+
+        config.llm.history = Some(11);
+        assert_eq!(
+            error_fields(manager.validate_config(&config)),
+            vec!["llm.history"]
+        );
+    }
+
+    fn health(status: HealthStatus, container_running: bool) -> HealthCheckResult {
+        HealthCheckResult {
+            http_reachable: status == HealthStatus::Healthy,
+            status,
+            container_running,
+            error_message: None,
+            logs_tail: None,
+            error_lines: Vec::new(),
+        }
+    }
+
+    /// 模拟 Docker 的容器查询接口：(完整 ID, 名称, 是否由控制台管理)，
+    /// 与 Docker 一样按完整 ID、ID 前缀或名称匹配
+    async fn fake_docker(containers: Vec<(&'static str, &'static str, bool, u16)>) -> Docker {
+        // Docker 对从未启动的容器报告零值启动时间
+        fake_docker_with_logs(containers, Vec::new(), "0001-01-01T00:00:00Z")
+            .await
+            .0
+    }
+
+    /// 在 `fake_docker` 基础上提供日志接口：`logs` 为带 RFC3339Nano 时间戳的日志行，
+    /// 与 Docker 一样按 since/until（秒）与 tail 过滤后以多路复用帧返回；
+    /// 容器启动时间为 `started_at`。同时返回记录的每次日志请求的查询参数
+    async fn fake_docker_with_logs(
+        containers: Vec<(&'static str, &'static str, bool, u16)>,
+        logs: Vec<String>,
+        started_at: &'static str,
+    ) -> (Docker, Arc<std::sync::Mutex<Vec<String>>>) {
+        use axum::http::{StatusCode, Uri};
+        use axum::response::IntoResponse;
+        use axum::Json;
+
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let logs_requests = requests.clone();
+        // 路径形如 [/v1.xx]/_ping、[/v1.xx]/containers/json、[/v1.xx]/containers/create、
+        // [/v1.xx]/containers/{id}/start、[/v1.xx]/containers/{id}/stop、
+        // [/v1.xx]/containers/{id}/logs 或 [/v1.xx]/containers/{id}/json
+        let inspect = move |uri: Uri| async move {
+            if uri.path().ends_with("/logs") {
+                let query = uri.query().unwrap_or_default().to_string();
+                logs_requests.lock().unwrap().push(query.clone());
+                let param = |key: &str| {
+                    query
+                        .split('&')
+                        .find_map(|kv| kv.strip_prefix(key)?.strip_prefix('='))
+                        .map(str::to_string)
+                };
+                let since: i64 = param("since").and_then(|v| v.parse().ok()).unwrap_or(0);
+                let until: i64 = param("until").and_then(|v| v.parse().ok()).unwrap_or(0);
+                let timestamps = param("timestamps").as_deref() == Some("true");
+
+                let mut selected: Vec<&String> = logs
+                    .iter()
+                    .filter(|line| {
+                        let (timestamp, _) = line.split_once(' ').unwrap();
+                        let time = DateTime::parse_from_rfc3339(timestamp)
+                            .unwrap()
+                            .with_timezone(&Utc);
+                        time.timestamp() >= since
+                            && (until == 0 || time <= DateTime::from_timestamp(until, 0).unwrap())
+                    })
+                    .collect();
+                if let Some(tail) = param("tail").and_then(|t| t.parse::<usize>().ok()) {
+                    selected.drain(..selected.len().saturating_sub(tail));
+                }
+
+                let mut body = Vec::new();
+                for line in selected {
+                    let text = if timestamps {
+                        format!("{}\n", line)
+                    } else {
+                        format!("{}\n", line.split_once(' ').unwrap().1)
+                    };
+                    // stdout 帧：类型、3 字节填充、4 字节大端长度
+                    body.extend([1u8, 0, 0, 0]);
+                    body.extend((text.len() as u32).to_be_bytes());
+                    body.extend(text.as_bytes());
+                }
+                return (StatusCode::OK, body).into_response();
+            }
+            if uri.path().ends_with("/_ping") {
+                return (StatusCode::OK, "OK").into_response();
+            }
+            if uri.path().ends_with("/start") || uri.path().ends_with("/stop") {
+                return StatusCode::NO_CONTENT.into_response();
+            }
+            if uri.path().ends_with("/containers/create") {
+                // 按 name 参数返回预先登记的容器
+                let name = uri
+                    .query()
+                    .and_then(|query| query.split('&').find_map(|kv| kv.strip_prefix("name=")))
+                    .unwrap_or_default();
+                return match containers.iter().find(|(_, n, _, _)| *n == name) {
+                    Some((full_id, ..)) => (
+                        StatusCode::CREATED,
+                        Json(serde_json::json!({ "Id": full_id, "Warnings": [] })),
+                    )
+                        .into_response(),
+                    None => StatusCode::CONFLICT.into_response(),
+                };
+            }
+            if uri.path().ends_with("/containers/json") {
+                let list: Vec<_> = containers
+                    .iter()
+                    .filter(|(_, _, managed, _)| *managed)
+                    .map(|(full_id, name, _, port)| {
+                        // 端口为 0 表示已创建但未启动，Docker 不报告公开端口
+                        let (state, ports) = if *port == 0 {
+                            ("created", serde_json::json!([{ "PrivatePort": ECHOKIT_CONTAINER_PORT, "Type": "tcp" }]))
+                        } else {
+                            ("running", serde_json::json!([{ "PrivatePort": ECHOKIT_CONTAINER_PORT, "PublicPort": port, "Type": "tcp" }]))
+                        };
+                        serde_json::json!({
+                            "Id": full_id,
+                            "Names": [format!("/{}", name)],
+                            "State": state,
+                            "Ports": ports,
+                            "Labels": { "managed-by": "echokit-console" },
+                        })
+                    })
+                    .collect();
+                return (StatusCode::OK, Json(serde_json::Value::from(list))).into_response();
+            }
+            let id = uri
+                .path()
+                .strip_suffix("/json")
+                .and_then(|path| path.rsplit('/').next())
+                .unwrap_or_default()
+                .to_string();
+            let found = containers
+                .iter()
+                .find(|(full_id, name, _, _)| full_id.starts_with(id.as_str()) || *name == id);
+            match found {
+                Some((full_id, name, managed, _)) => {
+                    let labels = if *managed {
+                        serde_json::json!({ "managed-by": "echokit-console" })
+                    } else {
+                        serde_json::json!({})
+                    };
+                    let body = serde_json::json!({
+                        "Id": full_id,
+                        "Name": format!("/{}", name),
+                        "Config": { "Labels": labels },
+                        "State": { "Running": true, "StartedAt": started_at },
+                    });
+                    (StatusCode::OK, Json(body)).into_response()
+                }
+                None => (
+                    StatusCode::NOT_FOUND,
+                    Json(serde_json::json!({ "message": format!("No such container: {}", id) })),
+                )
+                    .into_response(),
+            }
+        };
+        let app = axum::Router::new().fallback(inspect);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let docker =
+            Docker::connect_with_http(&format!("http://{}", addr), 5, bollard::API_DEFAULT_VERSION)
+                .unwrap();
+        (docker, requests)
+    }
+
+    #[tokio::test]
+    async fn names_and_prefixes_resolve_to_managed_container_ids() {
+        let manager = DockerManager {
+            docker: fake_docker(vec![
+                ("4f9a1c2b3d4e5f60718293a4b5c6d7e8", "echokit-acme", true, 9001),
+                ("9e8d7c6b5a4f3e2d1c0b9a8f7e6d5c4b", "postgres", false, 0),
+            ])
+            .await,
+            ..DockerManager::for_tests(AppConfig::default())
+        };
+        let full_id = "4f9a1c2b3d4e5f60718293a4b5c6d7e8";
+
+        assert_eq!(
+            manager.resolve_container_id("echokit-acme").await.unwrap(),
+            full_id
+        );
+        assert_eq!(
+            manager.resolve_container_id("4f9a1c2b").await.unwrap(),
+            full_id
+        );
+        assert_eq!(
+            manager.resolve_container_id(full_id).await.unwrap(),
+            full_id
+        );
+
+        // 非本控制台管理的容器：按名称或 ID 都视为不存在
+        for id in ["postgres", "9e8d7c6b"] {
+            let err = manager.resolve_container_id(id).await.unwrap_err();
+            assert_eq!(err.to_string(), "Container not found");
+        }
+        assert!(manager.resolve_container_id("missing").await.is_err());
+
+        // Docker 中不存在的外部服务器保持原 ID
+        assert_eq!(
+            manager.canonical_container_id("external-dallas").await,
+            "external-dallas"
+        );
+        assert_eq!(
+            manager.canonical_container_id("echokit-acme").await,
+            full_id
+        );
+    }
+
+    #[tokio::test]
+    async fn proxy_rate_limit_keeps_retry_after() {
+        use axum::http::{header, StatusCode};
+
+        // 模拟限流中的 Proxy 连接测试接口
+        let app = axum::Router::new().route(
+            "/ws-test/{device_id}",
+            axum::routing::get(|| async {
+                (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, "7")])
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let manager = DockerManager::for_tests(AppConfig {
+            proxy_ws_url: Some(format!("ws://{}", addr)),
+            ..AppConfig::default()
+        });
+
+        let err = manager
+            .test_device_route("98:A3:16:F0:B1:E5")
+            .await
+            .unwrap_err();
+        let limited = err.downcast_ref::<ProxyRateLimited>().unwrap();
+        assert_eq!(limited.retry_after, Duration::from_secs(7));
+    }
+
+    #[tokio::test]
+    async fn ports_report_predicts_the_allocated_port() {
+        // 范围起点被宿主机其他进程占用
+        let listener = std::net::TcpListener::bind(("0.0.0.0", 0)).unwrap();
+        let start = listener.local_addr().unwrap().port();
+        let manager = DockerManager {
+            docker: fake_docker(vec![(
+                "4f9a1c2b3d4e5f60718293a4b5c6d7e8",
+                "echokit-acme",
+                true,
+                start + 1,
+            )])
+            .await,
+            ..DockerManager::for_tests(AppConfig {
+                port_range_start: start,
+                port_range_end: start + 3,
+                ..AppConfig::default()
+            })
+        };
+        // 正在部署中的端口
+        manager.reserved_ports.write().await.push(start + 2);
+
+        let report = manager.ports_report().await.unwrap();
+        assert_eq!((report.range_start, report.range_end), (start, start + 3));
+        assert_eq!(report.used_ports, vec![start + 1]);
+        assert_eq!(report.total, 4);
+        assert_eq!(report.utilization_percent, 25.0);
+        assert_eq!(report.next_free_port, Some(start + 3));
+        // 报告不预留端口
+        assert_eq!(*manager.reserved_ports.read().await, vec![start + 2]);
+
+        let allocated = manager.allocate_port().await.unwrap();
+        assert_eq!(Some(allocated), report.next_free_port);
+        let report = manager.ports_report().await.unwrap();
+        assert_eq!(report.next_free_port, None);
+    }
+
+    #[tokio::test]
+    async fn explicit_port_collisions_are_rejected_before_deploy() {
+        let manager = DockerManager {
+            docker: fake_docker(vec![(
+                "4f9a1c2b3d4e5f60718293a4b5c6d7e8",
+                "echokit-acme",
+                true,
+                9001,
+            )])
+            .await,
+            ..DockerManager::for_tests(AppConfig {
+                port_range_start: 1024,
+                port_range_end: 65535,
+                ..AppConfig::default()
+            })
+        };
+        let target = |port| DeployTarget {
+            port: Some(port),
+            extra_ports: &[],
+            advertised_host: None,
+            hostname: None,
+            network: None,
+            image: None,
+            start: true,
+            wait_for_health: true,
+        };
+
+        // 已被托管容器使用的端口
+        let err = manager
+            .deploy(EchoKitConfig::sample(), target(9001))
+            .await
+            .unwrap_err();
+        match err.downcast_ref::<DeployError>() {
+            Some(DeployError::PortAllocated { port, container }) => {
+                assert_eq!(*port, 9001);
+                assert_eq!(container, "echokit-acme");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // 超出端口范围
+        let err = manager
+            .deploy(EchoKitConfig::sample(), target(80))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DeployError>(),
+            Some(DeployError::Invalid(_))
+        ));
+
+        // 被宿主机其他进程占用
+        let listener = std::net::TcpListener::bind(("0.0.0.0", 0)).unwrap();
+        let busy = listener.local_addr().unwrap().port();
+        let err = manager
+            .deploy(EchoKitConfig::sample(), target(busy))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DeployError>(),
+            Some(DeployError::HostPortInUse(p)) if *p == busy
+        ));
+
+        // 被拒绝的端口不会被预留
+        assert!(manager.reserved_ports.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn in_flight_reservations_block_the_port_until_released() {
+        let start = {
+            let listener = std::net::TcpListener::bind(("0.0.0.0", 0)).unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let data_dir = std::env::temp_dir().join(format!("test-reserve-{start}"));
+        let manager = DockerManager {
+            docker: fake_docker(vec![]).await,
+            ..DockerManager::for_tests(AppConfig {
+                port_range_start: start,
+                port_range_end: start + 1,
+                config_dir: data_dir.join("configs").to_string_lossy().into_owned(),
+                record_dir: data_dir.join("records").to_string_lossy().into_owned(),
+                ..AppConfig::default()
+            })
+        };
+
+        // 同一端口不能被两个进行中的部署预留，自动分配也会跳过它
+        manager.reserve_port(start).await.unwrap();
+        let err = manager.reserve_port(start).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DeployError>(),
+            Some(DeployError::PortReserved(p)) if *p == start
+        ));
+        assert_eq!(manager.allocate_port().await.unwrap(), start + 1);
+
+        manager.release_ports(&[start, start + 1]).await;
+        assert!(manager.reserved_ports.read().await.is_empty());
+        manager.reserve_port(start).await.unwrap();
+        manager.release_ports(&[start]).await;
+
+        // 部署失败（Docker 拒绝创建容器）后预留的端口被释放
+        let result = manager
+            .deploy(
+                EchoKitConfig::sample(),
+                DeployTarget {
+                    port: Some(start),
+                    extra_ports: &[9090],
+                    advertised_host: None,
+                    hostname: None,
+                    network: None,
+                    image: None,
+                    start: true,
+                    wait_for_health: true,
+                },
+            )
+            .await;
+        std::fs::remove_dir_all(&data_dir).ok();
+        assert!(result.is_err());
+        assert!(manager.reserved_ports.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unstarted_containers_keep_their_host_ports() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL 未设置，跳过数据库测试");
+            return;
+        };
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&url)
+            .await
+            .unwrap();
+        let suffix = Utc::now().timestamp_nanos_opt().unwrap();
+        let container_id: &'static str = format!("test-unstarted-id-{suffix}").leak();
+        let name: &'static str = format!("test-unstarted-{suffix}").leak();
+        let start = {
+            let listener = std::net::TcpListener::bind(("0.0.0.0", 0)).unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        // 已创建但未启动：Docker 不报告公开端口，主端口与额外端口只记录在数据库中
+        let extra_ports = serde_json::to_string(&[PortMapping {
+            container_port: 9090,
+            host_port: start + 1,
+        }])
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO containers (id, name, host, port, created_at, status, extra_ports_json) VALUES ($1, $2, 'dallas.echokit.dev', $3, 0, 'stopped', $4)",
+        )
+        .bind(container_id)
+        .bind(name)
+        .bind(i32::from(start))
+        .bind(&extra_ports)
+        .execute(&pool)
+        .await
+        .unwrap();
+        let manager = DockerManager {
+            docker: fake_docker(vec![(container_id, name, true, 0)]).await,
+            pool: pool.clone(),
+            ..DockerManager::for_tests(AppConfig {
+                port_range_start: start,
+                port_range_end: start + 2,
+                ..AppConfig::default()
+            })
+        };
+
+        let primary = manager.reserve_port(start).await;
+        let extra = manager.reserve_port(start + 1).await;
+        let report = manager.ports_report().await;
+        let allocated = manager.allocate_port().await;
+
+        sqlx::query("DELETE FROM containers WHERE id = $1")
+            .bind(container_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        for (err, port) in [
+            (primary.unwrap_err(), start),
+            (extra.unwrap_err(), start + 1),
+        ] {
+            match err.downcast_ref::<DeployError>() {
+                Some(DeployError::PortAllocated { port: p, container }) => {
+                    assert_eq!(*p, port);
+                    assert_eq!(container, name);
+                }
+                other => panic!("unexpected error: {:?}", other),
+            }
+        }
+        assert_eq!(report.unwrap().used_ports, vec![start, start + 1]);
+        assert_eq!(allocated.unwrap(), start + 2);
+    }
+
+    #[tokio::test]
+    async fn unstarted_deployment_is_recorded_as_stopped() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL 未设置，跳过数据库测试");
+            return;
+        };
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&url)
+            .await
+            .unwrap();
+        let manager = DockerManager {
+            pool: pool.clone(),
+            ..DockerManager::for_tests(AppConfig::default())
+        };
+        let container_id = format!("test-created-{}", Utc::now().timestamp_nanos_opt().unwrap());
+        let mut config = EchoKitConfig::sample();
+        config.name = container_id.clone();
+
+        let deployment = manager
+            .record_deployment(
+                &container_id,
+                &config,
+                47150,
+                Vec::new(),
+                None,
+                unstarted_health(),
+            )
+            .await
+            .unwrap();
+        let (status, config_json): (String, Option<String>) =
+            sqlx::query_as("SELECT status, config_json FROM containers WHERE id = $1")
+                .bind(&container_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+
+        sqlx::query("DELETE FROM containers WHERE id = $1")
+            .bind(&container_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(deployment.status, ContainerStatus::Stopped);
+        assert_eq!(deployment.health.status, HealthStatus::Unknown);
+        assert!(!deployment.health.container_running);
+        assert_eq!(status, "stopped");
+        // 配置已保存，之后启动时可直接使用
+        let stored = manager.parse_stored_config(&config_json.unwrap()).unwrap();
+        assert_eq!(stored.name, container_id);
+    }
+
+    #[tokio::test]
+    async fn description_round_trips_through_deploy_and_list() {
+        let manager = DockerManager::for_tests(AppConfig::default());
+        assert!(manager.validate_description(&"字".repeat(1000)).is_ok());
+        assert!(manager.validate_description(&"字".repeat(1001)).is_err());
+
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL 未设置，跳过数据库测试");
+            return;
+        };
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&url)
+            .await
+            .unwrap();
+        let container_id: &'static str = format!(
+            "test-description-{}",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        )
+        .leak();
+        let manager = DockerManager {
+            docker: fake_docker(vec![(container_id, container_id, true, 47151)]).await,
+            pool: pool.clone(),
+            ..DockerManager::for_tests(AppConfig::default())
+        };
+        let mut config = EchoKitConfig::sample();
+        config.name = container_id.to_string();
+        manager
+            .record_deployment(
+                container_id,
+                &config,
+                47151,
+                Vec::new(),
+                None,
+                unstarted_health(),
+            )
+            .await
+            .unwrap();
+
+        // 与 deploy 处理器相同：部署成功后保存描述
+        let updated = manager
+            .set_description(container_id, "  tenant: acme  ")
+            .await
+            .unwrap();
+        let listed = manager.list_containers().await.unwrap();
+        let cleared = manager.set_description(container_id, " ").await.unwrap();
+        let relisted = manager.list_containers().await.unwrap();
+        let missing = manager
+            .set_description("test-description-missing", "x")
+            .await
+            .unwrap();
+
+        sqlx::query("DELETE FROM containers WHERE id = $1")
+            .bind(container_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(updated && cleared && !missing);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].port, 47151);
+        assert_eq!(listed[0].description.as_deref(), Some("tenant: acme"));
+        assert_eq!(relisted[0].description, None);
+    }
+
+    #[tokio::test]
+    async fn deploy_without_health_wait_returns_promptly() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL 未设置，跳过数据库测试");
+            return;
+        };
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&url)
+            .await
+            .unwrap();
+        let suffix = Utc::now().timestamp_nanos_opt().unwrap();
+        let container_id: &'static str = format!("test-nowait-id-{suffix}").leak();
+        let name: &'static str = format!("test-nowait-{suffix}").leak();
+        let data_dir = std::env::temp_dir().join(name);
+        let port = {
+            let listener = std::net::TcpListener::bind(("0.0.0.0", 0)).unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let manager = DockerManager {
+            docker: fake_docker(vec![(container_id, name, true, 0)]).await,
+            pool: pool.clone(),
+            ..DockerManager::for_tests(AppConfig {
+                port_range_start: port,
+                port_range_end: port,
+                config_dir: data_dir.join("configs").to_string_lossy().into_owned(),
+                record_dir: data_dir.join("records").to_string_lossy().into_owned(),
+                ..AppConfig::default()
+            })
+        };
+        let mut config = EchoKitConfig::sample();
+        config.name = name.to_string();
+
+        // 服务并未真正启动，若等待就绪将阻塞到就绪超时（默认 30 秒）
+        let started = std::time::Instant::now();
+        let deployment = manager
+            .deploy(
+                config,
+                DeployTarget {
+                    port: None,
+                    extra_ports: &[],
+                    advertised_host: None,
+                    hostname: None,
+                    network: None,
+                    image: None,
+                    start: true,
+                    wait_for_health: false,
+                },
+            )
+            .await;
+        let elapsed = started.elapsed();
+        let status: Option<String> =
+            sqlx::query_scalar("SELECT status FROM containers WHERE id = $1")
+                .bind(container_id)
+                .fetch_optional(&pool)
+                .await
+                .unwrap();
+
+        sqlx::query("DELETE FROM containers WHERE id = $1")
+            .bind(container_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(&data_dir).unwrap();
+
+        let deployment = deployment.unwrap();
+        assert!(elapsed < Duration::from_secs(5), "{elapsed:?}");
+        assert_eq!(deployment.container_id, container_id);
+        assert_eq!(deployment.port, port);
+        assert_eq!(deployment.status, ContainerStatus::Starting);
+        assert_eq!(deployment.health.status, HealthStatus::Unknown);
+        assert!(deployment.health.container_running);
+        // 数据库中记为 running，Proxy 可立即路由
+        assert_eq!(status.as_deref(), Some("running"));
+    }
+
+    #[tokio::test]
+    async fn migrate_rebinds_devices_to_new_container() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL 未设置，跳过数据库测试");
+            return;
+        };
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&url)
+            .await
+            .unwrap();
+        let manager = DockerManager {
+            pool: pool.clone(),
+            ..DockerManager::for_tests(AppConfig::default())
+        };
+        let suffix = Utc::now().timestamp_nanos_opt().unwrap();
+        let source = format!("test-src-{suffix}");
+        let target = format!("test-dst-{suffix}");
+        let other = format!("test-other-{suffix}");
+
+        for (id, is_default) in [(&source, true), (&target, false)] {
+            sqlx::query(
+                r#"
+                INSERT INTO containers (id, name, host, is_default, created_at)
+                VALUES ($1, $1, 'localhost', $2, $3)
+                "#,
+            )
+            .bind(id)
+            .bind(is_default)
+            .bind(Utc::now().timestamp())
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        let devices = [
+            (format!("test-mig-a-{suffix}"), &source),
+            (format!("test-mig-b-{suffix}"), &source),
+            (format!("test-mig-c-{suffix}"), &other),
+        ];
+        for (device_id, bound) in &devices {
+            sqlx::query(
+                r#"
+                INSERT INTO devices (device_id, name, mac_address, created_at, bound_container_id)
+                VALUES ($1, $1, $1, 0, $2)
+                "#,
+            )
+            .bind(device_id)
+            .bind(bound)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let rebound = manager.transfer_bindings(&source, &target).await.unwrap();
+
+        let bindings: Vec<Option<String>> = sqlx::query_scalar(
+            "SELECT bound_container_id FROM devices WHERE device_id = ANY($1) ORDER BY device_id",
+        )
+        .bind(devices.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>())
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        let defaults: Vec<(String, bool)> =
+            sqlx::query_as("SELECT id, is_default FROM containers WHERE id = ANY($1) ORDER BY id")
+                .bind(vec![source.clone(), target.clone()])
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+
+        sqlx::query("DELETE FROM devices WHERE device_id = ANY($1)")
+            .bind(devices.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>())
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM containers WHERE id = ANY($1)")
+            .bind(vec![source.clone(), target.clone()])
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(rebound, 2);
+        assert_eq!(
+            bindings,
+            vec![Some(target.clone()), Some(target.clone()), Some(other)]
+        );
+        // 默认服务器标记随设备一起转移
+        assert_eq!(defaults, vec![(target, true), (source, false)]);
+    }
+
+    #[tokio::test]
+    async fn health_check_all_returns_a_result_per_unstopped_container() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let containers = vec![
+            container("run1", 10001, ContainerStatus::Running),
+            container("run2", 10002, ContainerStatus::Running),
+            container("run3", 10003, ContainerStatus::Running),
+            container("start", 10004, ContainerStatus::Starting),
+            container("broken", 10005, ContainerStatus::Error),
+            container("off", 10006, ContainerStatus::Stopped),
+        ];
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let results = check_unstopped_containers(containers, 2, |container| {
+            let (in_flight, peak) = (&in_flight, &peak);
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                if container.status == ContainerStatus::Error {
+                    health(HealthStatus::Unhealthy, false)
+                } else {
+                    health(HealthStatus::Healthy, true)
+                }
+            }
+        })
+        .await;
+
+        let mut ids: Vec<&str> = results.keys().map(String::as_str).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec!["broken", "run1", "run2", "run3", "start"]);
+        assert_eq!(results["run1"].status, HealthStatus::Healthy);
+        assert_eq!(results["broken"].status, HealthStatus::Unhealthy);
+        // 并发数受限
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn parse_started_at_reads_docker_timestamps() {
+        assert_eq!(
+            parse_started_at("2024-05-01T12:00:00.123456789Z"),
+            Some(1714564800)
+        );
+        assert_eq!(parse_started_at("0001-01-01T00:00:00Z"), None);
+        assert_eq!(parse_started_at(""), None);
+    }
+
+    #[test]
+    fn is_host_port_free_detects_bound_ports() {
+        let listener = std::net::TcpListener::bind(("0.0.0.0", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(!is_host_port_free(port));
+
+        drop(listener);
+        assert!(is_host_port_free(port));
+    }
+
+    fn log_line(timestamp: &str) -> LogLine {
+        LogLine {
+            timestamp: timestamp.to_string(),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn parse_logs_cursor_rounds_up_to_the_next_second() {
+        assert_eq!(
+            parse_logs_cursor("2024-05-01T12:00:00.5Z"),
+            Some(1714564801)
+        );
+        assert_eq!(parse_logs_cursor("yesterday"), None);
+        assert_eq!(parse_logs_cursor(""), None);
+    }
+
+    /// 2024-05-01 10:00 起第 `second` 秒的一行日志（Docker 的定宽 RFC3339Nano 时间戳）
+    fn docker_log_line(second: u32, nanos: u32, message: &str) -> String {
+        format!(
+            "2024-05-01T10:{:02}:{:02}.{:09}Z {}",
+            second / 60,
+            second % 60,
+            nanos,
+            message
+        )
+    }
+
+    /// 日志请求中的 tail 参数
+    fn requested_tails(requests: &std::sync::Mutex<Vec<String>>) -> Vec<usize> {
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|query| {
+                query
+                    .split('&')
+                    .find_map(|kv| kv.strip_prefix("tail="))?
+                    .parse()
+                    .ok()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn log_pages_cover_the_log_without_gaps_or_overlaps() {
+        const ID: &str = "4f9a1c2b3d4e5f60718293a4b5c6d7e8";
+        // 每秒 3 行，最后一秒突发 10 行，分页游标会落在同一秒内
+        let mut log: Vec<String> = (0..30)
+            .map(|i| docker_log_line(i / 3, (i % 3) * 1000, &format!("line {i}")))
+            .collect();
+        log.extend((0..10).map(|i| docker_log_line(10, i * 1000, &format!("burst {i}"))));
+        let (docker, requests) = fake_docker_with_logs(
+            vec![(ID, "echokit-acme", true, 9001)],
+            log.clone(),
+            "2024-05-01T10:00:00.000000000Z",
+        )
+        .await;
+        let manager = DockerManager {
+            docker,
+            ..DockerManager::for_tests(AppConfig::default())
+        };
+
+        // 从最新一页开始沿游标向前翻页，直到没有更早的日志
+        let mut pages = Vec::new();
+        let mut before: Option<String> = None;
+        loop {
+            let page = manager
+                .get_container_logs_page(ID, before.as_deref(), 4)
+                .await
+                .unwrap();
+            assert!(page.lines.len() <= 4);
+            pages.push(page.lines);
+            match page.prev_cursor {
+                Some(cursor) => before = Some(cursor),
+                None => break,
+            }
+            assert!(pages.len() <= log.len(), "paging does not terminate");
+        }
+
+        let walked: Vec<String> = pages
+            .iter()
+            .rev()
+            .flatten()
+            .map(|line| format!("{} {}", line.timestamp, line.message))
+            .collect();
+        assert_eq!(walked, log);
+        assert!(requested_tails(&requests)
+            .iter()
+            .all(|tail| *tail <= MAX_LOGS_PAGE_TAIL));
+    }
+
+    #[tokio::test]
+    async fn log_page_fetch_stops_at_the_tail_bound() {
+        const ID: &str = "4f9a1c2b3d4e5f60718293a4b5c6d7e8";
+        // 同一秒内的日志超过单次读取上限
+        let log: Vec<String> = (0..MAX_LOGS_PAGE_TAIL as u32 + 50)
+            .map(|i| docker_log_line(0, i, &format!("burst {i}")))
+            .collect();
+        let (docker, requests) = fake_docker_with_logs(
+            vec![(ID, "echokit-acme", true, 9001)],
+            log.clone(),
+            "2024-05-01T10:00:00.000000000Z",
+        )
+        .await;
+        let manager = DockerManager {
+            docker,
+            ..DockerManager::for_tests(AppConfig::default())
+        };
+
+        let cursor = log[60].split_once(' ').unwrap().0;
+        let page = manager
+            .get_container_logs_page(ID, Some(cursor), 20)
+            .await
+            .unwrap();
+
+        // 读取在上限处停止，返回上限内能取到的更早日志，并仍给出游标
+        let tails = requested_tails(&requests);
+        assert_eq!(tails.iter().max(), Some(&MAX_LOGS_PAGE_TAIL));
+        let messages: Vec<&str> = page.lines.iter().map(|l| l.message.as_str()).collect();
+        let expected: Vec<String> = (50..60).map(|i| format!("burst {i}")).collect();
+        assert_eq!(messages, expected);
+        assert_eq!(
+            page.prev_cursor.as_deref(),
+            Some(log[50].split_once(' ').unwrap().0)
+        );
+    }
+
+    #[tokio::test]
+    async fn since_deploy_logs_start_at_the_last_start() {
+        const ID: &str = "4f9a1c2b3d4e5f60718293a4b5c6d7e8";
+        let log = vec![
+            docker_log_line(10, 0, "previous run"),
+            docker_log_line(20, 0, "shutting down"),
+            docker_log_line(30, 200_000_000, "server started"),
+            docker_log_line(40, 0, "listening"),
+        ];
+        // 容器最近一次在 10:00:30 启动
+        let (docker, requests) = fake_docker_with_logs(
+            vec![(ID, "echokit-acme", true, 9001)],
+            log,
+            "2024-05-01T10:00:30.123456789Z",
+        )
+        .await;
+        let manager = DockerManager {
+            docker,
+            ..DockerManager::for_tests(AppConfig::default())
+        };
+
+        let logs = manager.get_container_logs(ID, None, true).await.unwrap();
+        assert_eq!(logs, "server started\nlistening\n");
+        let started = DateTime::parse_from_rfc3339("2024-05-01T10:00:30Z")
+            .unwrap()
+            .timestamp();
+        let query = requests.lock().unwrap().last().cloned().unwrap();
+        assert!(query.contains(&format!("since={started}")), "{query}");
+        assert!(query.contains("tail=all"), "{query}");
+
+        // 不限定本次启动时返回全部历史中的最近日志
+        let logs = manager.get_container_logs(ID, None, false).await.unwrap();
+        assert_eq!(logs.lines().count(), 4);
+    }
+
+    #[test]
+    fn logs_page_cursor_points_at_the_first_line() {
+        let lines = vec![
+            log_line("2024-05-01T12:00:01Z"),
+            log_line("2024-05-01T12:00:02Z"),
+        ];
+        assert_eq!(
+            logs_page_cursor(&lines, 2, false).as_deref(),
+            Some("2024-05-01T12:00:01Z")
+        );
+        // 达到读取上限未取满一页，但仍有更早的日志
+        assert_eq!(
+            logs_page_cursor(&lines, 5, true).as_deref(),
+            Some("2024-05-01T12:00:01Z")
+        );
+        assert_eq!(logs_page_cursor(&lines, 5, false), None);
+        assert_eq!(logs_page_cursor(&[], 5, true), None);
+    }
+
+    #[tokio::test]
+    async fn idle_containers_are_stopped_only_when_proxy_reports_no_connections() {
+        use axum::{routing::get, Json, Router};
+
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL 未设置，跳过数据库测试");
+            return;
+        };
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&url)
+            .await
+            .unwrap();
+        let suffix = Utc::now().timestamp_nanos_opt().unwrap();
+        let idle: &'static str = format!("test-idle-{suffix}").leak();
+        let busy: &'static str = format!("test-busy-{suffix}").leak();
+        let opted_out: &'static str = format!("test-opted-out-{suffix}").leak();
+        let ids = [idle, busy, opted_out];
+        let now = Utc::now().timestamp();
+        for (id, auto_stop) in [(idle, true), (busy, true), (opted_out, false)] {
+            sqlx::query(
+                "INSERT INTO containers (id, name, host, port, created_at, status, auto_stop_enabled, last_active_at) VALUES ($1, $1, 'dallas.echokit.dev', 9001, 0, 'running', $2, $3)",
+            )
+            .bind(id)
+            .bind(auto_stop)
+            .bind(now - 7200)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        // Proxy 报告 busy 容器仍有设备连接
+        let app = Router::new().route(
+            "/metrics/containers",
+            get(move || async move { Json(serde_json::json!({ "containers": { busy: 2 } })) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let unreachable_port = {
+            let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+            listener.local_addr().unwrap().port()
+        };
+
+        let docker = fake_docker(ids.iter().map(|id| (*id, *id, true, 9001)).collect()).await;
+        let manager = |proxy_port: u16| DockerManager {
+            docker: docker.clone(),
+            pool: pool.clone(),
+            ..DockerManager::for_tests(AppConfig {
+                auto_stop_idle_secs: 1800,
+                proxy_health_url: Some(format!("http://127.0.0.1:{proxy_port}/health")),
+                ..AppConfig::default()
+            })
+        };
+        let rows = || async {
+            let mut rows = Vec::new();
+            for id in ids {
+                let row: (String, Option<i64>, Option<i64>) = sqlx::query_as(
+                    "SELECT status, auto_stopped_at, last_active_at FROM containers WHERE id = $1",
+                )
+                .bind(id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+                rows.push(row);
+            }
+            rows
+        };
+
+        // Proxy 不可达时不停止任何容器
+        let unreachable = manager(unreachable_port).stop_idle_containers().await;
+        let before = rows().await;
+        let reachable = manager(proxy_port).stop_idle_containers().await;
+        let after = rows().await;
+
+        sqlx::query("DELETE FROM containers WHERE id = ANY($1)")
+            .bind(&ids[..])
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        unreachable.unwrap();
+        reachable.unwrap();
+        for (status, auto_stopped_at, last_active_at) in &before {
+            assert_eq!(status, "running");
+            assert_eq!(*auto_stopped_at, None);
+            assert_eq!(*last_active_at, Some(now - 7200));
+        }
+
+        let [(idle_status, idle_stopped_at, _), (busy_status, busy_stopped_at, busy_active_at), (opted_out_status, opted_out_stopped_at, _)] =
+            &after[..]
+        else {
+            unreachable!();
+        };
+        // 空闲超过阈值且开启自动停止的容器被停止并记录自动停止时间
+        assert_eq!(idle_status, "stopped");
+        assert!(idle_stopped_at.unwrap() >= now);
+        // 仍有连接的容器继续运行，并刷新最近活跃时间
+        assert_eq!(busy_status, "running");
+        assert_eq!(*busy_stopped_at, None);
+        assert!(busy_active_at.unwrap() >= now);
+        // 未开启自动停止的容器不受影响
+        assert_eq!(opted_out_status, "running");
+        assert_eq!(*opted_out_stopped_at, None);
+    }
+
+    #[test]
+    fn deployment_status_follows_docker_running_state() {
+        assert_eq!(
+            deployment_status(&health(HealthStatus::Healthy, true)),
+            ContainerStatus::Running
+        );
+        // 运行中但健康检查失败：响应为 error，数据库中仍记为 running
+        assert_eq!(
+            deployment_status(&health(HealthStatus::Unhealthy, true)),
+            ContainerStatus::Error
+        );
+        assert_eq!(
+            stored_status(&health(HealthStatus::Unhealthy, true)),
+            ContainerStatus::Running
+        );
+        // 未等待健康检查：响应为 starting，数据库中记为 running 以便 Proxy 路由
+        assert_eq!(
+            deployment_status(&health(HealthStatus::Unknown, true)),
+            ContainerStatus::Starting
+        );
+        assert_eq!(
+            stored_status(&health(HealthStatus::Unknown, true)),
+            ContainerStatus::Running
+        );
+        assert_eq!(
+            deployment_status(&health(HealthStatus::Unhealthy, false)),
+            ContainerStatus::Stopped
+        );
+        assert_eq!(
+            stored_status(&health(HealthStatus::Unhealthy, false)),
+            ContainerStatus::Stopped
+        );
+    }
 }
@@ -1,5 +1,9 @@
 mod echokit_config;
+mod endpoint_policy;
 mod manager;
 
-pub use echokit_config::generate_config_toml;
-pub use manager::DockerManager;
+pub use echokit_config::{generate_config_toml, mask_config_toml_secrets};
+pub use manager::{
+    parse_logs_cursor, DeployError, DeployTarget, DockerManager, ProxyRateLimited,
+    MAX_LOG_STREAM_CONTAINERS,
+};
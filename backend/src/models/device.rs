@@ -11,16 +11,56 @@ pub enum DeviceStatus {
     Unknown,
 }
 
-impl ToString for DeviceStatus {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for DeviceStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            DeviceStatus::Online => "online".to_string(),
-            DeviceStatus::Offline => "offline".to_string(),
-            DeviceStatus::Unknown => "unknown".to_string(),
+            DeviceStatus::Online => write!(f, "online"),
+            DeviceStatus::Offline => write!(f, "offline"),
+            DeviceStatus::Unknown => write!(f, "unknown"),
         }
     }
 }
 
+impl DeviceStatus {
+    /// 解析数据库中存储的状态字符串
+    ///
+    /// 未知取值记录警告后按 Unknown 处理，避免拼写错误等脏数据被静默吞掉。
+    pub fn from_db_str(value: &str) -> Self {
+        match value {
+            "online" => DeviceStatus::Online,
+            "offline" => DeviceStatus::Offline,
+            "unknown" => DeviceStatus::Unknown,
+            other => {
+                tracing::warn!("Unexpected device status in database: {:?}", other);
+                DeviceStatus::Unknown
+            }
+        }
+    }
+}
+
+/// 解析 MAC 地址
+///
+/// 接受冒号分隔、短横线分隔或无分隔符的 MAC 地址（大小写均可），
+/// 返回大写冒号分隔格式（如 "98:A3:16:F0:B1:E5"）；不是合法 MAC 时返回 None。
+pub fn parse_mac_address(mac: &str) -> Option<String> {
+    let hex: String = mac.chars().filter(|c| *c != ':' && *c != '-').collect();
+
+    if hex.len() == 12 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        let hex = hex.to_uppercase();
+        let bytes: Vec<&str> = (0..6).map(|i| &hex[i * 2..i * 2 + 2]).collect();
+        return Some(bytes.join(":"));
+    }
+
+    None
+}
+
+/// 标准化设备 ID（MAC 地址）为数据库存储格式
+///
+/// MAC 格式的 ID 统一转换为大写冒号分隔格式，非 MAC 格式的 ID 原样返回。
+pub fn normalize_device_id(device_id: &str) -> String {
+    parse_mac_address(device_id).unwrap_or_else(|| device_id.to_string())
+}
+
 /// 设备信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -30,9 +70,22 @@ pub struct Device {
     pub mac_address: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bound_container_id: Option<String>,
+    /// 创建时间（存储为 Unix 秒，序列化为 RFC3339）
+    #[serde(with = "super::timestamp::rfc3339")]
     pub created_at: i64,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// 最后连接时间（存储为 Unix 秒，序列化为 RFC3339）
+    #[serde(
+        default,
+        with = "super::timestamp::rfc3339_option",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub last_connected_at: Option<i64>,
+    /// 最近一次连接的来源 IP（由 Proxy 记录）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_ip: Option<String>,
+    /// 最近一次连接上报的 User-Agent 或固件版本标识（由 Proxy 记录）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_user_agent: Option<String>,
     pub status: DeviceStatus,
 }
 
@@ -52,4 +105,216 @@ pub struct RegisterDeviceRequest {
 #[serde(rename_all = "camelCase")]
 pub struct BindServerRequest {
     pub container_id: String,
+    /// 绑定时检查目标服务器健康状态（不健康时仍绑定，但在响应中给出警告）
+    #[serde(default)]
+    pub validate_health: bool,
+}
+
+/// 绑定服务器响应（仅在请求健康检查时返回）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BindServerResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// 设备路由预览（Proxy 会将设备转发到的服务器）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceWsTarget {
+    pub device_id: String,
+    /// 是否可以被路由
+    pub routable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_id: Option<String>,
+    /// 设备未绑定、通过默认服务器回退路由
+    pub fallback: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_url: Option<String>,
+    /// 无法路由的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// 设备当前应使用的连接参数（供设备主动拉取，反映最新的绑定）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceConnectionParams {
+    pub device_id: String,
+    /// 设备应连接的 WebSocket URL（配置了 Proxy 时为 Proxy 地址，否则为服务器直连地址）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// url 是否为 Proxy 地址（Proxy 按最新绑定转发，重新绑定后设备无需更换地址）
+    pub via_proxy: bool,
+    /// url 中签名令牌的过期时间，过期前需重新拉取（未启用令牌时为空）
+    #[serde(
+        default,
+        with = "super::timestamp::rfc3339_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub token_expires_at: Option<i64>,
+    /// 当前绑定（或默认回退）的服务器
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_id: Option<String>,
+    /// 服务器当前是否可以接受连接
+    pub routable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// 设备路由测试结果（经 Proxy 连接测试）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceRouteTest {
+    /// Proxy 是否成功连接到设备所路由的服务器
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// 失败原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// 批量导入设备条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportDeviceEntry {
+    pub device_id: String,
+    pub name: String,
+    pub mac_address: String,
+}
+
+/// 批量导入单行处理结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportDeviceOutcome {
+    Created,
+    SkippedDuplicate,
+    Invalid,
+}
+
+/// 批量导入单行结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportDeviceResult {
+    pub device_id: String,
+    pub outcome: ImportDeviceOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// 设备连接日志
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceConnection {
+    pub id: i64,
+    pub device_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_ip: Option<String>,
+    #[serde(with = "super::timestamp::rfc3339")]
+    pub connected_at: i64,
+    #[serde(
+        default,
+        with = "super::timestamp::rfc3339_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub disconnected_at: Option<i64>,
+    /// 设备->服务器转发的数据帧数（连接结束后由 Proxy 写入）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frames_to_server: Option<i64>,
+    /// 服务器->设备转发的数据帧数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frames_to_device: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forward_errors: Option<i64>,
+    /// 结束方式：device / server 为正常关闭，error 为异常中断
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub close_reason: Option<String>,
+}
+
+/// 连接日志事件类型（决定时间范围过滤所依据的时间戳）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// 建立连接，按 connected_at 过滤
+    Open,
+    /// 断开连接，按 disconnected_at 过滤（仅包含已断开的记录）
+    Close,
+}
+
+impl ConnectionEvent {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "open" | "connect" => Some(Self::Open),
+            "close" | "disconnect" => Some(Self::Close),
+            _ => None,
+        }
+    }
+}
+
+/// 连接日志查询条件（时间为 Unix 秒级时间戳，闭区间）
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionLogFilter {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub event: Option<ConnectionEvent>,
+}
+
+/// 分页连接日志响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionLogPage {
+    pub items: Vec<DeviceConnection>,
+    /// 满足过滤条件的记录总数（不受 limit/offset 影响）
+    pub total: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_status_parses_known_values() {
+        for status in [
+            DeviceStatus::Online,
+            DeviceStatus::Offline,
+            DeviceStatus::Unknown,
+        ] {
+            assert_eq!(DeviceStatus::from_db_str(&status.to_string()), status);
+        }
+    }
+
+    #[test]
+    fn device_status_maps_unexpected_values_to_unknown() {
+        assert_eq!(DeviceStatus::from_db_str("Online"), DeviceStatus::Unknown);
+        assert_eq!(DeviceStatus::from_db_str("onlien"), DeviceStatus::Unknown);
+        assert_eq!(DeviceStatus::from_db_str(""), DeviceStatus::Unknown);
+    }
+
+    #[test]
+    fn normalize_device_id_accepts_common_formats() {
+        let expected = "98:A3:16:F0:B1:E5";
+        assert_eq!(normalize_device_id("98:a3:16:f0:b1:e5"), expected);
+        assert_eq!(normalize_device_id("98-a3-16-f0-b1-e5"), expected);
+        assert_eq!(normalize_device_id("98A316F0B1E5"), expected);
+        assert_eq!(normalize_device_id("98a316f0b1e5"), expected);
+        assert_eq!(normalize_device_id(expected), expected);
+    }
+
+    #[test]
+    fn normalize_device_id_keeps_non_mac_ids() {
+        // 与 Proxy 的 normalize_mac_address 一致：非 MAC 格式原样返回
+        assert_eq!(normalize_device_id("device-01"), "device-01");
+        assert_eq!(normalize_device_id("98a316f0b1"), "98a316f0b1");
+    }
+
+    #[test]
+    fn parse_mac_address_rejects_invalid_input() {
+        assert_eq!(parse_mac_address("98:A3:16:F0:B1"), None);
+        assert_eq!(parse_mac_address("98:A3:16:F0:B1:ZZ"), None);
+        assert_eq!(parse_mac_address(""), None);
+    }
 }
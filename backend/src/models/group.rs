@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// 设备分组
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceGroup {
+    pub id: i64,
+    pub name: String,
+    pub device_ids: Vec<String>,
+    pub member_count: i64,
+    /// 当前在线的成员数量
+    pub online_count: i64,
+    /// 创建时间（存储为 Unix 秒，序列化为 RFC3339）
+    #[serde(with = "super::timestamp::rfc3339")]
+    pub created_at: i64,
+}
+
+/// 创建/更新分组请求（成员列表整体替换）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupRequest {
+    pub name: String,
+    #[serde(default)]
+    pub device_ids: Vec<String>,
+}
+
+/// 分组绑定/解绑响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupBindResponse {
+    /// 本次绑定或解绑的设备数量
+    pub updated_devices: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
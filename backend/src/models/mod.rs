@@ -1,13 +1,21 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 // 设备相关模型
 mod device;
 pub use device::*;
 
+// 设备分组模型
+mod group;
+pub use group::*;
+
+// 时间戳序列化（对外统一为 RFC3339 字符串）
+mod timestamp;
+
 /// ASR 配置 - 根据平台类型区分
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "platform")]
+#[serde(tag = "platform", deny_unknown_fields)]
 pub enum ASRConfig {
     /// OpenAI ASR (Whisper)
     Openai {
@@ -19,6 +27,9 @@ pub enum ASRConfig {
         prompt: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         url: Option<String>,
+        /// VAD 服务（未指定时使用 DEFAULT_ASR_VAD_URL）
+        #[serde(skip_serializing_if = "Option::is_none")]
+        vad: Option<VadConfig>,
     },
     /// Paraformer ASR (阿里)
     Paraformer {
@@ -27,9 +38,102 @@ pub enum ASRConfig {
     },
 }
 
+/// ASR 使用的 VAD（语音活动检测）服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VadConfig {
+    /// VAD 服务提供方标识（如 silero）
+    pub provider: String,
+    pub url: String,
+    /// 判定为语音的阈值（0-1，未指定时使用服务默认值）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f32>,
+}
+
+/// 导出配置时用于替换密钥的占位符
+pub const MASKED_SECRET: &str = "********";
+
+/// 支持的平台（标识与配置中的 platform 字段一致）
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PlatformInfo {
+    pub id: &'static str,
+    pub name: &'static str,
+}
+
+/// 支持的 ASR / TTS 平台列表
+#[derive(Debug, Clone, Serialize)]
+pub struct PlatformsResponse {
+    pub asr: &'static [PlatformInfo],
+    pub tts: &'static [PlatformInfo],
+}
+
+/// 为平台枚举生成平台列表、标识与显示名称
+///
+/// 列表与按变体穷举的 match 出自同一份定义，新增变体而未在此登记时无法编译
+macro_rules! platforms {
+    ($config:ident { $($variant:ident => $name:expr),+ $(,)? }) => {
+        impl $config {
+            /// 支持的平台
+            pub const PLATFORMS: &'static [PlatformInfo] = &[
+                $(PlatformInfo {
+                    id: stringify!($variant),
+                    name: $name,
+                }),+
+            ];
+
+            /// 平台标识（与配置中的 platform 字段一致）
+            pub fn platform_id(&self) -> &'static str {
+                match self {
+                    $($config::$variant { .. } => stringify!($variant)),+
+                }
+            }
+
+            /// 平台显示名称
+            pub fn display_name(&self) -> &'static str {
+                match self {
+                    $($config::$variant { .. } => $name),+
+                }
+            }
+        }
+    };
+}
+
+platforms!(ASRConfig {
+    Openai => "OpenAI (Whisper)",
+    Paraformer => "Paraformer (阿里)",
+});
+
+platforms!(TTSConfig {
+    Openai => "OpenAI",
+    Groq => "Groq",
+    Elevenlabs => "ElevenLabs",
+    GSV => "GSV (GPT-SoVITS)",
+    StreamGSV => "StreamGSV",
+    Fish => "Fish TTS",
+    CosyVoice => "CosyVoice (阿里百炼)",
+});
+
+impl ASRConfig {
+    /// 替换平台使用的密钥
+    pub fn set_secret(&mut self, secret: String) {
+        match self {
+            ASRConfig::Openai { api_key, .. } => *api_key = secret,
+            ASRConfig::Paraformer { paraformer_token } => *paraformer_token = secret,
+        }
+    }
+
+    /// 平台使用的密钥
+    fn secret_mut(&mut self) -> Option<&mut String> {
+        match self {
+            ASRConfig::Openai { api_key, .. } => Some(api_key),
+            ASRConfig::Paraformer { paraformer_token } => Some(paraformer_token),
+        }
+    }
+}
+
 /// LLM 配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct LLMConfig {
     pub url: String,
     pub api_key: String,
@@ -41,7 +145,8 @@ pub struct LLMConfig {
 
 /// TTS 配置 - 根据平台类型区分
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "platform")]
+#[serde(tag = "platform", deny_unknown_fields)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum TTSConfig {
     /// OpenAI TTS
     Openai {
@@ -102,9 +207,39 @@ pub enum TTSConfig {
     },
 }
 
+impl TTSConfig {
+    /// 替换平台使用的密钥
+    pub fn set_secret(&mut self, secret: String) {
+        match self {
+            TTSConfig::Openai { api_key, .. }
+            | TTSConfig::Groq { api_key, .. }
+            | TTSConfig::Fish { api_key, .. } => *api_key = secret,
+            TTSConfig::Elevenlabs { token, .. } | TTSConfig::CosyVoice { token, .. } => {
+                *token = secret
+            }
+            TTSConfig::GSV { api_key, .. } | TTSConfig::StreamGSV { api_key, .. } => {
+                *api_key = Some(secret)
+            }
+        }
+    }
+
+    /// 平台使用的密钥（未配置时为 None）
+    fn secret_mut(&mut self) -> Option<&mut String> {
+        match self {
+            TTSConfig::Openai { api_key, .. }
+            | TTSConfig::Groq { api_key, .. }
+            | TTSConfig::Fish { api_key, .. } => Some(api_key),
+            TTSConfig::Elevenlabs { token, .. } | TTSConfig::CosyVoice { token, .. } => Some(token),
+            TTSConfig::GSV { api_key, .. } | TTSConfig::StreamGSV { api_key, .. } => {
+                api_key.as_mut()
+            }
+        }
+    }
+}
+
 /// EchoKit 完整配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct EchoKitConfig {
     pub name: String,
     pub asr: ASRConfig,
@@ -112,13 +247,260 @@ pub struct EchoKitConfig {
     pub tts: TTSConfig,
 }
 
-/// 部署请求
+impl EchoKitConfig {
+    /// 长文本字段（系统提示词等），返回 (字段路径, 值)
+    pub fn prompt_fields(&self) -> Vec<(&'static str, &str)> {
+        let mut fields = vec![("llm.systemPrompt", self.llm.system_prompt.as_str())];
+        if let ASRConfig::Openai {
+            prompt: Some(prompt),
+            ..
+        } = &self.asr
+        {
+            fields.push(("asr.prompt", prompt.as_str()));
+        }
+        fields
+    }
+
+    /// 短文本字段（名称、模型、音色等），返回 (字段路径, 值)
+    pub fn text_fields(&self) -> Vec<(&'static str, &str)> {
+        let mut fields = vec![
+            ("name", self.name.as_str()),
+            ("llm.model", self.llm.model.as_str()),
+        ];
+        if let ASRConfig::Openai {
+            model, lang, vad, ..
+        } = &self.asr
+        {
+            fields.push(("asr.model", model.as_str()));
+            fields.push(("asr.lang", lang.as_str()));
+            if let Some(vad) = vad {
+                fields.push(("asr.vad.provider", vad.provider.as_str()));
+            }
+        }
+        match &self.tts {
+            TTSConfig::Openai { model, voice, .. } | TTSConfig::Groq { model, voice, .. } => {
+                fields.push(("tts.model", model.as_str()));
+                fields.push(("tts.voice", voice.as_str()));
+            }
+            TTSConfig::Elevenlabs { voice, .. } => fields.push(("tts.voice", voice.as_str())),
+            TTSConfig::GSV { speaker, .. }
+            | TTSConfig::StreamGSV { speaker, .. }
+            | TTSConfig::Fish { speaker, .. } => fields.push(("tts.speaker", speaker.as_str())),
+            TTSConfig::CosyVoice { speaker, .. } => {
+                if let Some(speaker) = speaker {
+                    fields.push(("tts.speaker", speaker.as_str()));
+                }
+            }
+        }
+        fields
+    }
+
+    /// 所选平台必须填写的字段（密钥、模型、音色等），返回 (字段路径, 值)
+    ///
+    /// 为空时 EchoKit Server 只会在运行时报鉴权或参数错误，因此在部署前检查。
+    /// LLM 密钥不在其中：本地部署的 LLM 服务可能不需要密钥。
+    pub fn required_fields(&self) -> Vec<(&'static str, &str)> {
+        let mut fields = vec![
+            ("llm.url", self.llm.url.as_str()),
+            ("llm.model", self.llm.model.as_str()),
+        ];
+        match &self.asr {
+            ASRConfig::Openai {
+                api_key,
+                model,
+                vad,
+                ..
+            } => {
+                fields.push(("asr.apiKey", api_key.as_str()));
+                fields.push(("asr.model", model.as_str()));
+                if let Some(vad) = vad {
+                    fields.push(("asr.vad.provider", vad.provider.as_str()));
+                    fields.push(("asr.vad.url", vad.url.as_str()));
+                }
+            }
+            ASRConfig::Paraformer { paraformer_token } => {
+                fields.push(("asr.paraformerToken", paraformer_token.as_str()));
+            }
+        }
+        match &self.tts {
+            TTSConfig::Openai {
+                api_key,
+                model,
+                voice,
+                ..
+            }
+            | TTSConfig::Groq {
+                api_key,
+                model,
+                voice,
+                ..
+            } => {
+                fields.push(("tts.apiKey", api_key.as_str()));
+                fields.push(("tts.model", model.as_str()));
+                fields.push(("tts.voice", voice.as_str()));
+            }
+            TTSConfig::Elevenlabs { token, voice, .. } => {
+                fields.push(("tts.token", token.as_str()));
+                fields.push(("tts.voice", voice.as_str()));
+            }
+            TTSConfig::GSV { url, speaker, .. } | TTSConfig::StreamGSV { url, speaker, .. } => {
+                fields.push(("tts.url", url.as_str()));
+                fields.push(("tts.speaker", speaker.as_str()));
+            }
+            TTSConfig::Fish { api_key, speaker } => {
+                fields.push(("tts.apiKey", api_key.as_str()));
+                fields.push(("tts.speaker", speaker.as_str()));
+            }
+            TTSConfig::CosyVoice { token, .. } => fields.push(("tts.token", token.as_str())),
+        }
+        fields
+    }
+
+    /// 配置中所有密钥字段（LLM、ASR、TTS）
+    pub fn secrets_mut(&mut self) -> Vec<&mut String> {
+        let mut secrets = vec![&mut self.llm.api_key];
+        secrets.extend(self.asr.secret_mut());
+        secrets.extend(self.tts.secret_mut());
+        secrets
+    }
+
+    /// 将所有密钥替换为占位符（用于导出）
+    pub fn mask_secrets(&mut self) {
+        for secret in self.secrets_mut() {
+            *secret = MASKED_SECRET.to_string();
+        }
+    }
+
+    /// 是否包含未填写的密钥占位符（导入脱敏导出的配置时）
+    pub fn has_masked_secrets(&self) -> bool {
+        self.clone()
+            .secrets_mut()
+            .iter()
+            .any(|secret| secret.as_str() == MASKED_SECRET)
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+/// 部署请求（拒绝未知字段，避免拼写错误的字段被静默忽略）
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct DeployRequest {
     pub config: EchoKitConfig,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub port: Option<u16>,
+    /// 主端口 8080 之外需要额外暴露的容器端口（自动分配宿主机端口）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_ports: Vec<u16>,
+    /// 对外展示的主机名（须在受管域名内），用于生成 WebSocket 地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub advertised_host: Option<String>,
+    /// 容器内部主机名（未指定时使用容器名称）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// 容器加入的 Docker 网络（须已存在，未指定时使用 DOCKER_NETWORK 配置）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    /// 无设备连接一段时间后自动停止，设备再次连接时自动启动
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub auto_stop: bool,
+    /// 创建后立即启动（为 false 时只创建容器，之后通过启动接口启动）
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub start: bool,
+    /// 启动后等待服务就绪再返回（为 false 时立即返回 starting 状态，健康状态为 unknown，之后通过健康检查接口轮询）
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub wait_for_health: bool,
+    /// 容器描述（用途、租户等）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// 容器迁移请求：以源容器的配置在新镜像/主机上部署新容器
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MigrateContainerRequest {
+    /// 新容器名称（未指定时为 "<源容器名>-migrated"）
+    pub name: Option<String>,
+    /// 新容器使用的镜像（未指定时使用 DOCKER_IMAGE 配置）
+    pub image: Option<String>,
+    pub port: Option<u16>,
+    pub advertised_host: Option<String>,
+    pub hostname: Option<String>,
+    pub network: Option<String>,
+    /// 将绑定到源容器的设备（及默认服务器标记）转到新容器
+    #[serde(default)]
+    pub rebind_devices: bool,
+    /// 迁移成功后删除源容器
+    #[serde(default)]
+    pub delete_source: bool,
+}
+
+/// 容器迁移结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrateContainerResponse {
+    pub source_container_id: String,
+    /// 新容器的部署结果
+    pub deployment: DeployResponse,
+    /// 转到新容器的设备数
+    pub rebound_devices: u64,
+    pub source_deleted: bool,
+}
+
+/// 容器端口到宿主机端口的映射
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortMapping {
+    pub container_port: u16,
+    pub host_port: u16,
+}
+
+/// 数据库中的结构化配置与磁盘上 config.toml 的比较结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigDiff {
+    /// 磁盘上的 config.toml 是否与结构化配置渲染结果不一致
+    pub drifted: bool,
+    /// 统一格式的差异（密钥已脱敏，因此仅密钥不同时也可能为空）
+    pub diff: String,
+}
+
+/// 容器信息更新请求（未提供的字段保持不变）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateContainerRequest {
+    /// 新的容器描述，空字符串表示清除
+    pub description: Option<String>,
+}
+
+/// 密钥轮换请求，各字段为对应组件的新密钥（未提供的保持不变）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotateKeysRequest {
+    pub asr: Option<String>,
+    pub llm: Option<String>,
+    pub tts: Option<String>,
+}
+
+impl RotateKeysRequest {
+    /// 用新密钥替换配置中对应组件的密钥
+    pub fn apply_to(self, config: &mut EchoKitConfig) {
+        if let Some(secret) = self.asr {
+            config.asr.set_secret(secret);
+        }
+        if let Some(secret) = self.llm {
+            config.llm.api_key = secret;
+        }
+        if let Some(secret) = self.tts {
+            config.tts.set_secret(secret);
+        }
+    }
 }
 
 /// 容器状态
@@ -132,6 +514,19 @@ pub enum ContainerStatus {
     Starting,
 }
 
+impl ContainerStatus {
+    /// 数据库中存储的状态字符串（与序列化格式一致）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContainerStatus::Running => "running",
+            ContainerStatus::Stopped => "stopped",
+            ContainerStatus::Error => "error",
+            ContainerStatus::Creating => "creating",
+            ContainerStatus::Starting => "starting",
+        }
+    }
+}
+
 /// 健康状态
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -152,6 +547,9 @@ pub struct HealthCheckResult {
     pub error_message: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub logs_tail: Option<String>,
+    /// 日志中匹配错误模式的行（便于前端高亮，完整上下文见 logs_tail）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub error_lines: Vec<String>,
 }
 
 /// 部署响应
@@ -164,6 +562,8 @@ pub struct DeployResponse {
     pub ws_url: String,
     pub status: ContainerStatus,
     pub health: HealthCheckResult,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extra_ports: Vec<PortMapping>,
 }
 
 /// 容器信息
@@ -175,9 +575,93 @@ pub struct ContainerInfo {
     pub port: u16,
     pub ws_url: String,
     pub status: ContainerStatus,
+    /// 创建时间（序列化为 RFC3339，与设备时间戳格式一致）
     pub created_at: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub health: Option<HealthCheckResult>,
+    /// 经 Proxy 连接到该容器的设备数（仅容器详情返回，Proxy 不可达时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_connections: Option<u64>,
+    /// 容器描述（用途、租户等）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// 单行容器日志
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLine {
+    /// Docker 记录的时间戳（RFC3339，纳秒精度）
+    pub timestamp: String,
+    pub message: String,
+}
+
+/// 分页日志响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogsPage {
+    /// 按时间正序排列的日志行
+    pub lines: Vec<LogLine>,
+    /// 获取上一页（更早日志）的游标，已到最早日志时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev_cursor: Option<String>,
+}
+
+/// 端口范围使用情况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortUtilization {
+    pub used: usize,
+    pub total: usize,
+}
+
+/// 端口范围详情：已用端口与下一次自动分配会使用的端口
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortsReport {
+    pub range_start: u16,
+    pub range_end: u16,
+    /// 端口范围内被托管容器占用的端口（升序）
+    pub used_ports: Vec<u16>,
+    pub total: usize,
+    /// 已用端口占范围的百分比
+    pub utilization_percent: f64,
+    /// 部署未指定端口时将分配的端口（范围已满时为空）
+    pub next_free_port: Option<u16>,
+}
+
+/// 管理概览数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminOverview {
+    pub total_containers: usize,
+    pub containers_by_status: HashMap<String, usize>,
+    pub total_devices: i64,
+    pub devices_by_status: HashMap<String, i64>,
+    /// Proxy 当前活跃连接数（未配置或 Proxy 不可达时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_proxy_connections: Option<u64>,
+    pub port_utilization: PortUtilization,
+}
+
+/// 构建信息（版本号、git 提交、构建时间）
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_sha: &'static str,
+    /// 构建时间（Unix 时间戳）
+    pub build_timestamp: u64,
+}
+
+impl BuildInfo {
+    /// 当前二进制的构建信息（由 build.rs 在编译期写入）
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_sha: env!("GIT_SHA"),
+            build_timestamp: env!("BUILD_TIMESTAMP").parse().unwrap_or(0),
+        }
+    }
 }
 
 /// API 错误响应
@@ -186,3 +670,265 @@ pub struct ApiError {
     pub error: String,
     pub message: String,
 }
+
+/// 配置校验错误：字段路径（如 tts.token）与面向用户的描述
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    /// 将多个校验错误合并为一条消息（部署接口的 400 响应使用）
+    pub fn join(errors: &[FieldError]) -> String {
+        errors
+            .iter()
+            .map(|e| e.message.as_str())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// 配置校验结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigValidationResponse {
+    pub valid: bool,
+    pub errors: Vec<FieldError>,
+}
+
+/// 限流（429）错误响应，在 ApiError 之外带上建议的重试等待时间
+#[derive(Debug, Serialize)]
+pub struct RateLimitedError {
+    #[serde(flatten)]
+    pub error: ApiError,
+    pub retry_after_ms: u64,
+}
+
+#[cfg(test)]
+impl EchoKitConfig {
+    /// 测试用的完整配置（OpenAI ASR / LLM / TTS）
+    pub(crate) fn sample() -> Self {
+        EchoKitConfig {
+            name: "echokit-test".to_string(),
+            asr: ASRConfig::Openai {
+                api_key: "sk-asr".to_string(),
+                model: "whisper-1".to_string(),
+                lang: "en".to_string(),
+                prompt: None,
+                url: None,
+                vad: None,
+            },
+            llm: LLMConfig {
+                url: "https://api.openai.com/v1/chat/completions".to_string(),
+                api_key: "sk-llm".to_string(),
+                model: "gpt-4o-mini".to_string(),
+                system_prompt: "You are a helpful assistant.".to_string(),
+                history: None,
+            },
+            tts: TTSConfig::Openai {
+                api_key: "sk-tts".to_string(),
+                model: "tts-1".to_string(),
+                voice: "alloy".to_string(),
+                url: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// 平台列表中的每个标识都必须是配置枚举的 platform 取值，且不重复
+    fn assert_platforms_match_variants<T: serde::de::DeserializeOwned>(platforms: &[PlatformInfo]) {
+        let mut ids: Vec<&str> = platforms.iter().map(|p| p.id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), platforms.len(), "duplicate platform ids");
+
+        for platform in platforms {
+            let error = serde_json::from_value::<T>(json!({ "platform": platform.id }))
+                .err()
+                .map(|e| e.to_string())
+                .unwrap_or_default();
+            assert!(
+                !error.contains("unknown variant"),
+                "{} is not a platform variant: {}",
+                platform.id,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn mask_secrets_replaces_every_secret() {
+        let mut config = EchoKitConfig::sample();
+        assert!(!config.has_masked_secrets());
+
+        config.mask_secrets();
+        assert!(config.has_masked_secrets());
+        assert_eq!(config.llm.api_key, MASKED_SECRET);
+        assert!(config
+            .secrets_mut()
+            .iter()
+            .all(|secret| secret.as_str() == MASKED_SECRET));
+        // 非密钥字段保持不变
+        assert_eq!(config.llm.model, "gpt-4o-mini");
+    }
+
+    #[test]
+    fn platform_lists_cover_config_variants() {
+        assert_platforms_match_variants::<ASRConfig>(ASRConfig::PLATFORMS);
+        assert_platforms_match_variants::<TTSConfig>(TTSConfig::PLATFORMS);
+
+        let tts: TTSConfig =
+            serde_json::from_value(json!({ "platform": "Fish", "apiKey": "k", "speaker": "s" }))
+                .unwrap();
+        assert_eq!(tts.platform_id(), "Fish");
+        assert_eq!(tts.display_name(), "Fish TTS");
+
+        let asr: ASRConfig =
+            serde_json::from_value(json!({ "platform": "Paraformer", "paraformerToken": "t" }))
+                .unwrap();
+        assert_eq!(asr.platform_id(), "Paraformer");
+    }
+
+    #[test]
+    fn asr_config_rejects_unknown_fields() {
+        let valid =
+            json!({ "platform": "Openai", "apiKey": "k", "model": "whisper-1", "lang": "en" });
+        assert!(serde_json::from_value::<ASRConfig>(valid).is_ok());
+
+        let typo = json!({
+            "platform": "Openai",
+            "apiKey": "k",
+            "model": "whisper-1",
+            "lang": "en",
+            "promt": "hi"
+        });
+        assert!(serde_json::from_value::<ASRConfig>(typo).is_err());
+    }
+
+    #[test]
+    fn deploy_request_starts_by_default() {
+        let config = serde_json::to_value(EchoKitConfig::sample()).unwrap();
+        let request: DeployRequest = serde_json::from_value(json!({ "config": config })).unwrap();
+        assert!(request.start);
+
+        let request: DeployRequest =
+            serde_json::from_value(json!({ "config": config, "start": false })).unwrap();
+        assert!(!request.start);
+        // 默认值不写入导出的请求
+        let exported = serde_json::to_value(DeployRequest {
+            start: true,
+            ..request
+        })
+        .unwrap();
+        assert!(exported.get("start").is_none());
+    }
+
+    #[test]
+    fn deploy_request_waits_for_health_by_default() {
+        let config = serde_json::to_value(EchoKitConfig::sample()).unwrap();
+        let request: DeployRequest = serde_json::from_value(json!({ "config": config })).unwrap();
+        assert!(request.wait_for_health);
+
+        let request: DeployRequest =
+            serde_json::from_value(json!({ "config": config, "waitForHealth": false })).unwrap();
+        assert!(!request.wait_for_health);
+        assert_eq!(
+            serde_json::to_value(&request).unwrap()["waitForHealth"],
+            false
+        );
+    }
+
+    #[test]
+    fn vad_config_rejects_unknown_fields() {
+        let typo = json!({ "provider": "silero", "url": "http://vad", "treshold": 0.5 });
+        assert!(serde_json::from_value::<VadConfig>(typo).is_err());
+    }
+
+    #[test]
+    fn tts_config_rejects_unknown_fields() {
+        let valid = json!({ "platform": "Fish", "apiKey": "k", "speaker": "s" });
+        assert!(serde_json::from_value::<TTSConfig>(valid).is_ok());
+
+        let typo = json!({ "platform": "Fish", "apiKey": "k", "speaker": "s", "voice": "v" });
+        assert!(serde_json::from_value::<TTSConfig>(typo).is_err());
+    }
+
+    #[test]
+    fn timestamps_serialize_as_rfc3339_for_every_model() {
+        // 2023-11-14T22:13:20Z
+        let ts = 1_700_000_000;
+        let expected = "2023-11-14T22:13:20Z";
+
+        let device = Device {
+            device_id: "98:A3:16:F0:B1:E5".to_string(),
+            name: "kitchen".to_string(),
+            mac_address: "98:A3:16:F0:B1:E5".to_string(),
+            bound_container_id: None,
+            created_at: ts,
+            last_connected_at: Some(ts + 60),
+            last_ip: None,
+            last_user_agent: None,
+            status: DeviceStatus::Online,
+        };
+        let value = serde_json::to_value(&device).unwrap();
+        assert_eq!(value["createdAt"], expected);
+        assert_eq!(value["lastConnectedAt"], "2023-11-14T22:14:20Z");
+        let parsed: Device = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.created_at, ts);
+        assert_eq!(parsed.last_connected_at, Some(ts + 60));
+
+        // 从未连接的设备不输出 lastConnectedAt
+        let never = Device {
+            last_connected_at: None,
+            ..device
+        };
+        let value = serde_json::to_value(&never).unwrap();
+        assert!(value.get("lastConnectedAt").is_none());
+        assert!(serde_json::from_value::<Device>(value).is_ok());
+
+        let container = ContainerInfo {
+            id: "abc123".to_string(),
+            name: "echokit-server-abc123".to_string(),
+            port: 10000,
+            ws_url: "ws://localhost:10000/ws".to_string(),
+            status: ContainerStatus::Running,
+            created_at: DateTime::from_timestamp(ts, 0).unwrap(),
+            health: None,
+            active_connections: None,
+            description: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&container).unwrap()["createdAt"],
+            expected
+        );
+
+        let group = DeviceGroup {
+            id: 1,
+            name: "lobby".to_string(),
+            device_ids: Vec::new(),
+            member_count: 0,
+            online_count: 0,
+            created_at: ts,
+        };
+        assert_eq!(serde_json::to_value(&group).unwrap()["createdAt"], expected);
+
+        // 非 RFC3339 的输入被拒绝
+        let invalid = json!({
+            "deviceId": "a", "name": "a", "macAddress": "a",
+            "createdAt": 1700000000, "status": "online"
+        });
+        assert!(serde_json::from_value::<Device>(invalid).is_err());
+    }
+}
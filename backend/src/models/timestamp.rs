@@ -0,0 +1,55 @@
+//! 时间戳序列化
+//!
+//! 数据库中的时间戳统一以 Unix 秒存储（`i64`），对外 API 一律序列化为
+//! RFC3339 字符串（如 "2025-01-01T08:00:00Z"），与 `DateTime<Utc>` 的默认
+//! 序列化格式保持一致，前端可直接用 `new Date(value)` 解析。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serializer};
+
+fn to_rfc3339(ts: i64) -> String {
+    DateTime::<Utc>::from_timestamp(ts, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+fn from_rfc3339<E: serde::de::Error>(s: &str) -> Result<i64, E> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.timestamp())
+        .map_err(E::custom)
+}
+
+/// `i64` Unix 秒 <-> RFC3339 字符串
+pub mod rfc3339 {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(ts: &i64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_rfc3339(*ts))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        from_rfc3339(&s)
+    }
+}
+
+/// `Option<i64>` Unix 秒 <-> RFC3339 字符串
+pub mod rfc3339_option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(ts: &Option<i64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match ts {
+            Some(ts) => serializer.serialize_some(&to_rfc3339(*ts)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<i64>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => from_rfc3339(&s).map(Some),
+            None => Ok(None),
+        }
+    }
+}
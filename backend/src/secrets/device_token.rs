@@ -0,0 +1,48 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::hmac;
+
+/// 签名使用的设备 ID 形式（小写、去掉分隔符），与 Proxy 的校验保持一致
+fn canonical_device_id(device_id: &str) -> String {
+    device_id.replace([':', '-'], "").to_lowercase()
+}
+
+/// 签发设备连接令牌
+///
+/// 格式为 `<过期时间>.<签名>`，签名为 base64url(HMAC-SHA256(密钥, "<设备ID>.<过期时间>"))，
+/// 过期时间为 Unix 秒。Proxy 使用同一密钥校验。
+pub fn sign_device_token(secret: &str, device_id: &str, expires_at: i64) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let message = format!("{}.{}", canonical_device_id(device_id), expires_at);
+    let tag = hmac::sign(&key, message.as_bytes());
+    format!("{}.{}", expires_at, URL_SAFE_NO_PAD.encode(tag.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_known_vector() {
+        // Proxy 的令牌校验测试使用同一令牌，保证两端格式一致
+        assert_eq!(
+            sign_device_token("shared-secret", "98:A3:16:F0:B1:E5", 1_700_000_000),
+            "1700000000.Ewgy7SIwZ65zkSRrtgL2xPszEFBbQ57c4H5d4xI_xaQ"
+        );
+    }
+
+    #[test]
+    fn device_id_format_does_not_change_signature() {
+        let expected = sign_device_token("shared-secret", "98a316f0b1e5", 1_700_000_000);
+        for device_id in ["98:A3:16:F0:B1:E5", "98-a3-16-f0-b1-e5"] {
+            assert_eq!(
+                sign_device_token("shared-secret", device_id, 1_700_000_000),
+                expected
+            );
+        }
+        assert_ne!(
+            sign_device_token("other-secret", "98a316f0b1e5", 1_700_000_000),
+            expected
+        );
+    }
+}
@@ -0,0 +1,168 @@
+mod device_token;
+
+pub use device_token::sign_device_token;
+
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::models::EchoKitConfig;
+
+/// 密文前缀，用于区分已加密的值与历史明文
+const CIPHERTEXT_PREFIX: &str = "enc:v1:";
+
+/// 密钥字段加密器（AES-256-GCM）
+///
+/// 密文格式为 `enc:v1:` + base64(nonce || ciphertext || tag)。
+pub struct SecretCipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl SecretCipher {
+    /// 从 base64 编码的 32 字节密钥创建加密器
+    pub fn from_base64_key(encoded: &str) -> Result<Self> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .context("Secrets encryption key is not valid base64")?;
+        let key = UnboundKey::new(&AES_256_GCM, &bytes)
+            .map_err(|_| anyhow!("Secrets encryption key must be 32 bytes"))?;
+
+        Ok(Self {
+            key: LessSafeKey::new(key),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// 加密单个值（已加密的值保持不变）
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        if plaintext.starts_with(CIPHERTEXT_PREFIX) {
+            return Ok(plaintext.to_string());
+        }
+
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("Failed to generate nonce"))?;
+
+        let mut in_out = plaintext.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut in_out,
+            )
+            .map_err(|_| anyhow!("Failed to encrypt secret"))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&in_out);
+        Ok(format!("{CIPHERTEXT_PREFIX}{}", STANDARD.encode(payload)))
+    }
+
+    /// 解密单个值（未加密的历史明文原样返回）
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let Some(encoded) = value.strip_prefix(CIPHERTEXT_PREFIX) else {
+            return Ok(value.to_string());
+        };
+
+        let mut payload = STANDARD
+            .decode(encoded)
+            .context("Encrypted secret is not valid base64")?;
+        if payload.len() < NONCE_LEN {
+            return Err(anyhow!("Encrypted secret is too short"));
+        }
+
+        let (nonce, ciphertext) = payload.split_at_mut(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| anyhow!("Encrypted secret has an invalid nonce"))?;
+        let plaintext = self
+            .key
+            .open_in_place(nonce, Aad::empty(), ciphertext)
+            .map_err(|_| anyhow!("Failed to decrypt secret, is the encryption key correct?"))?;
+
+        String::from_utf8(plaintext.to_vec()).context("Decrypted secret is not valid UTF-8")
+    }
+
+    /// 加密配置中的所有密钥字段
+    pub fn encrypt_config(&self, config: &mut EchoKitConfig) -> Result<()> {
+        for secret in config.secrets_mut() {
+            *secret = self.encrypt(secret)?;
+        }
+        Ok(())
+    }
+
+    /// 解密配置中的所有密钥字段
+    pub fn decrypt_config(&self, config: &mut EchoKitConfig) -> Result<()> {
+        for secret in config.secrets_mut() {
+            *secret = self.decrypt(secret)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher() -> SecretCipher {
+        SecretCipher::from_base64_key(&STANDARD.encode([7u8; 32])).unwrap()
+    }
+
+    #[test]
+    fn encrypt_round_trips_and_hides_plaintext() {
+        let cipher = cipher();
+        let encrypted = cipher.encrypt("sk-secret").unwrap();
+        assert!(encrypted.starts_with(CIPHERTEXT_PREFIX));
+        assert!(!encrypted.contains("sk-secret"));
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "sk-secret");
+
+        // 随机 nonce：同一明文每次加密结果不同；已加密的值不重复加密
+        assert_ne!(cipher.encrypt("sk-secret").unwrap(), encrypted);
+        assert_eq!(cipher.encrypt(&encrypted).unwrap(), encrypted);
+        // 历史明文原样返回
+        assert_eq!(cipher.decrypt("sk-plain").unwrap(), "sk-plain");
+    }
+
+    #[test]
+    fn decrypt_rejects_wrong_key_and_tampering() {
+        let encrypted = cipher().encrypt("sk-secret").unwrap();
+
+        let other = SecretCipher::from_base64_key(&STANDARD.encode([8u8; 32])).unwrap();
+        assert!(other.decrypt(&encrypted).is_err());
+
+        let mut payload = STANDARD
+            .decode(encrypted.strip_prefix(CIPHERTEXT_PREFIX).unwrap())
+            .unwrap();
+        let last = payload.len() - 1;
+        payload[last] ^= 1;
+        let tampered = format!("{CIPHERTEXT_PREFIX}{}", STANDARD.encode(payload));
+        assert!(cipher().decrypt(&tampered).is_err());
+        assert!(cipher().decrypt("enc:v1:AAAA").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        assert!(SecretCipher::from_base64_key("not base64!").is_err());
+        assert!(SecretCipher::from_base64_key(&STANDARD.encode([7u8; 16])).is_err());
+    }
+
+    #[test]
+    fn config_secrets_round_trip() {
+        let cipher = cipher();
+        let mut config = EchoKitConfig::sample();
+        cipher.encrypt_config(&mut config).unwrap();
+        assert!(config
+            .secrets_mut()
+            .iter()
+            .all(|secret| secret.starts_with(CIPHERTEXT_PREFIX)));
+
+        cipher.decrypt_config(&mut config).unwrap();
+        assert_eq!(config.llm.api_key, "sk-llm");
+        assert_eq!(
+            serde_json::to_value(&config).unwrap(),
+            serde_json::to_value(EchoKitConfig::sample()).unwrap()
+        );
+    }
+}
@@ -1,2 +1,4 @@
 mod pg_device_store;
+mod pg_group_store;
 pub use pg_device_store::PgDeviceStore;
+pub use pg_group_store::PgGroupStore;
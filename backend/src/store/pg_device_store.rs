@@ -1,7 +1,34 @@
-use crate::models::{Device, DeviceStatus};
+use crate::models::{
+    ConnectionEvent, ConnectionLogFilter, ConnectionLogPage, Device, DeviceConnection, DeviceStatus,
+};
 use anyhow::{Context, Result};
 use sqlx::{PgPool, Row};
 
+/// 容器路由信息（与 Proxy 的端点解析逻辑保持一致）
+pub struct ContainerRoute {
+    pub container_id: String,
+    pub host: String,
+    pub port: u16,
+    pub use_tls: bool,
+    pub status: String,
+    pub draining: bool,
+}
+
+impl ContainerRoute {
+    /// 构建设备连接的服务器 WebSocket URL（标准端口不显示端口号）
+    pub fn ws_url(&self, device_id: &str) -> String {
+        let protocol = if self.use_tls { "wss" } else { "ws" };
+        if self.port == 443 || self.port == 80 {
+            format!("{}://{}/ws/{}", protocol, self.host, device_id)
+        } else {
+            format!(
+                "{}://{}:{}/ws/{}",
+                protocol, self.host, self.port, device_id
+            )
+        }
+    }
+}
+
 pub struct PgDeviceStore {
     pool: PgPool,
 }
@@ -22,6 +49,8 @@ impl PgDeviceStore {
                 bound_container_id,
                 created_at,
                 last_connected_at,
+                last_ip,
+                last_user_agent,
                 status
             FROM devices
             ORDER BY created_at DESC
@@ -34,12 +63,7 @@ impl PgDeviceStore {
         let devices = rows
             .into_iter()
             .map(|row| {
-                let status_str: String = row.get("status");
-                let status = match status_str.as_str() {
-                    "online" => DeviceStatus::Online,
-                    "offline" => DeviceStatus::Offline,
-                    _ => DeviceStatus::Unknown,
-                };
+                let status = DeviceStatus::from_db_str(row.get("status"));
 
                 Device {
                     device_id: row.get("device_id"),
@@ -48,6 +72,73 @@ impl PgDeviceStore {
                     bound_container_id: row.get("bound_container_id"),
                     created_at: row.get("created_at"),
                     last_connected_at: row.get("last_connected_at"),
+                    last_ip: row.get("last_ip"),
+                    last_user_agent: row.get("last_user_agent"),
+                    status,
+                }
+            })
+            .collect();
+
+        Ok(devices)
+    }
+
+    /// 按状态统计设备数量
+    pub async fn count_by_status(&self) -> Result<Vec<(String, i64)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT status, COUNT(*) AS count
+            FROM devices
+            GROUP BY status
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to count devices by status")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get("status"), row.get("count")))
+            .collect())
+    }
+
+    /// 获取绑定到指定容器的设备
+    pub async fn list_by_container(&self, container_id: &str) -> Result<Vec<Device>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                device_id,
+                name,
+                mac_address,
+                bound_container_id,
+                created_at,
+                last_connected_at,
+                last_ip,
+                last_user_agent,
+                status
+            FROM devices
+            WHERE bound_container_id = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(container_id)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch devices for container")?;
+
+        let devices = rows
+            .into_iter()
+            .map(|row| {
+                let status = DeviceStatus::from_db_str(row.get("status"));
+
+                Device {
+                    device_id: row.get("device_id"),
+                    name: row.get("name"),
+                    mac_address: row.get("mac_address"),
+                    bound_container_id: row.get("bound_container_id"),
+                    created_at: row.get("created_at"),
+                    last_connected_at: row.get("last_connected_at"),
+                    last_ip: row.get("last_ip"),
+                    last_user_agent: row.get("last_user_agent"),
                     status,
                 }
             })
@@ -67,6 +158,8 @@ impl PgDeviceStore {
                 bound_container_id,
                 created_at,
                 last_connected_at,
+                last_ip,
+                last_user_agent,
                 status
             FROM devices
             WHERE device_id = $1
@@ -78,12 +171,7 @@ impl PgDeviceStore {
         .context("Failed to fetch device")?;
 
         Ok(row.map(|row| {
-            let status_str: String = row.get("status");
-            let status = match status_str.as_str() {
-                "online" => DeviceStatus::Online,
-                "offline" => DeviceStatus::Offline,
-                _ => DeviceStatus::Unknown,
-            };
+            let status = DeviceStatus::from_db_str(row.get("status"));
 
             Device {
                 device_id: row.get("device_id"),
@@ -92,11 +180,30 @@ impl PgDeviceStore {
                 bound_container_id: row.get("bound_container_id"),
                 created_at: row.get("created_at"),
                 last_connected_at: row.get("last_connected_at"),
+                last_ip: row.get("last_ip"),
+                last_user_agent: row.get("last_user_agent"),
                 status,
             }
         }))
     }
 
+    /// 检查设备名称是否已被其他设备使用
+    pub async fn name_exists(&self, name: &str) -> Result<bool> {
+        let row = sqlx::query(
+            r#"
+            SELECT 1 AS found
+            FROM devices
+            WHERE name = $1
+            "#,
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to check device name")?;
+
+        Ok(row.is_some())
+    }
+
     /// 注册新设备
     pub async fn register(&self, device: Device) -> Result<Device> {
         let now = chrono::Utc::now().timestamp();
@@ -125,7 +232,54 @@ impl PgDeviceStore {
         Ok(device)
     }
 
+    /// 在单个事务中批量导入设备
+    ///
+    /// 已存在的设备（device_id 或 MAC 冲突）会被跳过而不是中断整个导入，
+    /// 返回值与输入一一对应，表示该设备是否被实际插入。
+    pub async fn import(&self, devices: &[Device]) -> Result<Vec<bool>> {
+        let now = chrono::Utc::now().timestamp();
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to begin import transaction")?;
+        let mut inserted = Vec::with_capacity(devices.len());
+
+        for device in devices {
+            let result = sqlx::query(
+                r#"
+                INSERT INTO devices (
+                    device_id, name, mac_address, bound_container_id,
+                    created_at, last_connected_at, updated_at, status
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(&device.device_id)
+            .bind(&device.name)
+            .bind(&device.mac_address)
+            .bind(&device.bound_container_id)
+            .bind(device.created_at)
+            .bind(device.last_connected_at)
+            .bind(now)
+            .bind(device.status.to_string())
+            .execute(&mut *tx)
+            .await
+            .context("Failed to import device")?;
+
+            inserted.push(result.rows_affected() > 0);
+        }
+
+        tx.commit()
+            .await
+            .context("Failed to commit import transaction")?;
+
+        Ok(inserted)
+    }
+
     /// 更新设备
+    #[allow(dead_code)]
     pub async fn update(&self, device_id: &str, updates: Device) -> Result<Device> {
         let now = chrono::Utc::now().timestamp();
 
@@ -215,6 +369,131 @@ impl PgDeviceStore {
         Ok(())
     }
 
+    /// 获取设备最近的连接日志
+    pub async fn list_connections(
+        &self,
+        device_id: &str,
+        limit: i64,
+    ) -> Result<Vec<DeviceConnection>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, device_id, container_id, client_ip, connected_at, disconnected_at,
+                   frames_to_server, frames_to_device, forward_errors, close_reason
+            FROM device_connections
+            WHERE device_id = $1
+            ORDER BY connected_at DESC, id DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(device_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch device connections")?;
+
+        Ok(rows.iter().map(connection_from_row).collect())
+    }
+
+    /// 按时间范围和事件类型分页查询设备连接日志
+    pub async fn query_connection_log(
+        &self,
+        device_id: &str,
+        filter: &ConnectionLogFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<ConnectionLogPage> {
+        // 时间范围作用于事件对应的时间戳；列名来自固定枚举，不含用户输入
+        let (ts_column, event_clause) = match filter.event {
+            Some(ConnectionEvent::Close) => ("disconnected_at", "AND disconnected_at IS NOT NULL"),
+            Some(ConnectionEvent::Open) | None => ("connected_at", ""),
+        };
+        let conditions = format!(
+            "device_id = $1 {event_clause} \
+             AND ($2::BIGINT IS NULL OR {ts_column} >= $2) \
+             AND ($3::BIGINT IS NULL OR {ts_column} <= $3)"
+        );
+
+        let total: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM device_connections WHERE {conditions}"
+        ))
+        .bind(device_id)
+        .bind(filter.from)
+        .bind(filter.to)
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to count device connections")?;
+
+        let rows = sqlx::query(&format!(
+            "SELECT id, device_id, container_id, client_ip, connected_at, disconnected_at, \
+             frames_to_server, frames_to_device, forward_errors, close_reason \
+             FROM device_connections \
+             WHERE {conditions} \
+             ORDER BY {ts_column} DESC, id DESC \
+             LIMIT $4 OFFSET $5"
+        ))
+        .bind(device_id)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch device connection log")?;
+
+        Ok(ConnectionLogPage {
+            items: rows.iter().map(connection_from_row).collect(),
+            total,
+        })
+    }
+
+    /// 获取容器路由信息
+    pub async fn get_container_route(&self, container_id: &str) -> Result<Option<ContainerRoute>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, host, port, use_tls, status, draining
+            FROM containers
+            WHERE id = $1
+            "#,
+        )
+        .bind(container_id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch container route")?;
+
+        Ok(row.map(|row| {
+            let port: Option<i32> = row.get("port");
+            let use_tls: bool = row.get("use_tls");
+            ContainerRoute {
+                container_id: row.get("id"),
+                host: row.get("host"),
+                port: port
+                    .map(|p| p as u16)
+                    .unwrap_or(if use_tls { 443 } else { 80 }),
+                use_tls,
+                status: row.get("status"),
+                draining: row.get("draining"),
+            }
+        }))
+    }
+
+    /// 获取标记为默认的服务器 ID（未绑定设备的回退目标）
+    pub async fn get_default_container_id(&self) -> Result<Option<String>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id
+            FROM containers
+            WHERE is_default = true
+            ORDER BY created_at ASC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch default container")?;
+
+        Ok(row.map(|row| row.get("id")))
+    }
+
     /// 获取容器的 WebSocket URL
     pub async fn get_container_ws_url(&self, container_id: &str) -> Result<Option<String>> {
         let row = sqlx::query(
@@ -248,3 +527,142 @@ impl PgDeviceStore {
         }))
     }
 }
+
+fn connection_from_row(row: &sqlx::postgres::PgRow) -> DeviceConnection {
+    DeviceConnection {
+        id: row.get("id"),
+        device_id: row.get("device_id"),
+        container_id: row.get("container_id"),
+        client_ip: row.get("client_ip"),
+        connected_at: row.get("connected_at"),
+        disconnected_at: row.get("disconnected_at"),
+        frames_to_server: row.get("frames_to_server"),
+        frames_to_device: row.get("frames_to_device"),
+        forward_errors: row.get("forward_errors"),
+        close_reason: row.get("close_reason"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(port: u16, use_tls: bool) -> ContainerRoute {
+        ContainerRoute {
+            container_id: "abc123".to_string(),
+            host: "dallas.echokit.dev".to_string(),
+            port,
+            use_tls,
+            status: "running".to_string(),
+            draining: false,
+        }
+    }
+
+    #[test]
+    fn ws_url_hides_standard_ports() {
+        assert_eq!(
+            route(443, true).ws_url("98:A3:16:F0:B1:E5"),
+            "wss://dallas.echokit.dev/ws/98:A3:16:F0:B1:E5"
+        );
+        assert_eq!(
+            route(80, false).ws_url("98:A3:16:F0:B1:E5"),
+            "ws://dallas.echokit.dev/ws/98:A3:16:F0:B1:E5"
+        );
+    }
+
+    #[test]
+    fn ws_url_includes_custom_ports() {
+        assert_eq!(
+            route(8080, false).ws_url("98:A3:16:F0:B1:E5"),
+            "ws://dallas.echokit.dev:8080/ws/98:A3:16:F0:B1:E5"
+        );
+    }
+
+    #[tokio::test]
+    async fn connection_log_filters_by_event_and_time_range() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL 未设置，跳过数据库测试");
+            return;
+        };
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&url)
+            .await
+            .unwrap();
+        let store = PgDeviceStore::new(pool.clone());
+        let device_id = format!(
+            "test-log-{}",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap()
+        );
+
+        // (connected_at, disconnected_at)：第二条连接仍未断开
+        let connections: [(i64, Option<i64>); 4] = [
+            (100, Some(150)),
+            (200, None),
+            (300, Some(400)),
+            (500, Some(550)),
+        ];
+        for (connected_at, disconnected_at) in connections {
+            sqlx::query(
+                r#"
+                INSERT INTO device_connections (device_id, container_id, connected_at, disconnected_at)
+                VALUES ($1, 'abc123', $2, $3)
+                "#,
+            )
+            .bind(&device_id)
+            .bind(connected_at)
+            .bind(disconnected_at)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let query = |filter: ConnectionLogFilter, limit: i64, offset: i64| {
+            let store = &store;
+            let device_id = &device_id;
+            async move {
+                let page = store
+                    .query_connection_log(device_id, &filter, limit, offset)
+                    .await
+                    .unwrap();
+                let connected: Vec<i64> = page.items.iter().map(|c| c.connected_at).collect();
+                (page.total, connected)
+            }
+        };
+
+        let all = query(ConnectionLogFilter::default(), 100, 0).await;
+        let second_page = query(ConnectionLogFilter::default(), 2, 1).await;
+        // close 事件按断开时间过滤，且排除未断开的连接
+        let closed = query(
+            ConnectionLogFilter {
+                from: Some(150),
+                to: Some(400),
+                event: Some(ConnectionEvent::Close),
+            },
+            100,
+            0,
+        )
+        .await;
+        let opened = query(
+            ConnectionLogFilter {
+                from: Some(200),
+                to: Some(300),
+                event: Some(ConnectionEvent::Open),
+            },
+            100,
+            0,
+        )
+        .await;
+
+        sqlx::query("DELETE FROM device_connections WHERE device_id = $1")
+            .bind(&device_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(all, (4, vec![500, 300, 200, 100]));
+        assert_eq!(second_page, (4, vec![300, 200]));
+        assert_eq!(closed, (2, vec![300, 100]));
+        assert_eq!(opened, (2, vec![300, 200]));
+    }
+}
@@ -0,0 +1,363 @@
+use crate::models::DeviceGroup;
+use anyhow::{Context, Result};
+use sqlx::{postgres::PgRow, PgPool, Postgres, Row, Transaction};
+
+/// 分组查询：成员列表与在线数量随分组一起聚合返回
+const GROUP_SELECT: &str = r#"
+    SELECT
+        g.id,
+        g.name,
+        g.created_at,
+        COALESCE(
+            array_agg(m.device_id ORDER BY m.device_id) FILTER (WHERE m.device_id IS NOT NULL),
+            '{}'
+        )::TEXT[] AS device_ids,
+        COUNT(m.device_id) AS member_count,
+        COUNT(d.device_id) FILTER (WHERE d.status = 'online') AS online_count
+    FROM device_groups g
+    LEFT JOIN device_group_members m ON m.group_id = g.id
+    LEFT JOIN devices d ON d.device_id = m.device_id
+"#;
+
+pub struct PgGroupStore {
+    pool: PgPool,
+}
+
+impl PgGroupStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    fn row_to_group(row: PgRow) -> DeviceGroup {
+        DeviceGroup {
+            id: row.get("id"),
+            name: row.get("name"),
+            device_ids: row.get("device_ids"),
+            member_count: row.get("member_count"),
+            online_count: row.get("online_count"),
+            created_at: row.get("created_at"),
+        }
+    }
+
+    /// 获取所有分组
+    pub async fn list(&self) -> Result<Vec<DeviceGroup>> {
+        let sql = format!("{GROUP_SELECT} GROUP BY g.id ORDER BY g.created_at DESC, g.id DESC");
+        let rows = sqlx::query(&sql)
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to fetch device groups")?;
+
+        Ok(rows.into_iter().map(Self::row_to_group).collect())
+    }
+
+    /// 获取单个分组
+    pub async fn get(&self, group_id: i64) -> Result<Option<DeviceGroup>> {
+        let sql = format!("{GROUP_SELECT} WHERE g.id = $1 GROUP BY g.id");
+        let row = sqlx::query(&sql)
+            .bind(group_id)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to fetch device group")?;
+
+        Ok(row.map(Self::row_to_group))
+    }
+
+    /// 检查分组名称是否已被其他分组使用
+    pub async fn name_exists(&self, name: &str, exclude_id: Option<i64>) -> Result<bool> {
+        let row = sqlx::query(
+            r#"
+            SELECT 1 AS found
+            FROM device_groups
+            WHERE name = $1 AND ($2::BIGINT IS NULL OR id <> $2)
+            "#,
+        )
+        .bind(name)
+        .bind(exclude_id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to check group name")?;
+
+        Ok(row.is_some())
+    }
+
+    /// 替换分组成员（未注册的设备会被忽略）
+    async fn replace_members(
+        tx: &mut Transaction<'_, Postgres>,
+        group_id: i64,
+        device_ids: &[String],
+    ) -> Result<()> {
+        sqlx::query("DELETE FROM device_group_members WHERE group_id = $1")
+            .bind(group_id)
+            .execute(&mut **tx)
+            .await
+            .context("Failed to clear group members")?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO device_group_members (group_id, device_id)
+            SELECT $1, device_id
+            FROM devices
+            WHERE device_id = ANY($2)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(group_id)
+        .bind(device_ids)
+        .execute(&mut **tx)
+        .await
+        .context("Failed to insert group members")?;
+
+        Ok(())
+    }
+
+    /// 创建分组
+    pub async fn create(&self, name: &str, device_ids: &[String]) -> Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to begin group transaction")?;
+
+        let group_id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO device_groups (name, created_at, updated_at)
+            VALUES ($1, $2, $2)
+            RETURNING id
+            "#,
+        )
+        .bind(name)
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to create device group")?;
+
+        Self::replace_members(&mut tx, group_id, device_ids).await?;
+
+        tx.commit()
+            .await
+            .context("Failed to commit group transaction")?;
+
+        Ok(group_id)
+    }
+
+    /// 更新分组名称和成员，分组不存在时返回 false
+    pub async fn update(&self, group_id: i64, name: &str, device_ids: &[String]) -> Result<bool> {
+        let now = chrono::Utc::now().timestamp();
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to begin group transaction")?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE device_groups
+            SET name = $2, updated_at = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(group_id)
+        .bind(name)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to update device group")?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        Self::replace_members(&mut tx, group_id, device_ids).await?;
+
+        tx.commit()
+            .await
+            .context("Failed to commit group transaction")?;
+
+        Ok(true)
+    }
+
+    /// 删除分组（不影响成员设备本身），分组不存在时返回 false
+    pub async fn delete(&self, group_id: i64) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM device_groups WHERE id = $1")
+            .bind(group_id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete device group")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 在单个事务中将分组所有成员绑定到服务器，返回绑定的设备数量
+    ///
+    /// 分组不存在时返回 None。
+    pub async fn bind_to_server(&self, group_id: i64, container_id: &str) -> Result<Option<u64>> {
+        self.update_binding(group_id, Some(container_id)).await
+    }
+
+    /// 在单个事务中解绑分组所有成员，返回解绑的设备数量
+    ///
+    /// 分组不存在时返回 None。
+    pub async fn unbind(&self, group_id: i64) -> Result<Option<u64>> {
+        self.update_binding(group_id, None).await
+    }
+
+    async fn update_binding(
+        &self,
+        group_id: i64,
+        container_id: Option<&str>,
+    ) -> Result<Option<u64>> {
+        let now = chrono::Utc::now().timestamp();
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to begin group bind transaction")?;
+
+        // 锁定分组行，避免绑定过程中成员被修改
+        let exists = sqlx::query("SELECT id FROM device_groups WHERE id = $1 FOR UPDATE")
+            .bind(group_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .context("Failed to fetch device group")?
+            .is_some();
+
+        if !exists {
+            return Ok(None);
+        }
+
+        let result = sqlx::query(
+            r#"
+            UPDATE devices
+            SET
+                bound_container_id = $2,
+                updated_at = $3
+            WHERE device_id IN (
+                SELECT device_id FROM device_group_members WHERE group_id = $1
+            )
+            "#,
+        )
+        .bind(group_id)
+        .bind(container_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to update group member binding")?;
+
+        tx.commit()
+            .await
+            .context("Failed to commit group bind transaction")?;
+
+        Ok(Some(result.rows_affected()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::postgres::PgPoolOptions;
+
+    /// 连接 DATABASE_URL 指向的开发数据库（需已执行 migrations），未设置时跳过
+    async fn test_pool() -> Option<PgPool> {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL 未设置，跳过数据库测试");
+            return None;
+        };
+        Some(
+            PgPoolOptions::new()
+                .max_connections(2)
+                .connect(&url)
+                .await
+                .unwrap(),
+        )
+    }
+
+    async fn insert_device(pool: &PgPool, device_id: &str, status: &str) {
+        sqlx::query(
+            r#"
+            INSERT INTO devices (device_id, name, mac_address, created_at, status)
+            VALUES ($1, $1, $1, 0, $2)
+            "#,
+        )
+        .bind(device_id)
+        .bind(status)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn bound_container(pool: &PgPool, device_id: &str) -> Option<String> {
+        sqlx::query_scalar("SELECT bound_container_id FROM devices WHERE device_id = $1")
+            .bind(device_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn group_crud_and_bind() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let store = PgGroupStore::new(pool.clone());
+
+        let suffix = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+        let online = format!("test-group-online-{suffix}");
+        let offline = format!("test-group-offline-{suffix}");
+        insert_device(&pool, &online, "online").await;
+        insert_device(&pool, &offline, "offline").await;
+
+        // 创建：未注册的设备被忽略，在线数量随分组返回
+        let name = format!("test-group-{suffix}");
+        let members = vec![offline.clone(), online.clone(), "unregistered".to_string()];
+        let group_id = store.create(&name, &members).await.unwrap();
+        let group = store.get(group_id).await.unwrap().unwrap();
+        assert_eq!(group.name, name);
+        assert_eq!(group.member_count, 2);
+        assert_eq!(group.online_count, 1);
+        assert_eq!(group.device_ids, vec![offline.clone(), online.clone()]);
+        assert!(store.list().await.unwrap().iter().any(|g| g.id == group_id));
+
+        assert!(store.name_exists(&name, None).await.unwrap());
+        assert!(!store.name_exists(&name, Some(group_id)).await.unwrap());
+
+        // 更新：整体替换成员
+        let renamed = format!("{name}-renamed");
+        assert!(store
+            .update(group_id, &renamed, std::slice::from_ref(&online))
+            .await
+            .unwrap());
+        let group = store.get(group_id).await.unwrap().unwrap();
+        assert_eq!(group.name, renamed);
+        assert_eq!(group.device_ids, vec![online.clone()]);
+
+        // 绑定/解绑只影响当前成员
+        assert_eq!(
+            store.bind_to_server(group_id, "container-a").await.unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            bound_container(&pool, &online).await.as_deref(),
+            Some("container-a")
+        );
+        assert_eq!(bound_container(&pool, &offline).await, None);
+        assert_eq!(store.unbind(group_id).await.unwrap(), Some(1));
+        assert_eq!(bound_container(&pool, &online).await, None);
+
+        // 删除分组不删除成员设备
+        assert!(store.delete(group_id).await.unwrap());
+        assert!(!store.delete(group_id).await.unwrap());
+        assert!(store.get(group_id).await.unwrap().is_none());
+        assert_eq!(
+            store.bind_to_server(group_id, "container-a").await.unwrap(),
+            None
+        );
+        assert!(!store.update(group_id, &renamed, &[]).await.unwrap());
+
+        sqlx::query("DELETE FROM devices WHERE device_id = ANY($1)")
+            .bind(vec![online, offline])
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
@@ -136,9 +136,9 @@ export function Device() {
   };
 
   // 格式化时间戳
-  const formatTimestamp = (timestamp?: number) => {
+  const formatTimestamp = (timestamp?: string) => {
     if (!timestamp) return '-';
-    return new Date(timestamp * 1000).toLocaleString('zh-CN');
+    return new Date(timestamp).toLocaleString('zh-CN');
   };
 
   // 渲染设备状态
@@ -8,15 +8,15 @@ const MOCK_DEVICES: Device[] = [
     name: '客厅音箱',
     macAddress: 'AA:BB:CC:DD:EE:FF',
     boundContainerId: 'container_001',
-    createdAt: Date.now() / 1000 - 86400 * 7,
-    lastConnectedAt: Date.now() / 1000 - 3600,
+    createdAt: new Date(Date.now() - 86400 * 7 * 1000).toISOString(),
+    lastConnectedAt: new Date(Date.now() - 3600 * 1000).toISOString(),
     status: 'online',
   },
   {
     deviceId: '11:22:33:44:55:66',
     name: '卧室音箱',
     macAddress: '11:22:33:44:55:66',
-    createdAt: Date.now() / 1000 - 86400 * 3,
+    createdAt: new Date(Date.now() - 86400 * 3 * 1000).toISOString(),
     status: 'offline',
   },
 ];
@@ -56,7 +56,7 @@ export const deviceService = {
       await new Promise(resolve => setTimeout(resolve, 800));
       const newDevice: Device = {
         ...request,
-        createdAt: Date.now() / 1000,
+        createdAt: new Date().toISOString(),
         status: 'unknown',
       };
       MOCK_DEVICES.push(newDevice);
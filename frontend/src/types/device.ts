@@ -7,8 +7,10 @@ export interface Device {
   name: string;               // 设备名称（用户友好）
   macAddress: string;        // WiFi MAC 地址
   boundContainerId?: string; // 绑定的 EchoKit Server 容器 ID
-  createdAt: number;         // 创建时间（Unix 时间戳）
-  lastConnectedAt?: number; // 最后连接时间
+  createdAt: string;         // 创建时间（RFC3339）
+  lastConnectedAt?: string; // 最后连接时间（RFC3339）
+  lastIp?: string;           // 最近一次连接的来源 IP
+  lastUserAgent?: string;    // 最近一次连接的 User-Agent / 固件标识
   status: DeviceStatus;       // 连接状态
 }
 
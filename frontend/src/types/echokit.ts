@@ -10,6 +10,14 @@ export interface OpenaiASRConfig {
   lang: string;
   prompt?: string;
   url?: string;
+  vad?: VadConfig;
+}
+
+// ASR 使用的 VAD 服务（未指定时使用后端默认地址）
+export interface VadConfig {
+  provider: string;
+  url: string;
+  threshold?: number;
 }
 
 export interface ParaformerASRConfig {
@@ -118,6 +126,7 @@ export interface HealthCheckResult {
   containerRunning: boolean;
   errorMessage?: string;
   logsTail?: string;
+  errorLines?: string[];
 }
 
 export interface DeployResponse {
@@ -139,4 +148,5 @@ export interface ContainerInfo {
   status: ContainerStatus;
   createdAt: string;
   health?: HealthCheckResult;
+  description?: string;
 }
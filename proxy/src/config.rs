@@ -14,12 +14,52 @@ pub struct ProxyConfig {
     /// 日志级别
     pub log_level: String,
 
+    /// 是否输出连接访问日志（target 为 echokit_proxy::access，每个连接结束时一行）
+    pub access_log: bool,
+
     /// WebSocket 超时时间（秒）
+    #[allow(dead_code)]
     pub ws_timeout: u64,
 
     /// 数据库连接池大小
     pub db_pool_size: u32,
 
+    /// 启动时连接数据库的最大尝试次数（数据库尚未就绪时按退避间隔重试）
+    pub db_connect_max_attempts: u32,
+
+    /// 数据库连接重试的初始间隔（秒），每次失败后翻倍，最长 30 秒
+    pub db_connect_retry_interval_secs: u64,
+
+    /// 连接 EchoKit Server 的超时时间（毫秒）
+    pub server_connect_timeout_ms: u64,
+
+    /// 每个设备连接 设备->服务器 方向的带宽上限（字节/秒，未设置则不限速）
+    pub device_bandwidth_limit: Option<u64>,
+
+    /// Proxy 前面可信反向代理的层数（0 表示不信任 X-Forwarded-For）
+    pub trusted_proxy_depth: usize,
+
+    /// 未绑定设备是否回退到默认服务器
+    pub unbound_fallback_enabled: bool,
+
+    /// 未绑定设备回退使用的服务器 ID（未设置时使用 is_default 标记的服务器）
+    pub default_container_id: Option<String>,
+
+    /// 同一客户端 IP 两次连接测试的最小间隔（秒）
+    pub ws_test_interval_secs: u64,
+
+    /// 设备离线状态写入失败时的最大重试次数（指数退避，0 表示不重试）
+    pub status_update_max_retries: u32,
+
+    /// Backend 健康检查地址（可选，用于比对 Backend 与 Proxy 的版本）
+    pub backend_health_url: Option<String>,
+
+    /// 设备连接令牌的签名密钥（与 Backend 共享，可选）
+    pub device_token_secret: Option<String>,
+
+    /// 是否要求设备连接携带有效的签名令牌（需同时配置 DEVICE_TOKEN_SECRET）
+    pub require_device_token: bool,
+
     /// EchoKit Server 主机地址
     pub echokit_host: String,
 }
@@ -44,6 +84,11 @@ impl ProxyConfig {
             log_level: env::var("LOG_LEVEL")
                 .unwrap_or_else(|_| "info".to_string()),
 
+            access_log: env::var("ACCESS_LOG")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(true),
+
             ws_timeout: env::var("WS_TIMEOUT")
                 .ok()
                 .and_then(|s| s.parse().ok())
@@ -54,6 +99,64 @@ impl ProxyConfig {
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(10),
 
+            db_connect_max_attempts: env::var("DB_CONNECT_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|attempts| *attempts > 0)
+                .unwrap_or(10),
+
+            db_connect_retry_interval_secs: env::var("DB_CONNECT_RETRY_INTERVAL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(2),
+
+            server_connect_timeout_ms: env::var("SERVER_CONNECT_TIMEOUT_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(5000),
+
+            device_bandwidth_limit: env::var("DEVICE_BANDWIDTH_LIMIT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|limit| *limit > 0),
+
+            trusted_proxy_depth: env::var("TRUSTED_PROXY_DEPTH")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+
+            unbound_fallback_enabled: env::var("UNBOUND_FALLBACK_ENABLED")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(true),
+
+            default_container_id: env::var("DEFAULT_CONTAINER_ID")
+                .ok()
+                .filter(|s| !s.is_empty()),
+
+            ws_test_interval_secs: env::var("WS_TEST_INTERVAL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(10),
+
+            status_update_max_retries: env::var("STATUS_UPDATE_MAX_RETRIES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(5),
+
+            backend_health_url: env::var("BACKEND_HEALTH_URL")
+                .ok()
+                .filter(|s| !s.is_empty()),
+
+            device_token_secret: env::var("DEVICE_TOKEN_SECRET")
+                .ok()
+                .filter(|s| !s.is_empty()),
+
+            require_device_token: env::var("REQUIRE_DEVICE_TOKEN")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+
             echokit_host: env::var("ECHOKIT_HOST")
                 .unwrap_or_else(|_| "localhost".to_string()),
         }
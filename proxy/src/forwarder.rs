@@ -1,25 +1,192 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
 use anyhow::{Context, Result};
+use axum::extract::ws::{close_code, CloseFrame, WebSocket};
 use futures_util::{SinkExt, StreamExt};
 use tokio_tungstenite::tungstenite::Message;
-use tokio_tungstenite::{connect_async, WebSocketStream};
+use tokio_tungstenite::connect_async;
 use tracing::{debug, error, info, warn};
 
-type WsStream = WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+/// 转发参数
+#[derive(Debug, Clone)]
+pub struct ForwardOptions {
+    /// 连接 EchoKit Server 的超时时间
+    pub connect_timeout: Duration,
+    /// 设备->服务器 方向的带宽上限（字节/秒）
+    pub bandwidth_limit: Option<u64>,
+}
+
+/// 单个方向的转发计数
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DirectionStats {
+    pub text_frames: u64,
+    pub binary_frames: u64,
+    /// 数据帧（文本+二进制）的负载字节数
+    pub bytes: u64,
+    /// 读取或发送失败的次数（出错后该方向的转发即结束）
+    pub errors: u64,
+}
+
+impl DirectionStats {
+    /// 转发的数据帧数（文本+二进制，不含控制帧）
+    pub fn frames(&self) -> u64 {
+        self.text_frames + self.binary_frames
+    }
+}
+
+/// 连接结束方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    /// 设备先发送关闭帧
+    Device,
+    /// 服务器先发送关闭帧
+    Server,
+    /// 读写出错或未经关闭握手断开
+    Error,
+}
+
+impl CloseReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CloseReason::Device => "device",
+            CloseReason::Server => "server",
+            CloseReason::Error => "error",
+        }
+    }
+
+    pub fn is_clean(&self) -> bool {
+        *self != CloseReason::Error
+    }
+}
+
+/// 一次转发会话的统计
+#[derive(Debug, Clone, Copy)]
+pub struct ForwardStats {
+    pub device_to_server: DirectionStats,
+    pub server_to_device: DirectionStats,
+    pub close_reason: CloseReason,
+}
+
+impl ForwardStats {
+    pub fn errors(&self) -> u64 {
+        self.device_to_server.errors + self.server_to_device.errors
+    }
+}
+
+/// 令牌桶限速器（字节/秒）
+///
+/// 令牌不足时延迟转发而不是丢弃数据，允许最多 1 秒的突发流量
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            rate: bytes_per_sec as f64,
+            tokens: bytes_per_sec as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// 消耗指定字节数的令牌，令牌不足时等待补充
+    async fn consume(&mut self, bytes: usize) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_refill = now;
+
+        // 允许令牌为负（单条消息大于桶容量时），按欠额等待
+        self.tokens -= bytes as f64;
+        if self.tokens < 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(-self.tokens / self.rate)).await;
+        }
+    }
+}
+
+/// 连接 EchoKit Server 并完成一次 ping/pong，返回包含建连在内的总耗时（用于连接测试）
+pub async fn probe_server(server_url: &str, timeout: Duration) -> Result<Duration> {
+    let started = Instant::now();
+
+    let (mut server_ws, _) = tokio::time::timeout(timeout, connect_async(server_url))
+        .await
+        .map_err(|_| anyhow::anyhow!("连接到 EchoKit Server 超时 ({}ms)", timeout.as_millis()))?
+        .context("连接到 EchoKit Server 失败")?;
+
+    server_ws
+        .send(Message::Ping(b"echokit-proxy-test".to_vec().into()))
+        .await
+        .context("发送 Ping 失败")?;
+
+    let pong = tokio::time::timeout(timeout, async {
+        while let Some(message) = server_ws.next().await {
+            match message.context("读取服务器消息失败")? {
+                Message::Pong(_) => return Ok(()),
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+        anyhow::bail!("EchoKit Server 在返回 Pong 前关闭了连接")
+    })
+    .await
+    .map_err(|_| anyhow::anyhow!("等待 Pong 超时 ({}ms)", timeout.as_millis()))?;
+    pong?;
+
+    let elapsed = started.elapsed();
+    let _ = server_ws.close(None).await;
+    Ok(elapsed)
+}
+
+/// 以指定的关闭码和原因关闭设备 WebSocket
+pub async fn close_device_socket(mut device_ws: WebSocket, code: u16, reason: &str) {
+    let frame = CloseFrame {
+        code,
+        reason: reason.to_string().into(),
+    };
+    if let Err(e) = device_ws
+        .send(axum::extract::ws::Message::Close(Some(frame)))
+        .await
+    {
+        debug!("发送关闭帧到设备失败: {}", e);
+    }
+}
 
 /// 双向转发 WebSocket 消息
 ///
-/// 从设备到服务器，以及从服务器到设备
+/// 从设备到服务器，以及从服务器到设备。连接建立后总是返回转发统计，
+/// 仅在无法连接 EchoKit Server 时返回错误
 pub async fn bidirectional_forward(
-    device_ws: axum::extract::ws::WebSocket,
+    device_ws: WebSocket,
     server_url: String,
     device_id: String,
-) -> Result<()> {
+    options: ForwardOptions,
+) -> Result<ForwardStats> {
+    let connect_timeout = options.connect_timeout;
     info!("开始双向转发: device_id={}, server_url={}", device_id, server_url);
 
-    // 1. 连接到 EchoKit Server
-    let (server_ws, _) = connect_async(&server_url)
-        .await
-        .context("连接到 EchoKit Server 失败")?;
+    // 1. 连接到 EchoKit Server（带超时，避免服务器不可达时设备连接一直挂起）
+    let server_ws = match tokio::time::timeout(connect_timeout, connect_async(&server_url)).await {
+        Ok(Ok((server_ws, _))) => server_ws,
+        Ok(Err(e)) => {
+            close_device_socket(device_ws, close_code::ERROR, "EchoKit Server unavailable").await;
+            return Err(e).context("连接到 EchoKit Server 失败");
+        }
+        Err(_) => {
+            close_device_socket(
+                device_ws,
+                close_code::AGAIN,
+                "EchoKit Server connect timeout",
+            )
+            .await;
+            anyhow::bail!(
+                "连接到 EchoKit Server 超时 ({}ms)",
+                connect_timeout.as_millis()
+            );
+        }
+    };
 
     info!("已连接到 EchoKit Server: {}", server_url);
 
@@ -31,8 +198,14 @@ pub async fn bidirectional_forward(
 
     // 4. 创建两个转发任务
 
-    // 设备 -> 服务器
+    // 最先发送关闭帧的一方（两个方向都会看到关闭帧：一方发起，另一方回应）
+    let first_close = OnceLock::new();
+    let first_close = &first_close;
+
+    // 设备 -> 服务器（可选限速）
+    let mut limiter = options.bandwidth_limit.map(TokenBucket::new);
     let device_to_server = async move {
+        let mut stats = DirectionStats::default();
         while let Some(msg) = device_rx.next().await {
             match msg {
                 Ok(axum_msg) => {
@@ -40,10 +213,14 @@ pub async fn bidirectional_forward(
                     let tungstenite_msg = match axum_msg {
                         axum::extract::ws::Message::Text(text) => {
                             debug!("设备->服务器 [Text]: {} bytes", text.len());
+                            stats.text_frames += 1;
+                            stats.bytes += text.len() as u64;
                             Message::Text(text.to_string().into())
                         }
                         axum::extract::ws::Message::Binary(data) => {
                             debug!("设备->服务器 [Binary]: {} bytes", data.len());
+                            stats.binary_frames += 1;
+                            stats.bytes += data.len() as u64;
                             Message::Binary(data)
                         }
                         axum::extract::ws::Message::Ping(data) => {
@@ -56,6 +233,7 @@ pub async fn bidirectional_forward(
                         }
                         axum::extract::ws::Message::Close(frame) => {
                             info!("设备关闭连接");
+                            let _ = first_close.set(CloseReason::Device);
                             if let Some(f) = frame {
                                 Message::Close(Some(tokio_tungstenite::tungstenite::protocol::CloseFrame {
                                     code: tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode::from(f.code),
@@ -67,25 +245,40 @@ pub async fn bidirectional_forward(
                         }
                     };
 
+                    if let Some(limiter) = limiter.as_mut() {
+                        limiter.consume(tungstenite_msg.len()).await;
+                    }
+
+                    // 服务器先发起关闭时，回应的关闭帧可能因握手已完成而无法发送，不视为错误
+                    let is_close_reply = tungstenite_msg.is_close()
+                        && first_close.get() == Some(&CloseReason::Server);
+
                     // 发送到服务器
                     if let Err(e) = server_tx.send(tungstenite_msg).await {
+                        if is_close_reply {
+                            debug!("服务器连接已关闭，忽略回应的关闭帧: {}", e);
+                            break;
+                        }
                         error!("发送消息到服务器失败: {}", e);
+                        stats.errors += 1;
                         break;
                     }
                 }
                 Err(e) => {
                     error!("从设备接收消息失败: {}", e);
+                    stats.errors += 1;
                     break;
                 }
             }
         }
 
         info!("设备->服务器转发结束");
-        Ok::<(), anyhow::Error>(())
+        stats
     };
 
     // 服务器 -> 设备
     let server_to_device = async move {
+        let mut stats = DirectionStats::default();
         while let Some(msg) = server_rx.next().await {
             match msg {
                 Ok(tungstenite_msg) => {
@@ -93,10 +286,14 @@ pub async fn bidirectional_forward(
                     let axum_msg = match tungstenite_msg {
                         Message::Text(text) => {
                             debug!("服务器->设备 [Text]: {} bytes", text.len());
+                            stats.text_frames += 1;
+                            stats.bytes += text.len() as u64;
                             axum::extract::ws::Message::Text(text.to_string().into())
                         }
                         Message::Binary(data) => {
                             debug!("服务器->设备 [Binary]: {} bytes", data.len());
+                            stats.binary_frames += 1;
+                            stats.bytes += data.len() as u64;
                             axum::extract::ws::Message::Binary(data)
                         }
                         Message::Ping(data) => {
@@ -109,6 +306,7 @@ pub async fn bidirectional_forward(
                         }
                         Message::Close(frame) => {
                             info!("服务器关闭连接");
+                            let _ = first_close.set(CloseReason::Server);
                             if let Some(f) = frame {
                                 axum::extract::ws::Message::Close(Some(axum::extract::ws::CloseFrame {
                                     code: f.code.into(),
@@ -124,34 +322,300 @@ pub async fn bidirectional_forward(
                         }
                     };
 
+                    // 设备先发起关闭时，回应的关闭帧可能因握手已完成而无法发送，不视为错误
+                    let is_close_reply = matches!(axum_msg, axum::extract::ws::Message::Close(_))
+                        && first_close.get() == Some(&CloseReason::Device);
+
                     // 发送到设备
                     if let Err(e) = device_tx.send(axum_msg).await {
+                        if is_close_reply {
+                            debug!("设备连接已关闭，忽略回应的关闭帧: {}", e);
+                            break;
+                        }
                         error!("发送消息到设备失败: {}", e);
+                        stats.errors += 1;
                         break;
                     }
                 }
                 Err(e) => {
                     error!("从服务器接收消息失败: {}", e);
+                    stats.errors += 1;
                     break;
                 }
             }
         }
 
         info!("服务器->设备转发结束");
-        Ok::<(), anyhow::Error>(())
+        stats
     };
 
     // 5. 并发运行两个转发任务
-    let result = tokio::try_join!(device_to_server, server_to_device);
+    let (device_to_server, server_to_device) = tokio::join!(device_to_server, server_to_device);
+
+    // 任一方向出错，或没有任何一方发送关闭帧（连接被直接断开），都视为异常结束
+    let errors = device_to_server.errors + server_to_device.errors;
+    let close_reason = match first_close.get() {
+        Some(reason) if errors == 0 => *reason,
+        _ => CloseReason::Error,
+    };
+    let stats = ForwardStats {
+        device_to_server,
+        server_to_device,
+        close_reason,
+    };
+
+    if close_reason.is_clean() {
+        info!(
+            "双向转发正常结束: device_id={}, closed_by={}",
+            device_id,
+            close_reason.as_str()
+        );
+    } else {
+        warn!(
+            "双向转发异常结束: device_id={}, errors={}",
+            device_id, errors
+        );
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::ws::WebSocketUpgrade;
+    use axum::routing::get;
+    use axum::Router;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+    /// 只接受 TCP 连接、从不完成 WebSocket 握手的“服务器”
+    async fn silent_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                held.push(stream);
+            }
+        });
+        format!("ws://{}/ws", addr)
+    }
+
+    /// 完成握手并自动回应 Ping 的 EchoKit Server 替身
+    async fn echo_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(message)) = ws.next().await {
+                        if message.is_text() || message.is_binary() {
+                            let _ = ws.send(message).await;
+                        }
+                    }
+                });
+            }
+        });
+        format!("ws://{}/ws", addr)
+    }
+
+    /// 回显第一帧后主动关闭连接的服务器
+    async fn closing_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    if let Some(Ok(message)) = ws.next().await {
+                        let _ = ws.send(message).await;
+                    }
+                    let _ = ws.close(None).await;
+                    while ws.next().await.is_some() {}
+                });
+            }
+        });
+        format!("ws://{}/ws", addr)
+    }
+
+    #[tokio::test]
+    async fn probe_reports_reachable_server() {
+        let server_url = echo_server().await;
+        let elapsed = probe_server(&server_url, Duration::from_secs(2))
+            .await
+            .unwrap();
+        assert!(elapsed < Duration::from_secs(2));
+    }
 
-    match result {
-        Ok(_) => {
-            info!("双向转发正常结束: device_id={}", device_id);
-            Ok(())
+    #[tokio::test]
+    async fn probe_fails_for_unreachable_server() {
+        // 绑定后立即释放，得到一个无人监听的端口
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let refused = probe_server(&format!("ws://{}/ws", addr), Duration::from_secs(2)).await;
+        assert!(refused.is_err());
+
+        let timeout = Duration::from_millis(200);
+        let started = Instant::now();
+        let silent = probe_server(&silent_server().await, timeout).await;
+        assert!(silent.unwrap_err().to_string().contains("超时"));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn token_bucket_delays_transfer_beyond_burst() {
+        let mut bucket = TokenBucket::new(100_000);
+
+        // 1 秒的突发额度内不等待
+        let started = Instant::now();
+        for _ in 0..10 {
+            bucket.consume(10_000).await;
+        }
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        // 超出额度的 50KB 需按 100KB/s 等待约 0.5 秒
+        let started = Instant::now();
+        for _ in 0..5 {
+            bucket.consume(10_000).await;
+        }
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(450), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+    }
+
+    /// 将设备连接转发到指定服务器的代理，返回代理地址及每次转发的结果
+    async fn forwarding_proxy(
+        server_url: String,
+        connect_timeout: Duration,
+    ) -> (
+        std::net::SocketAddr,
+        tokio::sync::mpsc::UnboundedReceiver<Result<ForwardStats>>,
+    ) {
+        let (result_tx, result_rx) = tokio::sync::mpsc::unbounded_channel();
+        let app = Router::new().route(
+            "/ws",
+            get(move |ws: WebSocketUpgrade| {
+                let server_url = server_url.clone();
+                let result_tx = result_tx.clone();
+                async move {
+                    ws.on_upgrade(move |socket| async move {
+                        let options = ForwardOptions {
+                            connect_timeout,
+                            bandwidth_limit: None,
+                        };
+                        let result = bidirectional_forward(
+                            socket,
+                            server_url,
+                            "aabbccddeeff".to_string(),
+                            options,
+                        )
+                        .await;
+                        let _ = result_tx.send(result);
+                    })
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (proxy_addr, result_rx)
+    }
+
+    #[tokio::test]
+    async fn forward_stats_count_frames_in_both_directions() {
+        let (proxy_addr, mut result_rx) =
+            forwarding_proxy(echo_server().await, Duration::from_secs(2)).await;
+        let (mut device, _) = connect_async(format!("ws://{}/ws", proxy_addr))
+            .await
+            .unwrap();
+
+        let audio = vec![0u8; 640];
+        for _ in 0..3 {
+            device
+                .send(Message::Binary(audio.clone().into()))
+                .await
+                .unwrap();
         }
-        Err(e) => {
-            warn!("双向转发异常结束: device_id={}, error={}", device_id, e);
-            Err(e)
+        device.send(Message::Text("hello".into())).await.unwrap();
+        device.send(Message::Text("bye".into())).await.unwrap();
+
+        // 回显服务器原样返回每一帧
+        for _ in 0..5 {
+            let echoed = device.next().await.unwrap().unwrap();
+            assert!(echoed.is_binary() || echoed.is_text());
+        }
+        device.close(None).await.unwrap();
+        while device.next().await.is_some() {}
+
+        let stats = tokio::time::timeout(Duration::from_secs(5), result_rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        for direction in [stats.device_to_server, stats.server_to_device] {
+            assert_eq!(direction.binary_frames, 3);
+            assert_eq!(direction.text_frames, 2);
+            assert_eq!(direction.bytes, 3 * 640 + 5 + 3);
+            assert_eq!(direction.errors, 0);
         }
+        assert_eq!(stats.close_reason, CloseReason::Device);
+        assert_eq!(stats.errors(), 0);
+    }
+
+    #[tokio::test]
+    async fn server_initiated_close_is_clean() {
+        let (proxy_addr, mut result_rx) =
+            forwarding_proxy(closing_server().await, Duration::from_secs(2)).await;
+        let (mut device, _) = connect_async(format!("ws://{}/ws", proxy_addr))
+            .await
+            .unwrap();
+
+        device.send(Message::Text("hello".into())).await.unwrap();
+        let echoed = device.next().await.unwrap().unwrap();
+        assert!(echoed.is_text());
+        // 收到服务器的关闭帧后完成关闭握手
+        let closed = device.next().await.unwrap().unwrap();
+        assert!(closed.is_close());
+        while device.next().await.is_some() {}
+
+        let stats = tokio::time::timeout(Duration::from_secs(5), result_rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(stats.device_to_server.text_frames, 1);
+        assert_eq!(stats.server_to_device.text_frames, 1);
+        assert_eq!(stats.close_reason, CloseReason::Server);
+        assert_eq!(stats.errors(), 0);
+    }
+
+    #[tokio::test]
+    async fn unresponsive_server_times_out_and_closes_device() {
+        let timeout = Duration::from_millis(200);
+        let (proxy_addr, mut result_rx) = forwarding_proxy(silent_server().await, timeout).await;
+
+        let started = Instant::now();
+        let (mut device, _) = connect_async(format!("ws://{}/ws", proxy_addr))
+            .await
+            .unwrap();
+        let frame = tokio::time::timeout(Duration::from_secs(5), device.next())
+            .await
+            .expect("设备连接未在超时后关闭");
+        match frame {
+            Some(Ok(Message::Close(Some(frame)))) => {
+                assert_eq!(frame.code, CloseCode::from(close_code::AGAIN));
+                assert_eq!(frame.reason.as_str(), "EchoKit Server connect timeout");
+            }
+            other => panic!("expected close frame, got {:?}", other),
+        }
+        assert!(started.elapsed() >= timeout);
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        let err = result_rx.recv().await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("超时"));
     }
 }
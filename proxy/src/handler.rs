@@ -1,107 +1,432 @@
 use crate::config::ProxyConfig;
-use crate::forwarder::bidirectional_forward;
+use crate::forwarder::{
+    bidirectional_forward, close_device_socket, probe_server, ForwardOptions, ForwardStats,
+};
+use crate::models::{
+    BuildInfo, ContainerInfo, ContainerMetricsResponse, HealthCheckResponse, RemoteBuildInfo,
+    VersionResponse,
+};
 use crate::store::DeviceStore;
+use crate::token::verify_device_token;
 use axum::{
     extract::{
-        ws::{WebSocket, WebSocketUpgrade},
-        Path, State,
+        ws::{rejection::WebSocketUpgradeRejection, close_code, WebSocket, WebSocketUpgrade},
+        ConnectInfo, Path, Query, State,
     },
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
 };
-use std::sync::Arc;
-use tracing::{error, info, warn};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+/// 记录的设备 User-Agent 最大字符数（与数据库列宽一致）
+const MAX_USER_AGENT_CHARS: usize = 256;
+
+/// 访问日志的 tracing target，可通过 RUST_LOG 单独过滤（如 `echokit_proxy::access=off`）
+pub const ACCESS_LOG_TARGET: &str = "echokit_proxy::access";
 
-#[derive(Clone)]
 pub struct AppState {
     pub device_store: DeviceStore,
     pub config: ProxyConfig,
+    /// 当前正在转发的设备连接数
+    pub active_connections: AtomicUsize,
+    /// 服务启动时间
+    pub started_at: Instant,
+    /// 各客户端 IP 最近一次连接测试的时间（用于限流）
+    pub ws_test_last_seen: Mutex<HashMap<String, Instant>>,
+    /// 各服务器（容器 ID）当前正在转发的设备连接数
+    pub container_connections: Mutex<HashMap<String, usize>>,
+    /// 最近一次获取到的 Backend 构建信息
+    pub backend_version: Mutex<Option<RemoteBuildInfo>>,
+    /// 查询 Backend 使用的 HTTP 客户端
+    pub http_client: reqwest::Client,
+}
+
+impl AppState {
+    /// 检查客户端是否可以发起连接测试，允许时记录本次测试时间，
+    /// 不允许时返回还需等待的时间
+    fn try_acquire_ws_test(&self, client_ip: &str) -> Result<(), Duration> {
+        let interval = Duration::from_secs(self.config.ws_test_interval_secs);
+        let now = Instant::now();
+        let mut last_seen = self
+            .ws_test_last_seen
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
+        // 清理过期记录，避免表无限增长
+        last_seen.retain(|_, at| now.duration_since(*at) < interval);
+        if let Some(at) = last_seen.get(client_ip) {
+            return Err(interval.saturating_sub(now.duration_since(*at)));
+        }
+        last_seen.insert(client_ip.to_string(), now);
+        Ok(())
+    }
+
+    /// 从 Backend 健康检查接口获取其构建信息并缓存，版本不一致时记录警告
+    ///
+    /// 未配置 Backend 地址或获取失败时返回 None（保留上一次获取到的结果）。
+    pub async fn refresh_backend_version(&self) -> Option<RemoteBuildInfo> {
+        let url = self.config.backend_health_url.as_deref()?;
+        let body = match self.http_client.get(url).send().await {
+            Ok(response) => response.bytes().await.ok()?,
+            Err(e) => {
+                warn!("[Proxy] 获取 Backend 版本失败: url={}, error={}", url, e);
+                return None;
+            }
+        };
+        let body: serde_json::Value = serde_json::from_slice(&body).ok()?;
+        let backend: RemoteBuildInfo =
+            match body.get("version").cloned().map(serde_json::from_value) {
+                Some(Ok(version)) => version,
+                _ => {
+                    warn!("[Proxy] Backend 健康检查响应中没有版本信息: url={}", url);
+                    return None;
+                }
+            };
+
+        let proxy = BuildInfo::current();
+        if !proxy.matches(&backend) {
+            warn!(
+                "[Proxy] Backend 与 Proxy 版本不一致: backend={} ({}), proxy={} ({})",
+                backend.version, backend.git_sha, proxy.version, proxy.git_sha
+            );
+        }
+
+        *self
+            .backend_version
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(backend.clone());
+        Some(backend)
+    }
+
+    /// 记录一条转发到指定服务器的连接
+    fn connection_opened(&self, container_id: &str) {
+        self.active_connections.fetch_add(1, Ordering::Relaxed);
+        let mut connections = self
+            .container_connections
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        *connections.entry(container_id.to_string()).or_insert(0) += 1;
+    }
+
+    /// 记录一条转发到指定服务器的连接已关闭，计数归零时移除该服务器
+    fn connection_closed(&self, container_id: &str) {
+        self.active_connections.fetch_sub(1, Ordering::Relaxed);
+        let mut connections = self
+            .container_connections
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = connections.get_mut(container_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                connections.remove(container_id);
+            }
+        }
+    }
 }
 
 /// 标准化 MAC 地址格式（用于数据库查询）
 ///
-/// 将设备发送的格式（小写无冒号，如 "98a316f0b1e5"）
-/// 转换为数据库存储格式（大写带冒号，如 "98:A3:16:F0:B1:E4"）
+/// 将设备发送的格式（小写无冒号如 "98a316f0b1e5"，或冒号/短横线分隔）
+/// 转换为数据库存储格式（大写带冒号，如 "98:A3:16:F0:B1:E4"），
+/// 非 MAC 格式原样返回，与后端的 `normalize_device_id` 保持一致
 fn normalize_mac_address(mac: &str) -> String {
-    // 如果已经是带冒号的格式，直接返回大写版本
-    if mac.contains(':') {
-        return mac.to_uppercase();
-    }
+    let hex: String = mac.chars().filter(|c| *c != ':' && *c != '-').collect();
 
-    // 如果是12位十六进制字符串（无冒号格式）
-    if mac.len() == 12 && mac.chars().all(|c| c.is_ascii_hexdigit()) {
+    // 如果是12位十六进制字符串（去掉分隔符后）
+    if hex.len() == 12 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
         let bytes: Vec<String> = (0..6)
-            .map(|i| mac[i*2..i*2+2].to_uppercase())
+            .map(|i| hex[i*2..i*2+2].to_uppercase())
             .collect();
         return bytes.join(":");
     }
 
-    // 其他格式直接返回大写
-    mac.to_uppercase()
+    mac.to_string()
 }
 
-/// 将 device_id 转换为日志友好格式（小写无冒号）
+/// 将 device_id 转换为日志友好格式（小写无分隔符）
 fn format_device_id_for_log(device_id: &str) -> String {
-    device_id.replace(":", "").to_lowercase()
+    device_id.replace([':', '-'], "").to_lowercase()
+}
+
+/// 解析设备来源 IP
+///
+/// 只有配置了可信代理层数时才读取 X-Forwarded-For：每层代理都会在末尾追加
+/// 它看到的来源地址，因此客户端 IP 位于倒数第 `trusted_depth` 项。
+/// 未配置或头部项数不足时使用 TCP 对端地址，避免被伪造的头部欺骗。
+fn resolve_client_ip(peer: SocketAddr, headers: &HeaderMap, trusted_depth: usize) -> String {
+    if trusted_depth > 0 {
+        let forwarded: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .collect();
+
+        if forwarded.len() >= trusted_depth {
+            return forwarded[forwarded.len() - trusted_depth].to_string();
+        }
+    }
+
+    peer.ip().to_string()
+}
+
+/// 设备上报的客户端标识：优先 User-Agent，其次固件版本头，超长时截断
+fn resolve_user_agent(headers: &HeaderMap) -> Option<String> {
+    [header::USER_AGENT.as_str(), "x-firmware-version"]
+        .iter()
+        .filter_map(|name| headers.get(*name)?.to_str().ok())
+        .map(str::trim)
+        .find(|value| !value.is_empty())
+        .map(|value| value.chars().take(MAX_USER_AGENT_CHARS).collect())
+}
+
+/// 设备连接的查询参数
+#[derive(Debug, Deserialize)]
+pub struct DeviceWsQuery {
+    /// Backend 签发的连接令牌（启用 REQUIRE_DEVICE_TOKEN 时必需）
+    pub token: Option<String>,
+}
+
+/// 非 WebSocket 升级请求的响应：保留 Axum 给出的状态码，返回说明握手要求的 JSON
+///
+/// 浏览器直接打开或固件握手头缺失时，便于开发者看出问题所在。
+fn upgrade_required(rejection: WebSocketUpgradeRejection) -> axum::response::Response {
+    (
+        rejection.status(),
+        axum::Json(serde_json::json!({
+            "error": "websocket_upgrade_required",
+            "message": format!(
+                "This endpoint only accepts WebSocket connections. Send a GET request with \
+                 'Connection: Upgrade', 'Upgrade: websocket', 'Sec-WebSocket-Version: 13' and \
+                 'Sec-WebSocket-Key' headers ({})",
+                rejection.body_text()
+            ),
+        })),
+    )
+        .into_response()
 }
 
 /// 处理设备 WebSocket 连接请求
 ///
 /// 路径: /ws/{device_id}
 pub async fn handle_device_websocket(
-    ws: WebSocketUpgrade,
+    ws: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
     Path(device_id): Path<String>,
+    Query(query): Query<DeviceWsQuery>,
     State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> axum::response::Response {
     let device_id_log = format_device_id_for_log(&device_id);
-    info!("[Proxy] 收到设备 WebSocket 连接请求: device_id={}", device_id_log);
+    let client_ip = resolve_client_ip(peer, &headers, state.config.trusted_proxy_depth);
+    let user_agent = resolve_user_agent(&headers);
+    let ws = match ws {
+        Ok(ws) => ws,
+        Err(rejection) => {
+            warn!(
+                "[Proxy] 设备请求不是有效的 WebSocket 升级: device_id={}, client_ip={}, user_agent={:?}, reason={}",
+                device_id_log, client_ip, user_agent, rejection.body_text()
+            );
+            return upgrade_required(rejection);
+        }
+    };
+    info!(
+        "[Proxy] 收到设备 WebSocket 连接请求: device_id={}, client_ip={}, user_agent={:?}",
+        device_id_log, client_ip, user_agent
+    );
+
+    // 要求签名令牌时，令牌缺失、伪造或过期的连接以关闭帧拒绝
+    if state.config.require_device_token {
+        if let Some(secret) = state.config.device_token_secret.as_deref() {
+            let now = chrono::Utc::now().timestamp();
+            if let Err(e) = verify_device_token(secret, &device_id, query.token.as_deref(), now) {
+                warn!(
+                    "[Proxy] 设备连接令牌无效: device_id={}, client_ip={}, error={}",
+                    device_id_log, client_ip, e
+                );
+                return ws.on_upgrade(move |socket| async move {
+                    close_device_socket(socket, close_code::POLICY, &e.to_string()).await;
+                });
+            }
+        }
+    }
 
     // 升级到 WebSocket 连接
-    ws.on_upgrade(move |socket| handle_device_connection(socket, device_id, state))
+    ws.on_upgrade(move |socket| {
+        handle_device_connection(socket, device_id, client_ip, user_agent, state)
+    })
 }
 
-/// 处理设备 WebSocket 连接
-async fn handle_device_connection(
-    device_ws: WebSocket,
-    device_id: String,
-    state: Arc<AppState>,
-) {
-    // 用于日志的 device_id 格式（小写无冒号）
-    let device_id_log = format_device_id_for_log(&device_id);
+/// 设备路由结果
+struct DeviceRoute {
+    /// 数据库存储格式的设备 ID
+    normalized_device_id: String,
+    container: ContainerInfo,
+    /// 完整的 EchoKit Server WebSocket URL
+    server_url: String,
+    /// 用于日志的服务器 URL（不含 device_id 路径）
+    server_url_log: String,
+}
+
+/// 设备无法路由的原因
+struct RouteRejection {
+    /// 需要发送给设备的关闭码（None 表示直接断开）
+    close_code: Option<u16>,
+    reason: String,
+}
+
+impl RouteRejection {
+    fn silent(reason: &str) -> Self {
+        Self {
+            close_code: None,
+            reason: reason.to_string(),
+        }
+    }
+
+    fn close(code: u16, reason: &str) -> Self {
+        Self {
+            close_code: Some(code),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// 容器是否因空闲被自动停止、需要请求后端唤醒
+fn should_request_wake(container: &ContainerInfo) -> bool {
+    container.status != "running" && container.auto_stopped
+}
+
+/// 检查容器当前是否接受新的设备连接
+///
+/// 只在建立连接时检查，已建立的转发不受之后的状态变化（如开始排空）影响
+fn admit_new_connection(
+    container: &ContainerInfo,
+    device_id_log: &str,
+) -> Result<(), RouteRejection> {
+    // 容器未运行时直接拒绝，避免向已停止的服务器发起连接
+    if container.status != "running" {
+        warn!(
+            "[Proxy] 容器未运行，拒绝连接: device_id={}, container_id={}, status={}",
+            device_id_log, container.container_id, container.status
+        );
+        return Err(RouteRejection::close(
+            close_code::AGAIN,
+            "Server not available",
+        ));
+    }
+
+    // 排空中的容器不再接受新连接，已有连接继续转发
+    if container.draining {
+        warn!(
+            "[Proxy] 容器正在排空，拒绝新连接: device_id={}, container_id={}",
+            device_id_log, container.container_id
+        );
+        return Err(RouteRejection::close(
+            close_code::AGAIN,
+            "Server is draining, try again later",
+        ));
+    }
+
+    Ok(())
+}
 
-    info!("[Proxy] 设备 WebSocket 连接已建立: device_id={}", device_id_log);
+/// 查询设备并解析其目标 EchoKit Server（设备连接与连接测试共用）
+async fn resolve_device_route(
+    state: &AppState,
+    device_id: &str,
+) -> Result<DeviceRoute, RouteRejection> {
+    let device_id_log = format_device_id_for_log(device_id);
 
     // 标准化 MAC 地址格式（用于数据库查询）
-    let normalized_device_id = normalize_mac_address(&device_id);
+    let normalized_device_id = normalize_mac_address(device_id);
 
     // 1. 查询设备信息
     let device = match state.device_store.get_device(&normalized_device_id).await {
         Ok(Some(device)) => device,
         Ok(None) => {
             error!("[Proxy] 设备不存在: device_id={}", device_id_log);
-            return;
+            return Err(RouteRejection::silent("Device not registered"));
         }
         Err(e) => {
             error!("[Proxy] 查询设备失败: device_id={}, error={}", device_id_log, e);
-            return;
+            return Err(RouteRejection::silent("Device lookup failed"));
         }
     };
 
-    // 2. 检查设备是否绑定容器
-    if device.bound_container_id.is_none() {
-        warn!("[Proxy] 设备未绑定容器: device_id={}, device_name={}", device_id_log, device.name);
-        return;
-    }
+    // 2. 检查设备是否绑定容器，未绑定时尝试回退到默认服务器
+    let container_id = match device.bound_container_id {
+        Some(container_id) => container_id,
+        None => {
+            let fallback = if state.config.unbound_fallback_enabled {
+                state
+                    .device_store
+                    .get_default_container_id(state.config.default_container_id.as_deref())
+                    .await
+            } else {
+                Ok(None)
+            };
+
+            match fallback {
+                Ok(Some(container_id)) => {
+                    info!(
+                        "[Proxy] 设备未绑定容器，使用默认服务器: device_id={}, device_name={}, container_id={}",
+                        device_id_log, device.name, container_id
+                    );
+                    container_id
+                }
+                Ok(None) => {
+                    warn!("[Proxy] 设备未绑定容器: device_id={}, device_name={}", device_id_log, device.name);
+                    return Err(RouteRejection::silent("Device is not bound to a server"));
+                }
+                Err(e) => {
+                    error!("[Proxy] 查询默认服务器失败: device_id={}, error={}", device_id_log, e);
+                    return Err(RouteRejection::silent("Default server lookup failed"));
+                }
+            }
+        }
+    };
 
     // 3. 查询容器信息
-    let container = match state.device_store.get_container_for_device(&normalized_device_id).await {
+    let container = match state.device_store.resolve_container_endpoint(&container_id).await {
         Ok(container) => container,
         Err(e) => {
             error!("[Proxy] 查询容器信息失败: device_id={}, error={}", device_id_log, e);
-            return;
+            return Err(RouteRejection::silent("Server lookup failed"));
         }
     };
 
+    // 因空闲被自动停止的容器，请求后端唤醒，设备稍后重连即可
+    if should_request_wake(&container) {
+        info!(
+            "[Proxy] 容器已自动停止，请求唤醒: device_id={}, container_id={}",
+            device_id_log, container.container_id
+        );
+        if let Err(e) = state
+            .device_store
+            .request_container_wake(&container.container_id)
+            .await
+        {
+            error!(
+                "[Proxy] 请求唤醒容器失败: container_id={}, error={}",
+                container.container_id, e
+            );
+        }
+        return Err(RouteRejection::close(
+            close_code::AGAIN,
+            "Server is starting, try again later",
+        ));
+    }
+
+    admit_new_connection(&container, &device_id_log)?;
+
     // 4. 构建 EchoKit Server WebSocket URL（使用原始格式的 device_id）
     let server_url = if container.port == 443 || container.port == 80 {
         format!(
@@ -127,53 +452,982 @@ async fn handle_device_connection(
         device_id_log, device.name, server_url_log
     );
 
-    // 5. 标记设备为在线
-    if let Err(e) = state.device_store.mark_device_online(&normalized_device_id).await {
-        error!("[Proxy] 标记设备在线失败: device_id={}, error={}", device_id_log, e);
-    }
+    Ok(DeviceRoute {
+        normalized_device_id,
+        container,
+        server_url,
+        server_url_log,
+    })
+}
+
+/// 处理设备 WebSocket 连接
+async fn handle_device_connection(
+    device_ws: WebSocket,
+    device_id: String,
+    client_ip: String,
+    user_agent: Option<String>,
+    state: Arc<AppState>,
+) {
+    // 用于日志的 device_id 格式（小写无冒号）
+    let device_id_log = format_device_id_for_log(&device_id);
+
+    info!(
+        "[Proxy] 设备 WebSocket 连接已建立: device_id={}, client_ip={}",
+        device_id_log, client_ip
+    );
+
+    let DeviceRoute {
+        normalized_device_id,
+        container,
+        server_url,
+        server_url_log,
+    } = match resolve_device_route(&state, &device_id).await {
+        Ok(route) => route,
+        Err(rejection) => {
+            if let Some(code) = rejection.close_code {
+                close_device_socket(device_ws, code, &rejection.reason).await;
+            }
+            return;
+        }
+    };
+
+    // 5. 标记设备为在线，并记录连接日志
+    let session = match state
+        .device_store
+        .mark_device_online(&normalized_device_id, &client_ip, user_agent.as_deref())
+        .await
+    {
+        Ok(session) => session,
+        Err(e) => {
+            error!(
+                "[Proxy] 标记设备在线失败: device_id={}, error={}",
+                device_id_log, e
+            );
+            None
+        }
+    };
+
+    let connection_id = match state
+        .device_store
+        .record_connection(&normalized_device_id, &container.container_id, &client_ip)
+        .await
+    {
+        Ok(id) => Some(id),
+        Err(e) => {
+            error!(
+                "[Proxy] 记录连接日志失败: device_id={}, error={}",
+                device_id_log, e
+            );
+            None
+        }
+    };
 
     // 6. 开始双向转发
     info!(
         "[Proxy] 开始双向转发: device_id={} <-> server={}",
         device_id_log, server_url_log
     );
-    match bidirectional_forward(device_ws, server_url, normalized_device_id.clone()).await {
-        Ok(_) => {
-            info!("[Proxy] 设备连接正常结束: device_id={}, server={}", device_id_log, server_url_log);
+    let options = ForwardOptions {
+        connect_timeout: Duration::from_millis(state.config.server_connect_timeout_ms),
+        bandwidth_limit: state.config.device_bandwidth_limit,
+    };
+    state.connection_opened(&container.container_id);
+    let forward_started = Instant::now();
+    let result = bidirectional_forward(
+        device_ws,
+        server_url,
+        normalized_device_id.clone(),
+        options,
+    )
+    .await;
+    state.connection_closed(&container.container_id);
+    log_access(
+        &device_id_log,
+        &container.container_id,
+        &client_ip,
+        forward_started.elapsed(),
+        result.as_ref().ok(),
+    );
+
+    let stats = match result {
+        Ok(stats) => {
+            let summary = format!(
+                "device->server text={} binary={} errors={}, server->device text={} binary={} errors={}",
+                stats.device_to_server.text_frames,
+                stats.device_to_server.binary_frames,
+                stats.device_to_server.errors,
+                stats.server_to_device.text_frames,
+                stats.server_to_device.binary_frames,
+                stats.server_to_device.errors,
+            );
+            if stats.close_reason.is_clean() {
+                info!(
+                    "[Proxy] 设备连接正常结束: device_id={}, server={}, closed_by={}, {}",
+                    device_id_log,
+                    server_url_log,
+                    stats.close_reason.as_str(),
+                    summary
+                );
+            } else {
+                warn!(
+                    "[Proxy] 设备连接因错误结束: device_id={}, server={}, {}",
+                    device_id_log, server_url_log, summary
+                );
+            }
+            Some(stats)
         }
         Err(e) => {
             error!("[Proxy] 设备连接异常结束: device_id={}, server={}, error={}", device_id_log, server_url_log, e);
+            None
+        }
+    };
+
+    // 7. 标记设备为离线（设备已通过新会话重连时跳过）
+    if let Some(session) = session {
+        if let Err(e) = state
+            .device_store
+            .mark_device_offline(&normalized_device_id, session)
+            .await
+        {
+            // 数据库暂时不可用时在后台重试，避免设备一直显示在线
+            warn!(
+                "[Proxy] 标记设备离线失败，将在后台重试: device_id={}, error={}",
+                device_id_log, e
+            );
+            tokio::spawn(retry_mark_offline(
+                state.clone(),
+                normalized_device_id.clone(),
+                session,
+            ));
         }
     }
 
-    // 7. 标记设备为离线
-    if let Err(e) = state.device_store.mark_device_offline(&normalized_device_id).await {
-        error!("[Proxy] 标记设备离线失败: device_id={}, error={}", device_id_log, e);
+    if let Some(connection_id) = connection_id {
+        if let Err(e) = state
+            .device_store
+            .record_disconnection(connection_id, stats.as_ref())
+            .await {
+            error!("[Proxy] 记录断开时间失败: device_id={}, error={}", device_id_log, e);
+        }
     }
 
     info!("[Proxy] 设备 WebSocket 连接已关闭: device_id={}, server={}", device_id_log, server_url_log);
 }
 
+/// 连接结束时输出一行访问日志（单条结构化事件，便于日志系统解析）
+///
+/// `stats` 为 None 表示未能连接到 EchoKit Server。
+fn log_access(
+    device_id: &str,
+    server: &str,
+    client_ip: &str,
+    duration: Duration,
+    stats: Option<&ForwardStats>,
+) {
+    let (bytes_up, bytes_down, frames_up, frames_down, close_reason) = match stats {
+        Some(stats) => (
+            stats.device_to_server.bytes,
+            stats.server_to_device.bytes,
+            stats.device_to_server.frames(),
+            stats.server_to_device.frames(),
+            stats.close_reason.as_str(),
+        ),
+        None => (0, 0, 0, 0, "connect_failed"),
+    };
+    info!(
+        target: ACCESS_LOG_TARGET,
+        device_id,
+        server,
+        client_ip,
+        duration_ms = duration.as_millis() as u64,
+        bytes_up,
+        bytes_down,
+        frames_up,
+        frames_down,
+        close_reason,
+        "access"
+    );
+}
+
+/// 按指数退避重试标记设备离线，超过重试次数后放弃
+///
+/// 离线更新带有会话时间戳，设备在重试期间重连时不会被误标为离线。
+async fn retry_mark_offline(state: Arc<AppState>, device_id: String, session: i64) {
+    let device_id_log = format_device_id_for_log(&device_id);
+    let max_retries = state.config.status_update_max_retries;
+
+    let result = retry_with_backoff(max_retries, Duration::from_secs(1), |attempt| {
+        let state = state.clone();
+        let device_id = device_id.clone();
+        let device_id_log = device_id_log.clone();
+        async move {
+            let result = state
+                .device_store
+                .mark_device_offline(&device_id, session)
+                .await;
+            if let Err(e) = &result {
+                warn!(
+                    "[Proxy] 重试标记设备离线失败: device_id={}, attempt={}/{}, error={}",
+                    device_id_log, attempt, max_retries, e
+                );
+            }
+            result
+        }
+    })
+    .await;
+
+    match result {
+        Some((attempt, true)) => info!(
+            "[Proxy] 重试标记设备离线成功: device_id={}, attempt={}",
+            device_id_log, attempt
+        ),
+        Some((_, false)) => debug!(
+            "[Proxy] 设备已有新的会话，跳过离线标记: device_id={}",
+            device_id_log
+        ),
+        None => error!(
+            "[Proxy] 标记设备离线失败，已放弃重试: device_id={}",
+            device_id_log
+        ),
+    }
+}
+
+/// 按指数退避（上限 60 秒）重试操作，每次重试前先等待
+///
+/// 返回成功时的尝试次数与结果，重试次数用尽时返回 None。
+async fn retry_with_backoff<T, F, Fut>(
+    max_retries: u32,
+    initial_delay: Duration,
+    mut operation: F,
+) -> Option<(u32, T)>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<T>>,
+{
+    let mut delay = initial_delay;
+    for attempt in 1..=max_retries {
+        tokio::time::sleep(delay).await;
+        if let Ok(value) = operation(attempt).await {
+            return Some((attempt, value));
+        }
+        delay = (delay * 2).min(Duration::from_secs(60));
+    }
+    None
+}
+
+/// 处理连接测试请求
+///
+/// 路径: /ws-test/{device_id}
+///
+/// 与设备连接走相同的查询/路由/建连逻辑，但只和服务器交换一次 ping/pong，
+/// 结果以 JSON 形式放在关闭帧的原因中返回。按客户端 IP 限流。
+pub async fn handle_test_websocket(
+    ws: WebSocketUpgrade,
+    Path(device_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> axum::response::Response {
+    let device_id_log = format_device_id_for_log(&device_id);
+    let client_ip = resolve_client_ip(peer, &headers, state.config.trusted_proxy_depth);
+
+    if let Err(retry_after) = state.try_acquire_ws_test(&client_ip) {
+        warn!(
+            "[Proxy] 连接测试过于频繁，拒绝: device_id={}, client_ip={}",
+            device_id_log, client_ip
+        );
+        return too_many_requests("Connection test rate limit exceeded", retry_after);
+    }
+
+    info!(
+        "[Proxy] 收到连接测试请求: device_id={}, client_ip={}",
+        device_id_log, client_ip
+    );
+    ws.on_upgrade(move |socket| handle_test_connection(socket, device_id, state))
+        .into_response()
+}
+
+/// 限流响应：429 带 Retry-After 头（秒，向上取整）与 JSON 中的 retry_after_ms
+fn too_many_requests(message: &str, retry_after: Duration) -> axum::response::Response {
+    let retry_after_ms = retry_after.as_millis().max(1) as u64;
+    let retry_after_secs = retry_after_ms.div_ceil(1000);
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, retry_after_secs.to_string())],
+        axum::Json(serde_json::json!({
+            "error": "rate_limited",
+            "message": message,
+            "retry_after_ms": retry_after_ms,
+        })),
+    )
+        .into_response()
+}
+
+/// 执行连接测试并通过关闭帧报告结果
+async fn handle_test_connection(device_ws: WebSocket, device_id: String, state: Arc<AppState>) {
+    let device_id_log = format_device_id_for_log(&device_id);
+
+    let route = match resolve_device_route(&state, &device_id).await {
+        Ok(route) => route,
+        Err(rejection) => {
+            let reason = serde_json::json!({ "ok": false, "reason": rejection.reason });
+            close_device_socket(device_ws, close_code::POLICY, &reason.to_string()).await;
+            return;
+        }
+    };
+
+    let timeout = Duration::from_millis(state.config.server_connect_timeout_ms);
+    let (code, reason) = match probe_server(&route.server_url, timeout).await {
+        Ok(elapsed) => {
+            info!(
+                "[Proxy] 连接测试成功: device_id={}, server={}, 耗时={}ms",
+                device_id_log,
+                route.server_url_log,
+                elapsed.as_millis()
+            );
+            (
+                close_code::NORMAL,
+                serde_json::json!({
+                    "ok": true,
+                    "containerId": route.container.container_id,
+                    "latencyMs": elapsed.as_millis() as u64,
+                }),
+            )
+        }
+        Err(e) => {
+            warn!(
+                "[Proxy] 连接测试失败: device_id={}, server={}, error={:#}",
+                device_id_log, route.server_url_log, e
+            );
+            (
+                close_code::ERROR,
+                serde_json::json!({ "ok": false, "reason": "EchoKit Server unreachable" }),
+            )
+        }
+    };
+
+    close_device_socket(device_ws, code, &reason.to_string()).await;
+}
+
 /// 健康检查接口
 pub async fn health_check(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     // 检查数据库连接
     let db_connected = state.device_store.check_connection().await;
 
-    if db_connected {
-        (
-            StatusCode::OK,
-            axum::Json(serde_json::json!({
-                "status": "ok",
-                "database_connected": true
-            })),
-        )
+    let version = BuildInfo::current();
+    let backend_version = state
+        .backend_version
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    let version_mismatch = backend_version
+        .as_ref()
+        .is_some_and(|backend| !version.matches(backend));
+
+    let response = HealthCheckResponse {
+        status: if db_connected { "ok" } else { "error" }.to_string(),
+        uptime_seconds: state.started_at.elapsed().as_secs(),
+        active_connections: state.active_connections.load(Ordering::Relaxed),
+        database_connected: db_connected,
+        version,
+        backend_version,
+        version_mismatch,
+    };
+
+    let status = if db_connected {
+        StatusCode::OK
     } else {
-        (
-            StatusCode::SERVICE_UNAVAILABLE,
-            axum::Json(serde_json::json!({
-                "status": "error",
-                "database_connected": false
-            })),
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, axum::Json(response))
+}
+
+/// Proxy 与 Backend 的版本信息（每次请求时重新获取 Backend 版本）
+///
+/// 路径: /version（健康检查端口）
+pub async fn version_info(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let backend = state.refresh_backend_version().await;
+    let proxy = BuildInfo::current();
+    let mismatch = backend
+        .as_ref()
+        .is_some_and(|backend| !proxy.matches(backend));
+
+    axum::Json(VersionResponse {
+        proxy,
+        backend,
+        mismatch,
+    })
+}
+
+/// 各服务器当前连接数
+///
+/// 路径: /metrics/containers（健康检查端口）
+pub async fn container_metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let containers = state
+        .container_connections
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+
+    axum::Json(ContainerMetricsResponse { containers })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forwarder::{CloseReason, DirectionStats};
+
+    fn container(status: &str, auto_stopped: bool) -> ContainerInfo {
+        ContainerInfo {
+            container_id: "abc123".to_string(),
+            name: "echokit-server-abc123".to_string(),
+            host: "localhost".to_string(),
+            port: 8080,
+            protocol: "ws".to_string(),
+            status: status.to_string(),
+            draining: false,
+            auto_stopped,
+        }
+    }
+
+    /// 连接 DATABASE_URL 指向的开发数据库（需已执行 Backend migrations），未设置时跳过
+    async fn test_state(config: ProxyConfig) -> Option<(AppState, sqlx::PgPool)> {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL 未设置，跳过数据库测试");
+            return None;
+        };
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&url)
+            .await
+            .unwrap();
+        Some((app_state(pool.clone(), config), pool))
+    }
+
+    fn app_state(pool: sqlx::PgPool, config: ProxyConfig) -> AppState {
+        AppState {
+            device_store: DeviceStore::new(pool),
+            config,
+            active_connections: AtomicUsize::new(0),
+            started_at: Instant::now(),
+            ws_test_last_seen: Mutex::new(HashMap::new()),
+            container_connections: Mutex::new(HashMap::new()),
+            backend_version: Mutex::new(None),
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    /// 不访问数据库的状态（连接池延迟连接）
+    fn offline_state(config: ProxyConfig) -> AppState {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/echokit")
+            .unwrap();
+        app_state(pool, config)
+    }
+
+    async fn metrics(state: &Arc<AppState>) -> HashMap<String, usize> {
+        let response = container_metrics(State(state.clone()))
+            .await
+            .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        serde_json::from_value(body["containers"].clone()).unwrap()
+    }
+
+    /// 在给定的日志过滤规则下执行 `f`，返回输出的日志文本
+    fn capture_logs(filter: &str, f: impl FnOnce()) -> String {
+        #[derive(Clone, Default)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::new(filter))
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .without_time()
+            .finish();
+        tracing::subscriber::with_default(subscriber, f);
+
+        let output = buffer.0.lock().unwrap().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn access_log_summarizes_the_connection() {
+        let stats = ForwardStats {
+            device_to_server: DirectionStats {
+                text_frames: 1,
+                binary_frames: 40,
+                bytes: 25_600,
+                errors: 0,
+            },
+            server_to_device: DirectionStats {
+                text_frames: 3,
+                binary_frames: 12,
+                bytes: 9_000,
+                errors: 0,
+            },
+            close_reason: CloseReason::Device,
+        };
+        let log = || {
+            log_access(
+                "98:A3:16:F0:B1:E5",
+                "ws://localhost:8080/ws/98:A3:16:F0:B1:E5",
+                "203.0.113.7",
+                Duration::from_millis(1500),
+                Some(&stats),
+            )
+        };
+
+        let output = capture_logs("info", log);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 1, "{output}");
+        let line = lines[0];
+        assert!(
+            line.contains("INFO echokit_proxy::access: access"),
+            "{line}"
+        );
+        for field in [
+            r#"device_id="98:A3:16:F0:B1:E5""#,
+            r#"server="ws://localhost:8080/ws/98:A3:16:F0:B1:E5""#,
+            r#"client_ip="203.0.113.7""#,
+            "duration_ms=1500",
+            "bytes_up=25600",
+            "bytes_down=9000",
+            "frames_up=41",
+            "frames_down=15",
+            r#"close_reason="device""#,
+        ] {
+            assert!(line.contains(field), "missing {field} in {line}");
+        }
+
+        // 未能连接服务器时同样输出一行
+        let output = capture_logs("info", || {
+            log_access(
+                "98:A3:16:F0:B1:E5",
+                "ws://x",
+                "203.0.113.7",
+                Duration::ZERO,
+                None,
+            )
+        });
+        assert!(
+            output.contains(r#"close_reason="connect_failed""#),
+            "{output}"
+        );
+
+        // 可按 target 单独关闭
+        assert!(capture_logs("info,echokit_proxy::access=off", log).is_empty());
+    }
+
+    #[test]
+    fn user_agent_falls_back_to_firmware_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(resolve_user_agent(&headers), None);
+
+        headers.insert("x-firmware-version", "echokit-fw/1.4.2".parse().unwrap());
+        assert_eq!(
+            resolve_user_agent(&headers).as_deref(),
+            Some("echokit-fw/1.4.2")
+        );
+
+        // User-Agent 优先，空值被忽略
+        headers.insert(header::USER_AGENT, "  ".parse().unwrap());
+        assert_eq!(
+            resolve_user_agent(&headers).as_deref(),
+            Some("echokit-fw/1.4.2")
+        );
+        headers.insert(header::USER_AGENT, "esp-idf/5.1".parse().unwrap());
+        assert_eq!(resolve_user_agent(&headers).as_deref(), Some("esp-idf/5.1"));
+
+        let long = "a".repeat(MAX_USER_AGENT_CHARS + 10);
+        headers.insert(header::USER_AGENT, long.parse().unwrap());
+        assert_eq!(
+            resolve_user_agent(&headers).unwrap().len(),
+            MAX_USER_AGENT_CHARS
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_recovers_after_transient_failures() {
+        let mut calls = 0;
+        let started = Instant::now();
+        let result = retry_with_backoff(5, Duration::from_millis(10), |attempt| {
+            calls += 1;
+            async move {
+                if attempt < 3 {
+                    anyhow::bail!("connection refused");
+                }
+                Ok(true)
+            }
+        })
+        .await;
+
+        assert_eq!(result, Some((3, true)));
+        assert_eq!(calls, 3);
+        // 10ms + 20ms + 40ms 的退避等待
+        assert!(started.elapsed() >= Duration::from_millis(70));
+    }
+
+    #[tokio::test]
+    async fn retry_gives_up_after_max_retries() {
+        let mut calls = 0;
+        let result = retry_with_backoff(3, Duration::from_millis(1), |_| {
+            calls += 1;
+            async { anyhow::Result::<bool>::Err(anyhow::anyhow!("connection refused")) }
+        })
+        .await;
+
+        assert_eq!(result, None);
+        assert_eq!(calls, 3);
+    }
+
+    #[tokio::test]
+    async fn counts_live_connections_per_container() {
+        let state = Arc::new(offline_state(ProxyConfig::from_env()));
+
+        state.connection_opened("container-a");
+        state.connection_opened("container-a");
+        state.connection_opened("container-b");
+        let counts = metrics(&state).await;
+        assert_eq!(counts["container-a"], 2);
+        assert_eq!(counts["container-b"], 1);
+        assert_eq!(state.active_connections.load(Ordering::Relaxed), 3);
+
+        state.connection_closed("container-a");
+        assert_eq!(metrics(&state).await["container-a"], 1);
+        state.connection_closed("container-a");
+        state.connection_closed("container-b");
+        // 计数归零的服务器不再出现
+        assert!(metrics(&state).await.is_empty());
+        assert_eq!(state.active_connections.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn ws_test_is_rate_limited_per_client_ip() {
+        let state = offline_state(ProxyConfig {
+            ws_test_interval_secs: 60,
+            ..ProxyConfig::from_env()
+        });
+
+        assert!(state.try_acquire_ws_test("203.0.113.7").is_ok());
+        let retry_after = state.try_acquire_ws_test("203.0.113.7").unwrap_err();
+        assert!(retry_after > Duration::from_secs(59));
+        // 其他客户端不受影响
+        assert!(state.try_acquire_ws_test("203.0.113.8").is_ok());
+    }
+
+    #[tokio::test]
+    async fn rate_limited_response_carries_retry_hint() {
+        let response = too_many_requests("slow down", Duration::from_millis(1500));
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "rate_limited");
+        assert_eq!(body["message"], "slow down");
+        assert_eq!(body["retry_after_ms"], 1500);
+
+        // 间隔即将结束时仍给出至少 1 秒的等待提示
+        let response = too_many_requests("slow down", Duration::ZERO);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+    }
+
+    /// 模拟 Backend 健康检查接口，上报指定的构建信息
+    async fn backend_health_server(version: serde_json::Value) -> String {
+        let app = axum::Router::new().route(
+            "/health",
+            axum::routing::get(move || async move {
+                axum::Json(serde_json::json!({ "status": "ok", "version": version }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/health", addr)
+    }
+
+    async fn health_body(state: &Arc<AppState>) -> serde_json::Value {
+        let response = health_check(State(state.clone())).await.into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn health_reports_backend_version_mismatch() {
+        let url = backend_health_server(serde_json::json!({
+            "version": "0.0.0-older",
+            "git_sha": "0123abc",
+            "build_timestamp": 1,
+        }))
+        .await;
+        let state = Arc::new(offline_state(ProxyConfig {
+            backend_health_url: Some(url),
+            ..ProxyConfig::from_env()
+        }));
+
+        // 获取 Backend 版本之前不报告不一致
+        let body = health_body(&state).await;
+        assert_eq!(body["version_mismatch"], false);
+        assert!(body.get("backend_version").is_none());
+
+        let backend = state.refresh_backend_version().await.unwrap();
+        assert_eq!(backend.version, "0.0.0-older");
+
+        let body = health_body(&state).await;
+        assert_eq!(body["version_mismatch"], true);
+        assert_eq!(body["backend_version"]["version"], "0.0.0-older");
+        assert_eq!(body["version"]["version"], env!("CARGO_PKG_VERSION"));
+    }
+
+    #[tokio::test]
+    async fn health_reports_matching_backend_version() {
+        let proxy = BuildInfo::current();
+        let url = backend_health_server(serde_json::json!({
+            "version": proxy.version,
+            "git_sha": proxy.git_sha,
+            "build_timestamp": proxy.build_timestamp,
+        }))
+        .await;
+        let state = Arc::new(offline_state(ProxyConfig {
+            backend_health_url: Some(url),
+            ..ProxyConfig::from_env()
+        }));
+
+        state.refresh_backend_version().await.unwrap();
+        let body = health_body(&state).await;
+        assert_eq!(body["version_mismatch"], false);
+        assert_eq!(body["backend_version"]["git_sha"], proxy.git_sha);
+    }
+
+    #[tokio::test]
+    async fn plain_get_is_told_to_upgrade() {
+        let state = Arc::new(offline_state(ProxyConfig::from_env()));
+        let app = axum::Router::new()
+            .route(
+                "/ws/{device_id}",
+                axum::routing::get(handle_device_websocket),
+            )
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+        });
+
+        // 浏览器直接打开设备地址：没有任何握手头
+        let response = reqwest::get(format!("http://{}/ws/98:A3:16:F0:B1:E5", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let body: serde_json::Value =
+            serde_json::from_slice(&response.bytes().await.unwrap()).unwrap();
+        assert_eq!(body["error"], "websocket_upgrade_required");
+        let message = body["message"].as_str().unwrap();
+        assert!(
+            message.contains("only accepts WebSocket connections"),
+            "{message}"
+        );
+        assert!(message.contains("Sec-WebSocket-Key"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn invalid_device_tokens_are_closed_with_policy_violation() {
+        use futures_util::StreamExt;
+        use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let state = Arc::new(offline_state(ProxyConfig {
+            device_token_secret: Some("shared-secret".to_string()),
+            require_device_token: true,
+            ..ProxyConfig::from_env()
+        }));
+        let app = axum::Router::new()
+            .route(
+                "/ws/{device_id}",
+                axum::routing::get(handle_device_websocket),
+            )
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+        });
+
+        // 已过期的令牌（2023 年）、伪造的令牌与缺失的令牌
+        let expired = "1700000000.Ewgy7SIwZ65zkSRrtgL2xPszEFBbQ57c4H5d4xI_xaQ";
+        let forged = format!("{}.Ewgy7SIwZ65zkSRrtgL2xPszEFBbQ57c4H5d4xI_xaQ", i64::MAX);
+        for (query, reason) in [
+            (format!("?token={expired}"), "Device token expired"),
+            (format!("?token={forged}"), "Invalid device token"),
+            (String::new(), "Missing device token"),
+        ] {
+            let url = format!("ws://{}/ws/98:A3:16:F0:B1:E5{}", addr, query);
+            let (mut device, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+            match device.next().await {
+                Some(Ok(Message::Close(Some(frame)))) => {
+                    assert_eq!(frame.code, CloseCode::from(close_code::POLICY));
+                    assert_eq!(frame.reason.as_str(), reason);
+                }
+                other => panic!("expected close frame, got {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn unbound_device_routes_to_configured_default_server() {
+        let suffix = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+        let container_id = format!("test-default-{suffix}");
+        let device_id = format!("test-unbound-{suffix}");
+        let config = ProxyConfig {
+            unbound_fallback_enabled: true,
+            default_container_id: Some(container_id.clone()),
+            ..ProxyConfig::from_env()
+        };
+        let Some((state, pool)) = test_state(config).await else {
+            return;
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO containers (id, name, host, port, created_at)
+            VALUES ($1, $1, 'echokit-default', 8080, 0)
+            "#,
         )
+        .bind(&container_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO devices (device_id, name, mac_address, created_at)
+            VALUES ($1, $1, $1, 0)
+            "#,
+        )
+        .bind(&device_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let route = resolve_device_route(&state, &device_id).await;
+
+        // 关闭回退时未绑定设备仍被拒绝
+        let mut disabled = state;
+        disabled.config.unbound_fallback_enabled = false;
+        let rejected = resolve_device_route(&disabled, &device_id).await;
+
+        sqlx::query("DELETE FROM devices WHERE device_id = $1")
+            .bind(&device_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM containers WHERE id = $1")
+            .bind(&container_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let route = route.unwrap_or_else(|r| panic!("route rejected: {}", r.reason));
+        assert_eq!(route.container.container_id, container_id);
+        assert_eq!(
+            route.server_url,
+            format!("ws://echokit-default:8080/ws/{}", device_id)
+        );
+        let rejection = rejected.err().expect("fallback disabled");
+        assert_eq!(rejection.reason, "Device is not bound to a server");
+    }
+
+    #[test]
+    fn normalize_mac_address_accepts_common_formats() {
+        let expected = "98:A3:16:F0:B1:E5";
+        assert_eq!(normalize_mac_address("98:a3:16:f0:b1:e5"), expected);
+        assert_eq!(normalize_mac_address("98-a3-16-f0-b1-e5"), expected);
+        assert_eq!(normalize_mac_address("98A316F0B1E5"), expected);
+        assert_eq!(normalize_mac_address("98a316f0b1e5"), expected);
+        assert_eq!(normalize_mac_address(expected), expected);
+    }
+
+    #[test]
+    fn normalize_mac_address_keeps_non_mac_ids() {
+        // 与后端 normalize_device_id 一致：非 MAC 格式原样返回
+        assert_eq!(normalize_mac_address("device-01"), "device-01");
+        assert_eq!(normalize_mac_address("98a316f0b1"), "98a316f0b1");
+    }
+
+    #[test]
+    fn wakes_only_auto_stopped_containers() {
+        assert!(should_request_wake(&container("stopped", true)));
+        // 手动停止的容器保持停止
+        assert!(!should_request_wake(&container("stopped", false)));
+        assert!(!should_request_wake(&container("running", false)));
+    }
+
+    #[test]
+    fn client_ip_ignores_forwarded_for_without_trusted_proxy() {
+        let peer: SocketAddr = "203.0.113.7:51000".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(resolve_client_ip(peer, &headers, 0), "203.0.113.7");
+        assert_eq!(resolve_client_ip(peer, &headers, 1), "203.0.113.7");
+
+        headers.insert("x-forwarded-for", "198.51.100.1".parse().unwrap());
+        assert_eq!(resolve_client_ip(peer, &headers, 0), "203.0.113.7");
+    }
+
+    #[test]
+    fn client_ip_uses_trusted_forwarded_for_depth() {
+        let peer: SocketAddr = "10.0.0.2:51000".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        // 设备伪造了首项，可信代理依次追加看到的地址
+        headers.insert(
+            "x-forwarded-for",
+            "1.2.3.4, 198.51.100.1, 10.0.0.1".parse().unwrap(),
+        );
+        assert_eq!(resolve_client_ip(peer, &headers, 1), "10.0.0.1");
+        assert_eq!(resolve_client_ip(peer, &headers, 2), "198.51.100.1");
+        // 项数不足可信层数时退回 TCP 对端地址
+        assert_eq!(resolve_client_ip(peer, &headers, 4), "10.0.0.2");
+    }
+
+    #[test]
+    fn draining_container_refuses_new_connections() {
+        assert!(admit_new_connection(&container("running", false), "aabbccddeeff").is_ok());
+
+        let mut draining = container("running", false);
+        draining.draining = true;
+        let rejection = admit_new_connection(&draining, "aabbccddeeff").unwrap_err();
+        assert_eq!(rejection.close_code, Some(close_code::AGAIN));
+        assert_eq!(rejection.reason, "Server is draining, try again later");
+
+        let rejection =
+            admit_new_connection(&container("stopped", false), "aabbccddeeff").unwrap_err();
+        assert_eq!(rejection.reason, "Server not available");
     }
 }
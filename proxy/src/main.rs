@@ -3,24 +3,80 @@ mod forwarder;
 mod handler;
 mod models;
 mod store;
+mod token;
 
+use std::collections::HashMap;
 use std::future::IntoFuture;
-use std::sync::Arc;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::Context;
 use axum::{
     routing::get,
     Router,
 };
-use sqlx::postgres::PgPoolOptions;
+use sqlx::postgres::{PgPool, PgPoolOptions};
 use tower_http::cors::{Any, CorsLayer};
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::config::ProxyConfig;
-use crate::handler::{handle_device_websocket, health_check, AppState};
+use crate::handler::{
+    container_metrics, handle_device_websocket, handle_test_websocket, health_check, version_info,
+    AppState, ACCESS_LOG_TARGET,
+};
 use crate::store::DeviceStore;
 
+/// 连接数据库，失败时按指数退避重试（应对 docker-compose 中数据库晚于服务就绪）
+async fn connect_database(
+    options: PgPoolOptions,
+    database_url: &str,
+    max_attempts: u32,
+    initial_delay: Duration,
+) -> anyhow::Result<PgPool> {
+    retry_connect(max_attempts, initial_delay, || {
+        options.clone().connect(database_url)
+    })
+    .await
+}
+
+/// 重复尝试连接直至成功，间隔按指数增长（上限 30 秒），最多尝试 `max_attempts` 次
+async fn retry_connect<T, E, F, Fut>(
+    max_attempts: u32,
+    initial_delay: Duration,
+    mut connect: F,
+) -> anyhow::Result<T>
+where
+    E: std::error::Error + Send + Sync + 'static,
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, E>>,
+{
+    let mut delay = initial_delay;
+    let mut attempt = 1;
+    loop {
+        match connect().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < max_attempts => {
+                warn!(
+                    "数据库尚未就绪 (第 {}/{} 次): {}，{} 秒后重试",
+                    attempt,
+                    max_attempts,
+                    e,
+                    delay.as_secs()
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(Duration::from_secs(30));
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(e).context(format!("连接数据库失败（已尝试 {} 次）", max_attempts))
+            }
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // 加载环境变量
@@ -33,7 +89,15 @@ async fn main() -> anyhow::Result<()> {
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| format!("echokit_proxy={},sqlx=warn", config.log_level).into()),
+                .unwrap_or_else(|_| {
+                    // 访问日志单独控制，不受 LOG_LEVEL 影响
+                    let access_level = if config.access_log { "info" } else { "off" };
+                    format!(
+                        "echokit_proxy={},{}={},sqlx=warn",
+                        config.log_level, ACCESS_LOG_TARGET, access_level
+                    )
+                    .into()
+                }),
         )
         .with(tracing_subscriber::fmt::layer())
         .init();
@@ -42,16 +106,27 @@ async fn main() -> anyhow::Result<()> {
     info!("配置信息:");
     info!("  - Proxy 端口: {}", config.proxy_port);
     info!("  - 健康检查端口: {}", config.health_check_port);
-    info!("  - 数据库: {}", config.database_url.split('@').last().unwrap_or(""));
+    info!(
+        "  - 数据库: {}",
+        config.database_url.split('@').next_back().unwrap_or("")
+    );
     info!("  - EchoKit Server 主机: {}", config.echokit_host);
+    info!("  - 设备连接令牌校验: {}", config.require_device_token);
+    info!("  - 访问日志: {}", config.access_log);
+
+    if config.require_device_token && config.device_token_secret.is_none() {
+        anyhow::bail!("REQUIRE_DEVICE_TOKEN=true 需要同时配置 DEVICE_TOKEN_SECRET");
+    }
 
     // 初始化数据库连接池
     info!("连接到数据库...");
-    let pool = PgPoolOptions::new()
-        .max_connections(config.db_pool_size)
-        .connect(&config.database_url)
-        .await
-        .context("连接数据库失败")?;
+    let pool = connect_database(
+        PgPoolOptions::new().max_connections(config.db_pool_size),
+        &config.database_url,
+        config.db_connect_max_attempts,
+        Duration::from_secs(config.db_connect_retry_interval_secs),
+    )
+    .await?;
 
     info!("数据库连接成功");
 
@@ -62,11 +137,29 @@ async fn main() -> anyhow::Result<()> {
     let state = Arc::new(AppState {
         device_store,
         config: config.clone(),
+        active_connections: AtomicUsize::new(0),
+        started_at: Instant::now(),
+        ws_test_last_seen: Mutex::new(HashMap::new()),
+        container_connections: Mutex::new(HashMap::new()),
+        backend_version: Mutex::new(None),
+        http_client: reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .context("创建 HTTP 客户端失败")?,
+    });
+
+    // 启动时获取 Backend 版本，不一致时记录警告
+    tokio::spawn({
+        let state = state.clone();
+        async move {
+            state.refresh_backend_version().await;
+        }
     });
 
     // 创建 WebSocket 服务器路由
     let ws_app = Router::new()
         .route("/ws/{device_id}", get(handle_device_websocket))
+        .route("/ws-test/{device_id}", get(handle_test_websocket))
         .with_state(state.clone())
         .layer(
             CorsLayer::new()
@@ -78,6 +171,8 @@ async fn main() -> anyhow::Result<()> {
     // 创建健康检查服务器路由
     let health_app = Router::new()
         .route("/health", get(health_check))
+        .route("/metrics/containers", get(container_metrics))
+        .route("/version", get(version_info))
         .with_state(state.clone());
 
     // 启动 WebSocket 服务器
@@ -94,9 +189,63 @@ async fn main() -> anyhow::Result<()> {
 
     // 并发运行两个服务器
     tokio::try_join!(
-        axum::serve(ws_listener, ws_app).into_future(),
+        axum::serve(
+            ws_listener,
+            ws_app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .into_future(),
         axum::serve(health_listener, health_app).into_future(),
     )?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Error, ErrorKind};
+
+    #[tokio::test]
+    async fn retry_connect_succeeds_after_failures() {
+        let mut attempts = 0;
+        let result = retry_connect(5, Duration::from_millis(1), || {
+            attempts += 1;
+            let attempt = attempts;
+            async move {
+                if attempt <= 2 {
+                    Err(Error::new(
+                        ErrorKind::ConnectionRefused,
+                        "connection refused",
+                    ))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn retry_connect_gives_up_after_max_attempts() {
+        let mut attempts = 0;
+        let result: anyhow::Result<()> = retry_connect(2, Duration::from_millis(1), || {
+            attempts += 1;
+            async {
+                Err(Error::new(
+                    ErrorKind::ConnectionRefused,
+                    "connection refused",
+                ))
+            }
+        })
+        .await;
+
+        assert_eq!(attempts, 2);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "连接数据库失败（已尝试 2 次）"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// 设备状态
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -19,6 +20,23 @@ impl std::fmt::Display for DeviceStatus {
     }
 }
 
+impl DeviceStatus {
+    /// 解析数据库中存储的状态字符串
+    ///
+    /// 未知取值记录警告后按 Unknown 处理，避免拼写错误等脏数据被静默吞掉。
+    pub fn from_db_str(value: &str) -> Self {
+        match value {
+            "online" => DeviceStatus::Online,
+            "offline" => DeviceStatus::Offline,
+            "unknown" => DeviceStatus::Unknown,
+            other => {
+                tracing::warn!("Unexpected device status in database: {:?}", other);
+                DeviceStatus::Unknown
+            }
+        }
+    }
+}
+
 /// 设备信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -47,6 +65,7 @@ pub struct Device {
 
 /// 容器信息
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 pub struct ContainerInfo {
     /// 容器 ID
     pub container_id: String,
@@ -63,8 +82,14 @@ pub struct ContainerInfo {
     /// 协议类型（ws 或 wss）
     pub protocol: String,
 
-    /// 容器状态
+    /// 容器状态（running, stopped, error, creating, starting）
     pub status: String,
+
+    /// 是否处于排空模式（拒绝新的设备连接）
+    pub draining: bool,
+
+    /// 是否因空闲被自动停止（设备连接时请求后端唤醒，手动停止的容器不唤醒）
+    pub auto_stopped: bool,
 }
 
 /// 健康检查响应
@@ -74,4 +99,112 @@ pub struct HealthCheckResponse {
     pub uptime_seconds: u64,
     pub active_connections: usize,
     pub database_connected: bool,
+    pub version: BuildInfo,
+    /// 最近一次获取到的 Backend 版本（未配置或获取失败时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend_version: Option<RemoteBuildInfo>,
+    /// Backend 与 Proxy 版本是否不一致
+    pub version_mismatch: bool,
+}
+
+/// 版本信息响应（Proxy 自身与 Backend）
+#[derive(Debug, Serialize)]
+pub struct VersionResponse {
+    pub proxy: BuildInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<RemoteBuildInfo>,
+    pub mismatch: bool,
+}
+
+/// 各服务器连接数响应
+#[derive(Debug, Serialize)]
+pub struct ContainerMetricsResponse {
+    /// 容器 ID -> 当前连接数（无连接的服务器不出现）
+    pub containers: HashMap<String, usize>,
+}
+
+/// 构建信息（版本号、git 提交、构建时间）
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_sha: &'static str,
+    /// 构建时间（Unix 时间戳）
+    pub build_timestamp: u64,
+}
+
+impl BuildInfo {
+    /// 当前二进制的构建信息（由 build.rs 在编译期写入）
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_sha: env!("GIT_SHA"),
+            build_timestamp: env!("BUILD_TIMESTAMP").parse().unwrap_or(0),
+        }
+    }
+
+    /// 与另一个服务的构建信息是否一致（任一方 git 提交未知时只比较版本号）
+    pub fn matches(&self, other: &RemoteBuildInfo) -> bool {
+        let sha_known = self.git_sha != "unknown" && other.git_sha != "unknown";
+        self.version == other.version && (!sha_known || self.git_sha == other.git_sha)
+    }
+}
+
+/// 其他服务（Backend）上报的构建信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteBuildInfo {
+    pub version: String,
+    pub git_sha: String,
+    pub build_timestamp: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_info_is_populated() {
+        let info = BuildInfo::current();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(!info.git_sha.is_empty());
+        assert!(info.build_timestamp > 0);
+    }
+
+    #[test]
+    fn build_info_matches_ignores_unknown_git_sha() {
+        let proxy = BuildInfo {
+            version: "1.2.0",
+            git_sha: "abc1234",
+            build_timestamp: 1,
+        };
+        let backend = |version: &str, git_sha: &str| RemoteBuildInfo {
+            version: version.to_string(),
+            git_sha: git_sha.to_string(),
+            build_timestamp: 2,
+        };
+
+        assert!(proxy.matches(&backend("1.2.0", "abc1234")));
+        assert!(!proxy.matches(&backend("1.2.0", "def5678")));
+        assert!(!proxy.matches(&backend("1.1.0", "abc1234")));
+        // 任一方提交未知时只比较版本号
+        assert!(proxy.matches(&backend("1.2.0", "unknown")));
+        assert!(!proxy.matches(&backend("1.1.0", "unknown")));
+    }
+
+    #[test]
+    fn device_status_parses_known_values() {
+        for status in [
+            DeviceStatus::Online,
+            DeviceStatus::Offline,
+            DeviceStatus::Unknown,
+        ] {
+            assert_eq!(DeviceStatus::from_db_str(&status.to_string()), status);
+        }
+    }
+
+    #[test]
+    fn device_status_maps_unexpected_values_to_unknown() {
+        assert_eq!(DeviceStatus::from_db_str("Online"), DeviceStatus::Unknown);
+        assert_eq!(DeviceStatus::from_db_str("onlien"), DeviceStatus::Unknown);
+        assert_eq!(DeviceStatus::from_db_str(""), DeviceStatus::Unknown);
+    }
 }
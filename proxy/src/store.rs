@@ -1,7 +1,8 @@
 use crate::models::{ContainerInfo, Device, DeviceStatus};
+use crate::forwarder::ForwardStats;
 use anyhow::{anyhow, Context, Result};
 use sqlx::{PgPool, Row};
-use tracing::{debug, error};
+use tracing::debug;
 
 #[derive(Clone)]
 pub struct DeviceStore {
@@ -37,12 +38,7 @@ impl DeviceStore {
         .context("查询设备失败")?;
 
         Ok(row.map(|row| {
-            let status_str: String = row.get("status");
-            let status = match status_str.as_str() {
-                "online" => DeviceStatus::Online,
-                "offline" => DeviceStatus::Offline,
-                _ => DeviceStatus::Unknown,
-            };
+            let status = DeviceStatus::from_db_str(row.get("status"));
 
             Device {
                 device_id: row.get("device_id"),
@@ -56,47 +52,46 @@ impl DeviceStore {
         }))
     }
 
-    /// 获取设备绑定的容器信息
-    pub async fn get_container_for_device(
+    /// 获取未绑定设备的回退服务器 ID
+    ///
+    /// 优先使用配置指定的服务器，否则使用标记为默认的服务器。
+    pub async fn get_default_container_id(
         &self,
-        device_id: &str,
-    ) -> Result<ContainerInfo> {
-        debug!("查询设备绑定的容器: device_id={}", device_id);
+        configured: Option<&str>,
+    ) -> Result<Option<String>> {
+        if let Some(container_id) = configured {
+            return Ok(Some(container_id.to_string()));
+        }
 
-        // 1. 查询设备信息
-        let device = self
-            .get_device(device_id)
-            .await?
-            .ok_or_else(|| anyhow!("设备不存在: {}", device_id))?;
+        debug!("查询默认服务器");
 
-        // 2. 检查是否绑定容器
-        let container_id = device
-            .bound_container_id
-            .ok_or_else(|| anyhow!("设备未绑定容器: {}", device_id))?;
-
-        // 3. 根据容器 ID 判断是官方容器还是用户自建容器
-        let (host, port, protocol) = self.resolve_container_endpoint(&container_id).await?;
+        let row = sqlx::query(
+            r#"
+            SELECT id
+            FROM containers
+            WHERE is_default = true
+            ORDER BY created_at ASC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("查询默认服务器失败")?;
 
-        Ok(ContainerInfo {
-            container_id: container_id.clone(),
-            name: format!("echokit-server-{}", &container_id),
-            host,
-            port,
-            protocol,
-            status: "running".to_string(),
-        })
+        Ok(row.map(|row| row.get("id")))
     }
 
     /// 解析容器端点信息
     ///
-    /// 从数据库查询容器的 host, port, use_tls 信息
-    async fn resolve_container_endpoint(&self, container_id: &str) -> Result<(String, u16, String)> {
+    /// 从数据库查询容器的 host, port, use_tls, status, draining 及是否已被自动停止
+    pub async fn resolve_container_endpoint(&self, container_id: &str) -> Result<ContainerInfo> {
         debug!("解析容器端点: container_id={}", container_id);
 
         // 从数据库查询容器信息
         let row = sqlx::query(
             r#"
-            SELECT host, port, use_tls
+            SELECT host, port, use_tls, status, draining,
+                   (auto_stop_enabled AND auto_stopped_at IS NOT NULL) AS auto_stopped
             FROM containers
             WHERE id = $1
             "#,
@@ -110,6 +105,9 @@ impl DeviceStore {
         let host: String = row.get("host");
         let port: Option<i32> = row.get("port");
         let use_tls: bool = row.get("use_tls");
+        let status: String = row.get("status");
+        let draining: bool = row.get("draining");
+        let auto_stopped: bool = row.get("auto_stopped");
 
         // 如果 port 为 NULL，根据 use_tls 设置默认端口
         let port = port.map(|p| p as u16).unwrap_or(if use_tls { 443 } else { 80 });
@@ -120,55 +118,169 @@ impl DeviceStore {
             container_id, host, port, protocol
         );
 
-        Ok((host, port, protocol))
+        Ok(ContainerInfo {
+            container_id: container_id.to_string(),
+            name: format!("echokit-server-{}", container_id),
+            host,
+            port,
+            protocol,
+            status,
+            draining,
+            auto_stopped,
+        })
     }
 
-    /// 更新设备状态为在线
-    pub async fn mark_device_online(&self, device_id: &str) -> Result<()> {
-        debug!("标记设备在线: device_id={}", device_id);
+    /// 请求后端唤醒已自动停止的容器
+    pub async fn request_container_wake(&self, container_id: &str) -> Result<()> {
+        debug!("请求唤醒容器: container_id={}", container_id);
 
         let now = chrono::Utc::now().timestamp();
 
         sqlx::query(
+            r#"
+            UPDATE containers
+            SET wake_requested_at = COALESCE(wake_requested_at, $2)
+            WHERE id = $1 AND auto_stop_enabled = true AND auto_stopped_at IS NOT NULL
+            "#,
+        )
+        .bind(container_id)
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .context("请求唤醒容器失败")?;
+
+        Ok(())
+    }
+
+    /// 更新设备状态为在线，返回本次会话的连接时间戳
+    ///
+    /// 时间戳严格大于上一次会话的 `last_connected_at`，作为本次会话的标识，
+    /// 离线时据此判断是否已被更新的会话接管（同一秒内的重连也能区分）。
+    pub async fn mark_device_online(
+        &self,
+        device_id: &str,
+        client_ip: &str,
+        user_agent: Option<&str>,
+    ) -> Result<Option<i64>> {
+        debug!("标记设备在线: device_id={}", device_id);
+
+        let now = chrono::Utc::now().timestamp();
+
+        let session = sqlx::query_scalar::<_, i64>(
             r#"
             UPDATE devices
             SET
                 status = 'online',
-                last_connected_at = $2,
+                last_connected_at = GREATEST($2, COALESCE(last_connected_at + 1, $2)),
+                last_ip = $3,
+                last_user_agent = COALESCE($4, last_user_agent),
                 updated_at = $2
             WHERE device_id = $1
+            RETURNING last_connected_at
             "#,
         )
         .bind(device_id)
         .bind(now)
-        .execute(&self.pool)
+        .bind(client_ip)
+        .bind(user_agent)
+        .fetch_optional(&self.pool)
         .await
         .context("更新设备状态失败")?;
 
-        Ok(())
+        Ok(session)
     }
 
     /// 更新设备状态为离线
-    pub async fn mark_device_offline(&self, device_id: &str) -> Result<()> {
-        debug!("标记设备离线: device_id={}", device_id);
+    ///
+    /// 仅当 `last_connected_at` 仍为本次会话的时间戳时才更新，避免旧会话的断开
+    /// 覆盖新会话的在线状态。返回是否实际更新。
+    pub async fn mark_device_offline(&self, device_id: &str, session: i64) -> Result<bool> {
+        debug!("标记设备离线: device_id={}, session={}", device_id, session);
 
         let now = chrono::Utc::now().timestamp();
 
-        sqlx::query(
+        let result = sqlx::query(
             r#"
             UPDATE devices
             SET
                 status = 'offline',
                 updated_at = $2
-            WHERE device_id = $1
+            WHERE device_id = $1 AND last_connected_at = $3
             "#,
         )
         .bind(device_id)
         .bind(now)
+        .bind(session)
         .execute(&self.pool)
         .await
         .context("更新设备状态失败")?;
 
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 记录设备连接，返回连接日志 ID
+    pub async fn record_connection(
+        &self,
+        device_id: &str,
+        container_id: &str,
+        client_ip: &str,
+    ) -> Result<i64> {
+        debug!(
+            "记录设备连接: device_id={}, client_ip={}",
+            device_id, client_ip
+        );
+
+        let now = chrono::Utc::now().timestamp();
+
+        let row = sqlx::query(
+            r#"
+            INSERT INTO device_connections (device_id, container_id, client_ip, connected_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(device_id)
+        .bind(container_id)
+        .bind(client_ip)
+        .bind(now)
+        .fetch_one(&self.pool)
+        .await
+        .context("记录设备连接失败")?;
+
+        Ok(row.get("id"))
+    }
+
+    /// 记录设备断开时间及转发统计（未能建立转发时统计为空）
+    pub async fn record_disconnection(
+        &self,
+        connection_id: i64,
+        stats: Option<&ForwardStats>,
+    ) -> Result<()> {
+        debug!("记录设备断开: connection_id={}", connection_id);
+
+        let now = chrono::Utc::now().timestamp();
+
+        sqlx::query(
+            r#"
+            UPDATE device_connections
+            SET disconnected_at = $2,
+                frames_to_server = $3,
+                frames_to_device = $4,
+                forward_errors = $5,
+                close_reason = $6
+            WHERE id = $1
+            "#,
+        )
+        .bind(connection_id)
+        .bind(now)
+        .bind(stats.map(|s| s.device_to_server.frames() as i64))
+        .bind(stats.map(|s| s.server_to_device.frames() as i64))
+        .bind(stats.map(|s| s.errors() as i64))
+        .bind(stats.map(|s| s.close_reason.as_str()))
+        .execute(&self.pool)
+        .await
+        .context("记录设备断开失败")?;
+
         Ok(())
     }
 
@@ -180,3 +292,136 @@ impl DeviceStore {
             .is_ok()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 连接 DATABASE_URL 指向的开发数据库（需已执行 Backend migrations），未设置时跳过
+    async fn test_store() -> Option<(DeviceStore, PgPool)> {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL 未设置，跳过数据库测试");
+            return None;
+        };
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&url)
+            .await
+            .unwrap();
+        Some((DeviceStore::new(pool.clone()), pool))
+    }
+
+    #[tokio::test]
+    async fn old_session_offline_does_not_clobber_reconnect() {
+        let Some((store, pool)) = test_store().await else {
+            return;
+        };
+        let device_id = format!(
+            "test-reconnect-{}",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap()
+        );
+        sqlx::query(
+            r#"
+            INSERT INTO devices (device_id, name, mac_address, created_at)
+            VALUES ($1, $1, $1, 0)
+            "#,
+        )
+        .bind(&device_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // 设备快速重连：新会话在旧会话断开处理之前上线
+        let old = store
+            .mark_device_online(&device_id, "203.0.113.7", None)
+            .await
+            .unwrap()
+            .unwrap();
+        let new = store
+            .mark_device_online(&device_id, "203.0.113.7", None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(new > old, "同一秒内的会话也必须可区分");
+
+        let old_applied = store.mark_device_offline(&device_id, old).await.unwrap();
+        let status_after_old = store.get_device(&device_id).await.unwrap().unwrap().status;
+        let new_applied = store.mark_device_offline(&device_id, new).await.unwrap();
+        let status_after_new = store.get_device(&device_id).await.unwrap().unwrap().status;
+
+        sqlx::query("DELETE FROM devices WHERE device_id = $1")
+            .bind(&device_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(!old_applied);
+        assert_eq!(status_after_old, DeviceStatus::Online);
+        assert!(new_applied);
+        assert_eq!(status_after_new, DeviceStatus::Offline);
+    }
+
+    #[tokio::test]
+    async fn online_records_last_ip_and_user_agent() {
+        let Some((store, pool)) = test_store().await else {
+            return;
+        };
+        let device_id = format!(
+            "test-last-ip-{}",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap()
+        );
+        sqlx::query(
+            r#"
+            INSERT INTO devices (device_id, name, mac_address, created_at)
+            VALUES ($1, $1, $1, 0)
+            "#,
+        )
+        .bind(&device_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let network_context = || async {
+            sqlx::query_as::<_, (Option<String>, Option<String>)>(
+                "SELECT last_ip, last_user_agent FROM devices WHERE device_id = $1",
+            )
+            .bind(&device_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+        };
+
+        store
+            .mark_device_online(&device_id, "203.0.113.7", Some("echokit-fw/1.4.2"))
+            .await
+            .unwrap();
+        let first = network_context().await;
+        // 未携带 User-Agent 时保留上一次的值
+        store
+            .mark_device_online(&device_id, "198.51.100.20", None)
+            .await
+            .unwrap();
+        let second = network_context().await;
+
+        sqlx::query("DELETE FROM devices WHERE device_id = $1")
+            .bind(&device_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            first,
+            (
+                Some("203.0.113.7".to_string()),
+                Some("echokit-fw/1.4.2".to_string())
+            )
+        );
+        assert_eq!(
+            second,
+            (
+                Some("198.51.100.20".to_string()),
+                Some("echokit-fw/1.4.2".to_string())
+            )
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::hmac;
+
+/// 设备连接令牌校验失败的原因
+#[derive(Debug, thiserror::Error)]
+pub enum TokenError {
+    #[error("Missing device token")]
+    Missing,
+    #[error("Malformed device token")]
+    Malformed,
+    #[error("Device token expired")]
+    Expired,
+    #[error("Invalid device token")]
+    InvalidSignature,
+}
+
+/// 签名使用的设备 ID 形式（小写、去掉分隔符），与 Backend 的签发保持一致
+fn canonical_device_id(device_id: &str) -> String {
+    device_id.replace([':', '-'], "").to_lowercase()
+}
+
+/// 校验 Backend 签发的设备连接令牌（`<过期时间>.<签名>`）
+pub fn verify_device_token(
+    secret: &str,
+    device_id: &str,
+    token: Option<&str>,
+    now: i64,
+) -> Result<(), TokenError> {
+    let token = token.filter(|t| !t.is_empty()).ok_or(TokenError::Missing)?;
+    let (expires_at, signature) = token.split_once('.').ok_or(TokenError::Malformed)?;
+    let expires_at: i64 = expires_at.parse().map_err(|_| TokenError::Malformed)?;
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| TokenError::Malformed)?;
+
+    // 先校验签名，避免伪造的过期时间影响返回的错误类型
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let message = format!("{}.{}", canonical_device_id(device_id), expires_at);
+    hmac::verify(&key, message.as_bytes(), &signature).map_err(|_| TokenError::InvalidSignature)?;
+
+    if expires_at < now {
+        return Err(TokenError::Expired);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 与 Backend `sign_device_token` 的测试向量相同
+    const TOKEN: &str = "1700000000.Ewgy7SIwZ65zkSRrtgL2xPszEFBbQ57c4H5d4xI_xaQ";
+    const EXPIRES_AT: i64 = 1_700_000_000;
+
+    #[test]
+    fn accepts_valid_token() {
+        for device_id in ["98:A3:16:F0:B1:E5", "98a316f0b1e5"] {
+            assert!(
+                verify_device_token("shared-secret", device_id, Some(TOKEN), EXPIRES_AT - 60)
+                    .is_ok()
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_expired_token() {
+        let result =
+            verify_device_token("shared-secret", "98a316f0b1e5", Some(TOKEN), EXPIRES_AT + 1);
+        assert!(matches!(result, Err(TokenError::Expired)));
+    }
+
+    #[test]
+    fn rejects_forged_tokens() {
+        let now = EXPIRES_AT - 60;
+        let verify = |secret: &str, device_id: &str, token: Option<&str>| {
+            verify_device_token(secret, device_id, token, now)
+        };
+
+        // 其他设备、错误密钥、篡改过期时间
+        assert!(matches!(
+            verify("shared-secret", "98a316f0b1e6", Some(TOKEN)),
+            Err(TokenError::InvalidSignature)
+        ));
+        assert!(matches!(
+            verify("other-secret", "98a316f0b1e5", Some(TOKEN)),
+            Err(TokenError::InvalidSignature)
+        ));
+        let extended = TOKEN.replacen("1700000000", "1800000000", 1);
+        assert!(matches!(
+            verify("shared-secret", "98a316f0b1e5", Some(&extended)),
+            Err(TokenError::InvalidSignature)
+        ));
+
+        assert!(matches!(
+            verify("shared-secret", "98a316f0b1e5", None),
+            Err(TokenError::Missing)
+        ));
+        for malformed in ["1700000000", "soon.abc", "1700000000.not base64!"] {
+            assert!(matches!(
+                verify("shared-secret", "98a316f0b1e5", Some(malformed)),
+                Err(TokenError::Malformed)
+            ));
+        }
+    }
+}